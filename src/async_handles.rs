@@ -0,0 +1,532 @@
+//! The machinery shared by the asynchronous adapters for [`ReadHandle`],
+//! [`WriteHandle`], and [`ReadWriteHandle`], for each supported runtime.
+//!
+//! Descriptors which the reactor can poll, such as pipes, sockets, and
+//! terminals, are put in non-blocking mode and registered with the reactor.
+//! Other descriptors, such as regular files, can't be polled, so I/O on them
+//! is performed on the runtime's blocking thread pool instead. So is I/O on
+//! streams which can wait for a thread or child process, such as a piped
+//! thread, whose flush waits for the thread to catch up, or a child process,
+//! which is waited for at the end of its output when exit-status checking is
+//! enabled, so that the executor is never blocked.
+//!
+//! The handles' read and write timeouts are cleared, since they'd block the
+//! executor too; use the runtime's timers instead.
+
+use crate::{AsRawReadWriteFd, HandleKind, ReadHandle, ReadWriteHandle, WriteHandle};
+use std::{
+    cmp,
+    future::Future,
+    io::{self, Read, Write},
+    mem,
+    os::unix::io::{AsFd, AsRawFd, BorrowedFd, RawFd},
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+/// The largest amount of data transferred in a single operation on the
+/// blocking thread pool.
+const MAX_BUF: usize = 16 * 1024;
+
+/// An async runtime, providing a reactor and a blocking thread pool.
+pub(crate) trait Runtime {
+    /// A descriptor registered with the reactor.
+    type Registration;
+
+    /// An operation in progress on the blocking thread pool.
+    type JoinHandle<T>: Future<Output = io::Result<T>> + Unpin;
+
+    /// Register `fd` with the reactor, or return `Ok(None)` if it's not a
+    /// kind of descriptor that can be polled.
+    fn register(fd: Fd, direction: Direction) -> io::Result<Option<Self::Registration>>;
+
+    /// Perform `io`, waiting for the descriptor to become ready in
+    /// `direction` whenever it fails with `WouldBlock`.
+    fn poll_io<T>(
+        registration: &Self::Registration,
+        cx: &mut Context,
+        direction: Direction,
+        io: impl FnMut() -> io::Result<T>,
+    ) -> Poll<io::Result<T>>;
+
+    /// Run `f` on the blocking thread pool.
+    fn spawn_blocking<T, F>(f: F) -> Self::JoinHandle<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static;
+}
+
+/// Which way I/O on a registered descriptor goes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Direction {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+/// A raw file descriptor registered with the reactor. This doesn't own the
+/// descriptor; the handle it's registered for does.
+pub(crate) struct Fd {
+    raw_fd: RawFd,
+    // The file status flags the descriptor had before it was registered.
+    flags: libc::c_int,
+}
+
+impl AsRawFd for Fd {
+    #[inline]
+    fn as_raw_fd(&self) -> RawFd {
+        self.raw_fd
+    }
+}
+
+impl AsFd for Fd {
+    #[inline]
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        // The handle keeps the descriptor open for as long as it's registered.
+        unsafe { BorrowedFd::borrow_raw(self.raw_fd) }
+    }
+}
+
+impl Drop for Fd {
+    fn drop(&mut self) {
+        // Take the descriptor back out of non-blocking mode, since it may be
+        // shared with other processes, or used again as a plain handle.
+        unsafe {
+            libc::fcntl(self.raw_fd, libc::F_SETFL, self.flags);
+        }
+    }
+}
+
+/// Register `raw_fd` with the reactor and put it in non-blocking mode, or
+/// return `Ok(None)` if it's not a kind of descriptor that can be polled.
+fn register<R: Runtime>(
+    raw_fd: RawFd,
+    direction: Direction,
+) -> io::Result<Option<R::Registration>> {
+    let flags = unsafe { libc::fcntl(raw_fd, libc::F_GETFL) };
+    if flags == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    let registration = match R::register(Fd { raw_fd, flags }, direction)? {
+        Some(registration) => registration,
+        None => return Ok(None),
+    };
+    if unsafe { libc::fcntl(raw_fd, libc::F_SETFL, flags | libc::O_NONBLOCK) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(Some(registration))
+}
+
+/// The error reported by writes after the stream has been shut down.
+fn shut_down() -> io::Error {
+    io::Error::new(io::ErrorKind::BrokenPipe, "stream has been shut down")
+}
+
+/// The two ways of performing I/O on a handle.
+pub(crate) enum Inner<H, R: Runtime> {
+    Reactor(Reactor<H, R>),
+    Blocking(Blocking<H, R>),
+}
+
+/// A handle whose descriptors are registered with the reactor.
+pub(crate) struct Reactor<H, R: Runtime> {
+    // The registrations are declared before `handle` so that they're dropped
+    // before the handle closes the descriptors. For a `ReadWriteHandle` with
+    // a single descriptor, `write` is `None` and `read` is used for both.
+    read: Option<R::Registration>,
+    write: Option<R::Registration>,
+    handle: H,
+    // Whether the writing side has been shut down.
+    shut_down: bool,
+}
+
+/// A handle which performs I/O on the blocking thread pool.
+pub(crate) struct Blocking<H, R: Runtime> {
+    state: State<H, R>,
+
+    // Data which has been read but not yet consumed.
+    read_buf: Vec<u8>,
+    read_pos: usize,
+    read_ready: bool,
+    read_err: Option<io::Error>,
+
+    // Whether the most recent operation was a successful flush, and an error
+    // from a write, flush, or close which hasn't been reported yet.
+    flushed: bool,
+    write_err: Option<io::Error>,
+
+    // Whether the handle has been closed.
+    closed: bool,
+}
+
+enum State<H, R: Runtime> {
+    Idle(Option<H>),
+    Busy(R::JoinHandle<(Operation, Option<H>)>),
+}
+
+/// The result of an operation on the blocking thread pool.
+enum Operation {
+    Read(io::Result<Vec<u8>>),
+    Write(io::Result<()>),
+    Flush(io::Result<()>),
+    Close(io::Result<()>),
+}
+
+impl<R: Runtime> Inner<ReadHandle, R> {
+    pub(crate) fn new(mut handle: ReadHandle) -> io::Result<Self> {
+        handle.set_read_timeout(None)?;
+        // Reaching the end of a piped thread's or a child process' output
+        // waits for the thread or process.
+        if !matches!(handle.kind(), HandleKind::PipedThread | HandleKind::Child) {
+            if let Some(read) = register::<R>(handle.as_raw_fd(), Direction::Read)? {
+                return Ok(Self::Reactor(Reactor::new(Some(read), None, handle)));
+            }
+        }
+        Ok(Self::Blocking(Blocking::new(handle)))
+    }
+}
+
+impl<R: Runtime> Inner<WriteHandle, R> {
+    pub(crate) fn new(mut handle: WriteHandle) -> io::Result<Self> {
+        handle.set_write_timeout(None)?;
+        // Flushing a piped thread waits for the thread to catch up.
+        if handle.kind() != HandleKind::PipedThread {
+            if let Some(write) = register::<R>(handle.as_raw_fd(), Direction::Write)? {
+                return Ok(Self::Reactor(Reactor::new(None, Some(write), handle)));
+            }
+        }
+        Ok(Self::Blocking(Blocking::new(handle)))
+    }
+
+    /// Close the handle, so that the other end sees the end of the stream,
+    /// and report any error, such as from a piped thread, or an unsuccessful
+    /// exit of a child process with exit-status checking enabled.
+    pub(crate) fn poll_close(&mut self, cx: &mut Context) -> Poll<io::Result<()>> {
+        // Closing can wait for a thread or child process, so do it on the
+        // blocking thread pool.
+        if let Self::Reactor(_) = self {
+            if let Self::Reactor(reactor) = mem::replace(self, Self::Blocking(Blocking::lost())) {
+                *self = Self::Blocking(Blocking::new(reactor.into_handle()));
+            }
+        }
+        match self {
+            Self::Blocking(blocking) => blocking.poll_close(cx, WriteHandle::close),
+            Self::Reactor(_) => unreachable!(),
+        }
+    }
+}
+
+impl<R: Runtime> Inner<ReadWriteHandle, R> {
+    pub(crate) fn new(mut handle: ReadWriteHandle) -> io::Result<Self> {
+        handle.set_read_timeout(None)?;
+        handle.set_write_timeout(None)?;
+        let raw_read_fd = handle.as_raw_read_fd();
+        let raw_write_fd = handle.as_raw_write_fd();
+        if raw_read_fd == raw_write_fd {
+            if let Some(read) = register::<R>(raw_read_fd, Direction::ReadWrite)? {
+                return Ok(Self::Reactor(Reactor::new(Some(read), None, handle)));
+            }
+        } else if let (Some(read), Some(write)) = (
+            register::<R>(raw_read_fd, Direction::Read)?,
+            register::<R>(raw_write_fd, Direction::Write)?,
+        ) {
+            return Ok(Self::Reactor(Reactor::new(Some(read), Some(write), handle)));
+        }
+        Ok(Self::Blocking(Blocking::new(handle)))
+    }
+
+    /// Shut down the writing side of the handle, so that the other end sees
+    /// the end of the stream, while the reading side stays open.
+    pub(crate) fn poll_shutdown_write(&mut self, cx: &mut Context) -> Poll<io::Result<()>> {
+        ready!(self.poll_flush(cx))?;
+        Poll::Ready(match self {
+            Self::Reactor(reactor) => reactor.shutdown_write(),
+            Self::Blocking(blocking) => blocking.handle_mut()?.shutdown_write(),
+        })
+    }
+}
+
+impl<H, R: Runtime> Inner<H, R> {
+    /// Return the handle, unless an operation on it is in progress, or it's
+    /// been closed or lost.
+    pub(crate) fn handle(&self) -> Option<&H> {
+        match self {
+            Self::Reactor(reactor) => Some(&reactor.handle),
+            Self::Blocking(Blocking {
+                state: State::Idle(handle),
+                ..
+            }) => handle.as_ref(),
+            Self::Blocking(_) => None,
+        }
+    }
+}
+
+impl<H: Read + Send + 'static, R: Runtime> Inner<H, R> {
+    pub(crate) fn poll_read(
+        &mut self,
+        cx: &mut Context,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        match self {
+            Self::Reactor(reactor) => reactor.poll_read(cx, buf),
+            Self::Blocking(blocking) => blocking.poll_read(cx, buf),
+        }
+    }
+}
+
+impl<H: Write + Send + 'static, R: Runtime> Inner<H, R> {
+    pub(crate) fn poll_write(&mut self, cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+        match self {
+            Self::Reactor(reactor) => reactor.poll_write(cx, buf),
+            Self::Blocking(blocking) => blocking.poll_write(cx, buf),
+        }
+    }
+
+    pub(crate) fn poll_flush(&mut self, cx: &mut Context) -> Poll<io::Result<()>> {
+        match self {
+            Self::Reactor(reactor) => reactor.poll_flush(),
+            Self::Blocking(blocking) => blocking.poll_flush(cx),
+        }
+    }
+}
+
+impl<H, R: Runtime> Reactor<H, R> {
+    fn new(read: Option<R::Registration>, write: Option<R::Registration>, handle: H) -> Self {
+        Self {
+            read,
+            write,
+            handle,
+            shut_down: false,
+        }
+    }
+
+    /// Deregister the descriptors, taking them out of non-blocking mode, and
+    /// return the handle.
+    fn into_handle(self) -> H {
+        let Self {
+            read,
+            write,
+            handle,
+            ..
+        } = self;
+        drop((read, write));
+        handle
+    }
+}
+
+impl<H: Read, R: Runtime> Reactor<H, R> {
+    fn poll_read(&mut self, cx: &mut Context, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        let Self { read, handle, .. } = self;
+        R::poll_io(read.as_ref().unwrap(), cx, Direction::Read, || {
+            handle.read(buf)
+        })
+    }
+}
+
+impl<H: Write, R: Runtime> Reactor<H, R> {
+    fn poll_write(&mut self, cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+        if self.shut_down {
+            return Poll::Ready(Err(shut_down()));
+        }
+        let Self {
+            read,
+            write,
+            handle,
+            ..
+        } = self;
+        let registration = write.as_ref().or(read.as_ref()).unwrap();
+        R::poll_io(registration, cx, Direction::Write, || handle.write(buf))
+    }
+
+    fn poll_flush(&mut self) -> Poll<io::Result<()>> {
+        Poll::Ready(self.handle.flush())
+    }
+}
+
+impl<R: Runtime> Reactor<ReadWriteHandle, R> {
+    fn shutdown_write(&mut self) -> io::Result<()> {
+        if self.shut_down {
+            return Ok(());
+        }
+        // Shutting down a pipe replaces the write descriptor, so deregister
+        // it first, and register it again if the shutdown fails.
+        let registered = self.write.take().is_some();
+        match self.handle.shutdown_write() {
+            Ok(()) => {
+                self.shut_down = true;
+                Ok(())
+            }
+            Err(e) => {
+                if registered {
+                    let raw_write_fd = self.handle.as_raw_write_fd();
+                    self.write = register::<R>(raw_write_fd, Direction::Write)?;
+                }
+                Err(e)
+            }
+        }
+    }
+}
+
+impl<H, R: Runtime> Blocking<H, R> {
+    fn new(handle: H) -> Self {
+        Self::with_state(State::Idle(Some(handle)))
+    }
+
+    /// A placeholder with no handle.
+    fn lost() -> Self {
+        Self::with_state(State::Idle(None))
+    }
+
+    fn with_state(state: State<H, R>) -> Self {
+        Self {
+            state,
+            read_buf: Vec::new(),
+            read_pos: 0,
+            read_ready: false,
+            read_err: None,
+            flushed: false,
+            write_err: None,
+            closed: false,
+        }
+    }
+
+    /// Wait for the operation in progress, if any, to complete.
+    fn poll_idle(&mut self, cx: &mut Context) -> Poll<()> {
+        if let State::Busy(join_handle) = &mut self.state {
+            let result = ready!(Pin::new(join_handle).poll(cx));
+            // If the operation panicked, or was cancelled, the handle is
+            // lost, and `start` reports an error from then on.
+            self.state = State::Idle(None);
+            if let Ok((operation, handle)) = result {
+                self.state = State::Idle(handle);
+                match operation {
+                    Operation::Read(Ok(data)) => {
+                        self.read_buf = data;
+                        self.read_pos = 0;
+                        self.read_ready = true;
+                    }
+                    Operation::Read(Err(e)) => self.read_err = Some(e),
+                    Operation::Write(Ok(())) => {}
+                    Operation::Flush(Ok(())) => self.flushed = true,
+                    Operation::Write(Err(e)) | Operation::Flush(Err(e)) => self.write_err = Some(e),
+                    Operation::Close(result) => {
+                        self.closed = true;
+                        self.write_err = result.err();
+                    }
+                }
+            }
+        }
+        Poll::Ready(())
+    }
+
+    /// Return the handle. This must only be called when no operation is in
+    /// progress.
+    fn handle_mut(&mut self) -> io::Result<&mut H> {
+        let closed = self.closed;
+        match &mut self.state {
+            State::Idle(Some(handle)) => Ok(handle),
+            State::Idle(None) if closed => Err(shut_down()),
+            State::Idle(None) => Err(io::Error::other(
+                "handle lost due to a panic on the blocking thread pool",
+            )),
+            State::Busy(_) => unreachable!("operation in progress"),
+        }
+    }
+
+    /// Start an operation on the blocking thread pool, which hands the
+    /// handle back unless it consumes it. This must only be called when no
+    /// operation is in progress.
+    fn start<F>(&mut self, f: F) -> io::Result<()>
+    where
+        H: Send + 'static,
+        F: FnOnce(H) -> (Operation, Option<H>) + Send + 'static,
+    {
+        self.handle_mut()?;
+        let handle = match mem::replace(&mut self.state, State::Idle(None)) {
+            State::Idle(handle) => handle.unwrap(),
+            State::Busy(_) => unreachable!(),
+        };
+        self.state = State::Busy(R::spawn_blocking(move || f(handle)));
+        Ok(())
+    }
+
+    /// Close the handle with `close`, once, and report its result.
+    fn poll_close(
+        &mut self,
+        cx: &mut Context,
+        close: fn(H) -> io::Result<()>,
+    ) -> Poll<io::Result<()>>
+    where
+        H: Send + 'static,
+    {
+        loop {
+            ready!(self.poll_idle(cx));
+            if let Some(e) = self.write_err.take() {
+                return Poll::Ready(Err(e));
+            }
+            if self.closed {
+                return Poll::Ready(Ok(()));
+            }
+            self.start(move |handle| (Operation::Close(close(handle)), None))?;
+        }
+    }
+}
+
+impl<H: Read + Send + 'static, R: Runtime> Blocking<H, R> {
+    fn poll_read(&mut self, cx: &mut Context, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        loop {
+            ready!(self.poll_idle(cx));
+            if let Some(e) = self.read_err.take() {
+                return Poll::Ready(Err(e));
+            }
+            if self.read_ready {
+                let rest = &self.read_buf[self.read_pos..];
+                let len = cmp::min(buf.len(), rest.len());
+                buf[..len].copy_from_slice(&rest[..len]);
+                self.read_pos += len;
+                self.read_ready = self.read_pos < self.read_buf.len();
+                return Poll::Ready(Ok(len));
+            }
+            if buf.is_empty() {
+                return Poll::Ready(Ok(0));
+            }
+            let len = cmp::min(buf.len(), MAX_BUF);
+            let mut data = mem::take(&mut self.read_buf);
+            self.start(move |mut handle| {
+                data.resize(len, 0);
+                let result = handle.read(&mut data).map(|size| {
+                    data.truncate(size);
+                    data
+                });
+                (Operation::Read(result), Some(handle))
+            })?;
+        }
+    }
+}
+
+impl<H: Write + Send + 'static, R: Runtime> Blocking<H, R> {
+    fn poll_write(&mut self, cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+        ready!(self.poll_idle(cx));
+        if let Some(e) = self.write_err.take() {
+            return Poll::Ready(Err(e));
+        }
+        let data = buf[..cmp::min(buf.len(), MAX_BUF)].to_vec();
+        let len = data.len();
+        self.flushed = false;
+        self.start(move |mut handle| (Operation::Write(handle.write_all(&data)), Some(handle)))?;
+        Poll::Ready(Ok(len))
+    }
+
+    fn poll_flush(&mut self, cx: &mut Context) -> Poll<io::Result<()>> {
+        loop {
+            ready!(self.poll_idle(cx));
+            if let Some(e) = self.write_err.take() {
+                return Poll::Ready(Err(e));
+            }
+            if mem::take(&mut self.flushed) {
+                return Poll::Ready(Ok(()));
+            }
+            self.start(|mut handle| (Operation::Flush(handle.flush()), Some(handle)))?;
+        }
+    }
+}
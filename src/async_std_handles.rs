@@ -0,0 +1,293 @@
+//! Asynchronous adapters for [`ReadHandle`], [`WriteHandle`], and
+//! [`ReadWriteHandle`], implementing async-std's [`Read`] and [`Write`].
+//!
+//! [`Read`]: async_std::io::Read
+//! [`Write`]: async_std::io::Write
+
+use crate::{
+    async_handles::{Direction, Fd, Inner, Runtime},
+    AsRawReadWriteFd, ReadHandle, ReadWriteHandle, WriteHandle,
+};
+use async_io::Async;
+use async_std::{
+    io::{Read as AsyncRead, Write as AsyncWrite},
+    task::{spawn_blocking, JoinHandle},
+};
+use std::{
+    fmt::{self, Debug},
+    future::Future,
+    io,
+    os::unix::io::{AsRawFd, RawFd},
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+/// An asynchronous adapter for [`ReadHandle`], implementing async-std's
+/// [`Read`].
+///
+/// If the underlying descriptor can be polled, it's put in non-blocking mode
+/// for as long as this adapter is live. Otherwise, and for piped threads and
+/// child processes, which can wait for the thread or process at the end of
+/// the stream, reads are performed on async-std's blocking thread pool. The
+/// handle's read timeout is cleared; use async-std's timers instead.
+///
+/// [`Read`]: async_std::io::Read
+pub struct AsyncStdReadHandle {
+    raw_fd: RawFd,
+    inner: Inner<ReadHandle, AsyncStd>,
+}
+
+/// An asynchronous adapter for [`WriteHandle`], implementing async-std's
+/// [`Write`].
+///
+/// If the underlying descriptor can be polled, it's put in non-blocking mode
+/// for as long as this adapter is live. Otherwise, and for piped threads,
+/// whose flush waits for the thread, writes are performed on async-std's
+/// blocking thread pool. In that case, as with [`async_std::fs::File`], a
+/// write is reported as complete once it has been handed off to the pool,
+/// and any error it encounters is reported by the next write or flush. The
+/// handle's write timeout is cleared; use async-std's timers instead.
+///
+/// [`Write::poll_close`] closes the handle, on the blocking thread pool, so
+/// that the other end sees the end of the stream, and reports any error
+/// [`WriteHandle::close`] does.
+///
+/// [`Write`]: async_std::io::Write
+/// [`Write::poll_close`]: async_std::io::Write::poll_close
+pub struct AsyncStdWriteHandle {
+    raw_fd: RawFd,
+    inner: Inner<WriteHandle, AsyncStd>,
+}
+
+/// An asynchronous adapter for [`ReadWriteHandle`], implementing async-std's
+/// [`Read`] and [`Write`].
+///
+/// If the underlying descriptors can both be polled, they're put in
+/// non-blocking mode for as long as this adapter is live. Otherwise, reads
+/// and writes are performed on async-std's blocking thread pool, one at a
+/// time, so a pending read delays any subsequent write until it completes.
+/// The handle's timeouts are cleared; use async-std's timers instead.
+///
+/// [`Write::poll_close`] shuts down the writing side with
+/// [`ReadWriteHandle::shutdown_write`], so that the other end sees the end of
+/// the stream, while reading continues.
+///
+/// [`Read`]: async_std::io::Read
+/// [`Write`]: async_std::io::Write
+/// [`Write::poll_close`]: async_std::io::Write::poll_close
+pub struct AsyncStdReadWriteHandle {
+    raw_read_fd: RawFd,
+    raw_write_fd: RawFd,
+    inner: Inner<ReadWriteHandle, AsyncStd>,
+}
+
+impl AsyncStdReadHandle {
+    /// Create a new `AsyncStdReadHandle` for the given `ReadHandle`.
+    pub fn new(handle: ReadHandle) -> io::Result<Self> {
+        let raw_fd = handle.as_raw_fd();
+        let inner = Inner::<ReadHandle, AsyncStd>::new(handle)?;
+        Ok(Self { raw_fd, inner })
+    }
+}
+
+impl AsyncStdWriteHandle {
+    /// Create a new `AsyncStdWriteHandle` for the given `WriteHandle`.
+    pub fn new(handle: WriteHandle) -> io::Result<Self> {
+        let raw_fd = handle.as_raw_fd();
+        let inner = Inner::<WriteHandle, AsyncStd>::new(handle)?;
+        Ok(Self { raw_fd, inner })
+    }
+}
+
+impl AsyncStdReadWriteHandle {
+    /// Create a new `AsyncStdReadWriteHandle` for the given
+    /// `ReadWriteHandle`.
+    pub fn new(handle: ReadWriteHandle) -> io::Result<Self> {
+        let raw_read_fd = handle.as_raw_read_fd();
+        let raw_write_fd = handle.as_raw_write_fd();
+        let inner = Inner::<ReadWriteHandle, AsyncStd>::new(handle)?;
+        Ok(Self {
+            raw_read_fd,
+            raw_write_fd,
+            inner,
+        })
+    }
+}
+
+/// The async-std runtime, whose reactor is async-io's.
+pub(crate) enum AsyncStd {}
+
+impl Runtime for AsyncStd {
+    type Registration = Async<Fd>;
+    type JoinHandle<T> = Join<T>;
+
+    fn register(fd: Fd, _direction: Direction) -> io::Result<Option<Async<Fd>>> {
+        // The descriptor is put in non-blocking mode once it's registered.
+        match Async::new_nonblocking(fd) {
+            Ok(async_fd) => Ok(Some(async_fd)),
+            // epoll fails with `EPERM` for descriptors which don't support
+            // polling, such as regular files.
+            Err(e) if e.raw_os_error() == Some(libc::EPERM) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn poll_io<T>(
+        async_fd: &Async<Fd>,
+        cx: &mut Context,
+        direction: Direction,
+        mut io: impl FnMut() -> io::Result<T>,
+    ) -> Poll<io::Result<T>> {
+        loop {
+            match io() {
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => ready!(match direction {
+                    Direction::Read => async_fd.poll_readable(cx),
+                    Direction::Write | Direction::ReadWrite => async_fd.poll_writable(cx),
+                })?,
+                result => return Poll::Ready(result),
+            }
+        }
+    }
+
+    #[inline]
+    fn spawn_blocking<T, F>(f: F) -> Join<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        Join(spawn_blocking(f))
+    }
+}
+
+/// An operation on async-std's blocking thread pool.
+pub(crate) struct Join<T>(JoinHandle<T>);
+
+impl<T> Future for Join<T> {
+    type Output = io::Result<T>;
+
+    #[inline]
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<T>> {
+        Pin::new(&mut self.0).poll(cx).map(Ok)
+    }
+}
+
+impl AsyncRead for AsyncStdReadHandle {
+    #[inline]
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        self.get_mut().inner.poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for AsyncStdWriteHandle {
+    #[inline]
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+        self.get_mut().inner.poll_write(cx, buf)
+    }
+
+    #[inline]
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        self.get_mut().inner.poll_flush(cx)
+    }
+
+    #[inline]
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        self.get_mut().inner.poll_close(cx)
+    }
+}
+
+impl AsyncRead for AsyncStdReadWriteHandle {
+    #[inline]
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        self.get_mut().inner.poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for AsyncStdReadWriteHandle {
+    #[inline]
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+        self.get_mut().inner.poll_write(cx, buf)
+    }
+
+    #[inline]
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        self.get_mut().inner.poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.inner.poll_shutdown_write(cx))?;
+        // Shutting down a pipe replaces the write descriptor.
+        if let Some(handle) = this.inner.handle() {
+            this.raw_write_fd = handle.as_raw_write_fd();
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsRawFd for AsyncStdReadHandle {
+    #[inline]
+    fn as_raw_fd(&self) -> RawFd {
+        self.raw_fd
+    }
+}
+
+impl AsRawFd for AsyncStdWriteHandle {
+    #[inline]
+    fn as_raw_fd(&self) -> RawFd {
+        self.raw_fd
+    }
+}
+
+impl AsRawReadWriteFd for AsyncStdReadWriteHandle {
+    #[inline]
+    fn as_raw_read_fd(&self) -> RawFd {
+        self.raw_read_fd
+    }
+
+    #[inline]
+    fn as_raw_write_fd(&self) -> RawFd {
+        self.raw_write_fd
+    }
+}
+
+impl Debug for AsyncStdReadHandle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut b = f.debug_struct("AsyncStdReadHandle");
+
+        // As with `ReadHandle`, just print the fd number.
+        b.field("raw_fd", &self.as_raw_fd());
+
+        b.finish()
+    }
+}
+
+impl Debug for AsyncStdWriteHandle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut b = f.debug_struct("AsyncStdWriteHandle");
+
+        // As with `WriteHandle`, just print the fd number.
+        b.field("raw_fd", &self.as_raw_fd());
+
+        b.finish()
+    }
+}
+
+impl Debug for AsyncStdReadWriteHandle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut b = f.debug_struct("AsyncStdReadWriteHandle");
+
+        // As with `ReadWriteHandle`, just print the fd numbers.
+        b.field("raw_read_fd", &self.as_raw_read_fd());
+        b.field("raw_write_fd", &self.as_raw_write_fd());
+
+        b.finish()
+    }
+}
@@ -1,7 +1,9 @@
 //! This file is derived from Rust's library/std/src/io/buffered at revision
 //! f7801d6c7cc19ab22bdebcc8efa894a564c53469.
 
-use super::{buf_reader_writer::BufReaderWriterBackend, BufReaderLineWriterShim, IntoInnerError};
+use super::{
+    buf_reader_writer::BufReaderWriterBackend, BufReaderLineWriterShim, FlushPolicy, IntoInnerError,
+};
 #[cfg(read_initializer)]
 use std::io::Initializer;
 use std::{
@@ -183,6 +185,33 @@ impl<RW: Read + Write> BufReaderLineWriter<RW> {
         self.inner.get_mut()
     }
 
+    /// Returns the policy for flushing the writer buffer on behalf of reads.
+    ///
+    /// The default is [`FlushPolicy::Always`].
+    #[inline]
+    pub fn flush_policy(&self) -> FlushPolicy {
+        self.inner.inner.flush_policy()
+    }
+
+    /// Sets the policy for flushing the writer buffer on behalf of reads.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use io_handles::{BufReaderLineWriter, FlushPolicy};
+    /// use std::net::TcpStream;
+    ///
+    /// let stream = TcpStream::connect("127.0.0.1:34254").unwrap();
+    /// let mut stream = BufReaderLineWriter::new(stream);
+    ///
+    /// // Leave partial lines buffered, even when waiting for input.
+    /// stream.set_flush_policy(FlushPolicy::Never);
+    /// ```
+    #[inline]
+    pub fn set_flush_policy(&mut self, flush_policy: FlushPolicy) {
+        self.inner.inner.set_flush_policy(flush_policy)
+    }
+
     /// Unwraps this `BufReaderLineWriter`, returning the underlying writer.
     ///
     /// The internal buffer is written out before returning the writer.
@@ -324,17 +353,11 @@ impl<RW: Read + Write> Write for BufReaderLineWriterBackend<RW> {
 impl<RW: Read + Write> Read for BufReaderLineWriter<RW> {
     #[inline]
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        // Flush the output buffer before reading.
-        self.inner.flush()?;
-
         self.inner.read(buf)
     }
 
     #[inline]
     fn read_vectored(&mut self, bufs: &mut [IoSliceMut<'_>]) -> io::Result<usize> {
-        // Flush the output buffer before reading.
-        self.inner.flush()?;
-
         self.inner.read_vectored(bufs)
     }
 
@@ -389,16 +412,14 @@ impl<RW: Read + Write> BufRead for BufReaderLineWriter<RW> {
 
     #[inline]
     fn read_until(&mut self, byte: u8, buf: &mut Vec<u8>) -> io::Result<usize> {
-        // Flush the output buffer before reading.
-        self.flush()?;
+        self.inner.inner.flush_before_line_read()?;
 
         self.inner.read_until(byte, buf)
     }
 
     #[inline]
     fn read_line(&mut self, buf: &mut String) -> io::Result<usize> {
-        // Flush the output buffer before reading.
-        self.flush()?;
+        self.inner.inner.flush_before_line_read()?;
 
         let t = self.inner.read_line(buf)?;
 
@@ -1,7 +1,7 @@
 //! This file is derived from Rust's library/std/src/io/buffered at revision
 //! f7801d6c7cc19ab22bdebcc8efa894a564c53469.
 
-use super::{IntoInnerError, DEFAULT_BUF_SIZE};
+use super::{FlushPolicy, IntoInnerError, DEFAULT_BUF_SIZE};
 #[cfg(read_initializer)]
 use std::io::Initializer;
 use std::{
@@ -116,6 +116,8 @@ pub(crate) struct BufReaderWriterBackend<RW: Read + Write> {
     reader_buf: Box<[u8]>,
     pos: usize,
     cap: usize,
+
+    flush_policy: FlushPolicy,
 }
 
 impl<RW: Read + Write> BufReaderWriter<RW> {
@@ -282,6 +284,32 @@ impl<RW: Read + Write> BufReaderWriter<RW> {
         self.inner.reader_capacity()
     }
 
+    /// Returns the policy for flushing the writer buffer on behalf of reads.
+    ///
+    /// The default is [`FlushPolicy::Always`].
+    #[inline]
+    pub fn flush_policy(&self) -> FlushPolicy {
+        self.inner.flush_policy()
+    }
+
+    /// Sets the policy for flushing the writer buffer on behalf of reads.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use io_handles::{BufReaderWriter, FlushPolicy};
+    /// use std::net::TcpStream;
+    ///
+    /// let mut stream = BufReaderWriter::new(TcpStream::connect("127.0.0.1:34254").unwrap());
+    ///
+    /// // Only send pending output when waiting for a line of input.
+    /// stream.set_flush_policy(FlushPolicy::LineReads);
+    /// ```
+    #[inline]
+    pub fn set_flush_policy(&mut self, flush_policy: FlushPolicy) {
+        self.inner.set_flush_policy(flush_policy)
+    }
+
     /// Unwraps this `BufReaderWriter<RW>`, returning the underlying reader/writer.
     ///
     /// The buffer is written out before returning the reader/writer.
@@ -332,6 +360,7 @@ impl<RW: Read + Write> BufReaderWriterBackend<RW> {
             reader_buf: buffer.into_boxed_slice(),
             pos: 0,
             cap: 0,
+            flush_policy: FlushPolicy::default(),
         }
     }
 
@@ -440,6 +469,35 @@ impl<RW: Read + Write> BufReaderWriterBackend<RW> {
         self.reader_buf.len()
     }
 
+    #[inline]
+    pub fn flush_policy(&self) -> FlushPolicy {
+        self.flush_policy
+    }
+
+    #[inline]
+    pub fn set_flush_policy(&mut self, flush_policy: FlushPolicy) {
+        self.flush_policy = flush_policy;
+    }
+
+    /// Flush the writer half, if the policy calls for it, before reading
+    /// from the inner reader.
+    fn flush_before_inner_read(&mut self) -> io::Result<()> {
+        match self.flush_policy {
+            FlushPolicy::Always => self.flush(),
+            FlushPolicy::LineReads | FlushPolicy::Never => Ok(()),
+        }
+    }
+
+    /// Flush the writer half, if the policy calls for it, before a
+    /// `read_until` or `read_line`. Under `FlushPolicy::Always`, the flush is
+    /// instead done if and when the read reaches the inner reader.
+    pub(super) fn flush_before_line_read(&mut self) -> io::Result<()> {
+        match self.flush_policy {
+            FlushPolicy::LineReads => self.flush(),
+            FlushPolicy::Always | FlushPolicy::Never => Ok(()),
+        }
+    }
+
     pub fn into_inner(mut self) -> Result<RW, IntoInnerError<Self>> {
         match self.flush_buf() {
             Err(e) => Err(IntoInnerError::new(self, e)),
@@ -553,17 +611,11 @@ impl<RW: Read + Write> Write for BufReaderWriterBackend<RW> {
 impl<RW: Read + Write> Read for BufReaderWriter<RW> {
     #[inline]
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        // Flush the writer half of this `BufReaderWriter` before reading.
-        self.inner.flush()?;
-
         self.inner.read(buf)
     }
 
     #[inline]
     fn read_vectored(&mut self, bufs: &mut [IoSliceMut<'_>]) -> io::Result<usize> {
-        // Flush the writer half of this `BufReaderWriter` before reading.
-        self.inner.flush()?;
-
         self.inner.read_vectored(bufs)
     }
 
@@ -585,6 +637,7 @@ impl<RW: Read + Write> Read for BufReaderWriterBackend<RW> {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
         if self.pos == self.cap && buf.len() >= self.reader_buf.len() {
             self.discard_reader_buffer();
+            self.flush_before_inner_read()?;
             return self.inner.as_mut().unwrap().read(buf);
         }
         let size = {
@@ -599,6 +652,7 @@ impl<RW: Read + Write> Read for BufReaderWriterBackend<RW> {
         let total_len = bufs.iter().map(|b| b.len()).sum::<usize>();
         if self.pos == self.cap && total_len >= self.reader_buf.len() {
             self.discard_reader_buffer();
+            self.flush_before_inner_read()?;
             return self.inner.as_mut().unwrap().read_vectored(bufs);
         }
         let size = {
@@ -634,16 +688,14 @@ impl<RW: Read + Write> BufRead for BufReaderWriter<RW> {
 
     #[inline]
     fn read_until(&mut self, byte: u8, buf: &mut Vec<u8>) -> io::Result<usize> {
-        // Flush the writer half of this `BufReaderWriter` before reading.
-        self.inner.flush()?;
+        self.inner.flush_before_line_read()?;
 
         self.inner.read_until(byte, buf)
     }
 
     #[inline]
     fn read_line(&mut self, buf: &mut String) -> io::Result<usize> {
-        // Flush the writer half of this `BufReaderWriter` before reading.
-        self.inner.flush()?;
+        self.inner.flush_before_line_read()?;
 
         self.inner.read_line(buf)
     }
@@ -658,6 +710,7 @@ impl<RW: Read + Write> BufRead for BufReaderWriterBackend<RW> {
         // to tell the compiler that the pos..cap slice is always valid.
         if self.pos >= self.cap {
             debug_assert!(self.pos == self.cap);
+            self.flush_before_inner_read()?;
             self.cap = self.inner.as_mut().unwrap().read(&mut self.reader_buf)?;
             self.pos = 0;
         }
@@ -16,6 +16,27 @@ pub use buf_reader_writer::BufReaderWriter;
 /// The value from `library/std/src/sys_common/io.rs`.
 pub(super) const DEFAULT_BUF_SIZE: usize = 8 * 1024;
 
+/// When [`BufReaderWriter`] and [`BufReaderLineWriter`] flush their writer
+/// buffers on behalf of reads.
+///
+/// Interactive streams typically need pending output, such as a prompt, to
+/// be sent before blocking on a reply. Other streams may prefer to keep
+/// their output batched for as long as possible.
+///
+/// [`BufReaderWriter`]: crate::BufReaderWriter
+/// [`BufReaderLineWriter`]: crate::BufReaderLineWriter
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum FlushPolicy {
+    /// Flush before any read that needs to read from the inner stream. Reads
+    /// satisfied entirely from the reader buffer don't flush.
+    #[default]
+    Always,
+    /// Flush only before `read_until` and `read_line`.
+    LineReads,
+    /// Never flush on behalf of reads.
+    Never,
+}
+
 /// An error returned by [`BufWriter::into_inner`] which combines an error that
 /// happened while writing out the buffer, and the buffered writer object
 /// which may be used to recover from the condition.
@@ -0,0 +1,401 @@
+//! Child processes held as resources by command-backed streams.
+
+#[cfg(unix)]
+use crate::piped_thread::JoinGuard;
+use os_pipe::{pipe, PipeReader};
+#[cfg(windows)]
+use std::os::windows::io::{FromRawHandle, IntoRawHandle, OwnedHandle};
+#[cfg(unix)]
+use std::{
+    fmt,
+    os::unix::io::{FromRawFd, IntoRawFd, OwnedFd},
+    thread,
+};
+use std::{
+    io,
+    process::{Child, ChildStderr, ChildStdin, ChildStdout, Command, ExitStatus, Stdio},
+};
+
+/// Where the standard error of a child process spawned for a command-backed
+/// stream goes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StderrMode {
+    /// Inherit our standard error.
+    Inherit,
+    /// Discard it.
+    Null,
+    /// Send it to the same place as the child's standard output, like `2>&1`
+    /// in a shell. When the child's standard output is discarded, this
+    /// discards standard error too.
+    Merge,
+    /// Capture it in a pipe, which can be read from the stream returned by
+    /// the command-backed stream's `take_stderr`.
+    ///
+    /// The child may block if it writes more than the pipe can hold while
+    /// nothing reads from it.
+    Capture,
+}
+
+/// What a stream converted into a [`Stdio`] by its `try_into_stdio` leaves
+/// behind, which the child process using the `Stdio` depends on.
+///
+/// For a piped thread, this holds the thread, which keeps feeding the child
+/// or consuming its output. For a stream backed by a child process, this
+/// holds that process. Otherwise, this holds nothing.
+///
+/// Use [`close`] to wait for what it holds and observe errors. Dropping it
+/// instead waits for a piped thread, ignoring errors, and leaves a child
+/// process unwaited-for.
+///
+/// [`close`]: Self::close
+#[cfg(unix)]
+pub struct StdioGuard(Option<StdioResource>);
+
+#[cfg(unix)]
+enum StdioResource {
+    /// A piped thread writing to the pipe the child reads from.
+    ReadThread(JoinGuard),
+    /// A piped thread reading from the pipe the child writes to.
+    WriteThread(JoinGuard),
+    Child(ChildProcess),
+}
+
+#[cfg(unix)]
+impl StdioGuard {
+    #[inline]
+    pub(crate) fn empty() -> Self {
+        Self(None)
+    }
+
+    #[inline]
+    pub(crate) fn read_thread(join_guard: JoinGuard) -> Self {
+        Self(Some(StdioResource::ReadThread(join_guard)))
+    }
+
+    #[inline]
+    pub(crate) fn write_thread(join_guard: JoinGuard) -> Self {
+        Self(Some(StdioResource::WriteThread(join_guard)))
+    }
+
+    #[inline]
+    pub(crate) fn child(child: ChildProcess) -> Self {
+        Self(Some(StdioResource::Child(child)))
+    }
+
+    /// Let what this holds carry on without us, discarding its errors. A
+    /// piped thread is detached, and a child process is waited for on a
+    /// background thread, so that it doesn't linger as a zombie once it
+    /// exits.
+    pub(crate) fn detach(self) {
+        match self.0 {
+            Some(StdioResource::ReadThread(mut join_guard))
+            | Some(StdioResource::WriteThread(mut join_guard)) => join_guard.detach(),
+            Some(StdioResource::Child(mut child)) => {
+                // If the thread can't be spawned, the child is left
+                // unwaited-for, as `Child`'s own `Drop` would do.
+                let _ = thread::Builder::new()
+                    .name("reaper for child process".to_owned())
+                    .spawn(move || child.wait_all());
+            }
+            None => (),
+        }
+    }
+
+    /// Wait for the piped thread or child process this holds, if any, and
+    /// report its errors.
+    ///
+    /// A piped thread consuming the child's output exits once every copy of
+    /// the writing end of its pipe is closed, including the one held by the
+    /// [`Command`] the `Stdio` was given to, so drop that first. A child
+    /// process is waited for, and if [`set_check_exit_status`] enabled
+    /// exit-status checking on the stream, an unsuccessful exit is reported
+    /// as an error.
+    ///
+    /// [`set_check_exit_status`]: crate::ReadHandle::set_check_exit_status
+    pub fn close(self) -> io::Result<()> {
+        match self.0 {
+            // As in `ReadHandle::close`, a broken pipe means the reader has
+            // stopped reading, which isn't an error.
+            Some(StdioResource::ReadThread(mut join_guard)) => match join_guard.join() {
+                Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+                result => result,
+            },
+            Some(StdioResource::WriteThread(mut join_guard)) => join_guard.join(),
+            Some(StdioResource::Child(mut child)) => child.close(),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(unix)]
+impl fmt::Debug for StdioGuard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Don't print the resources themselves, just what kind they are.
+        let kind = match &self.0 {
+            Some(StdioResource::ReadThread(_)) | Some(StdioResource::WriteThread(_)) => {
+                "piped thread"
+            }
+            Some(StdioResource::Child(_)) => "child process",
+            None => "none",
+        };
+        f.debug_struct("StdioGuard").field("kind", &kind).finish()
+    }
+}
+
+/// A spawned child process, along with whether an unsuccessful exit should be
+/// reported as an I/O error.
+///
+/// For a pipeline, `child` is the last stage, and `upstream` holds the
+/// earlier stages, which are waited for along with it. `stdin_guard` holds
+/// what the stream feeding the first stage left behind, which is closed
+/// after them.
+pub(crate) struct ChildProcess {
+    child: Child,
+    upstream: Box<[Child]>,
+    #[cfg(unix)]
+    stdin_guard: Option<Box<StdioGuard>>,
+    check_exit_status: bool,
+}
+
+impl ChildProcess {
+    #[inline]
+    pub(crate) fn new(child: Child) -> Self {
+        Self::pipeline(Vec::new(), child)
+    }
+
+    #[inline]
+    pub(crate) fn pipeline(upstream: Vec<Child>, child: Child) -> Self {
+        Self {
+            child,
+            upstream: upstream.into_boxed_slice(),
+            #[cfg(unix)]
+            stdin_guard: None,
+            check_exit_status: false,
+        }
+    }
+
+    #[cfg(unix)]
+    #[inline]
+    pub(crate) fn set_stdin_guard(&mut self, stdin_guard: StdioGuard) {
+        self.stdin_guard = stdin_guard.0.is_some().then(|| Box::new(stdin_guard));
+    }
+
+    /// Test whether this holds more than the one child, either earlier
+    /// stages of a pipeline or what the stream feeding it left behind.
+    #[cfg(unix)]
+    #[inline]
+    pub(crate) fn is_pipeline(&self) -> bool {
+        !self.upstream.is_empty() || self.stdin_guard.is_some()
+    }
+
+    #[inline]
+    pub(crate) fn set_check_exit_status(&mut self, check: bool) {
+        self.check_exit_status = check;
+    }
+
+    #[cfg(unix)]
+    #[inline]
+    pub(crate) fn checks_exit_status(&self) -> bool {
+        self.check_exit_status
+    }
+
+    #[inline]
+    pub(crate) fn into_child(self) -> Child {
+        self.child
+    }
+
+    /// Wait for the child to exit. Like [`Child::wait`], this closes the
+    /// child's standard input first, if it's still held.
+    ///
+    /// For a pipeline, this waits for every stage, and returns the status of
+    /// the last stage to exit unsuccessfully, or of the last stage if they
+    /// all succeeded, like a shell's `pipefail` option.
+    #[inline]
+    pub(crate) fn wait(&mut self) -> io::Result<ExitStatus> {
+        self.wait_all().map(|statuses| pipefail(&statuses))
+    }
+
+    /// Wait for every stage of a pipeline to exit, and return their exit
+    /// statuses in pipeline order. Then close the stdin guard, if any, and
+    /// report its errors.
+    pub(crate) fn wait_all(&mut self) -> io::Result<Vec<ExitStatus>> {
+        let status = self.child.wait()?;
+        let mut statuses = self
+            .upstream
+            .iter_mut()
+            .map(Child::wait)
+            .collect::<io::Result<Vec<_>>>()?;
+        statuses.push(status);
+        #[cfg(unix)]
+        if let Some(stdin_guard) = self.stdin_guard.take() {
+            stdin_guard.close()?;
+        }
+        Ok(statuses)
+    }
+
+    pub(crate) fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> {
+        let status = match self.child.try_wait()? {
+            Some(status) => status,
+            None => return Ok(None),
+        };
+        let mut statuses = Vec::with_capacity(self.upstream.len() + 1);
+        for child in &mut self.upstream {
+            match child.try_wait()? {
+                Some(status) => statuses.push(status),
+                None => return Ok(None),
+            }
+        }
+        statuses.push(status);
+        Ok(Some(pipefail(&statuses)))
+    }
+
+    /// Take our end of the child's standard error, if it was captured and
+    /// is still held.
+    #[inline]
+    pub(crate) fn take_stderr(&mut self) -> Option<ChildStderr> {
+        self.child.stderr.take()
+    }
+
+    /// Take our end of the child's standard output, if it's still held.
+    #[cfg(unix)]
+    #[inline]
+    pub(crate) fn take_stdout(&mut self) -> Option<ChildStdout> {
+        self.child.stdout.take()
+    }
+
+    /// Take our end of the child's standard input, if it's still held.
+    #[inline]
+    pub(crate) fn take_stdin(&mut self) -> Option<ChildStdin> {
+        self.child.stdin.take()
+    }
+
+    /// Put back our end of the child's standard input, failing if it's
+    /// already held.
+    pub(crate) fn restore_stdin(&mut self, child_stdin: ChildStdin) -> Result<(), ChildStdin> {
+        if self.child.stdin.is_some() {
+            return Err(child_stdin);
+        }
+        self.child.stdin = Some(child_stdin);
+        Ok(())
+    }
+
+    /// Replace our end of the child's standard input, closing the old one.
+    #[inline]
+    pub(crate) fn replace_stdin(&mut self, child_stdin: ChildStdin) {
+        self.child.stdin = Some(child_stdin);
+    }
+
+    /// Close our end of the child's standard output, if it's still held, so
+    /// that it doesn't block writing to it while we wait for it.
+    #[inline]
+    pub(crate) fn close_stdout(&mut self) {
+        drop(self.child.stdout.take());
+    }
+
+    /// Wait for the child to exit, and if exit-status checking is enabled,
+    /// report an unsuccessful exit as an error.
+    #[cfg(unix)]
+    pub(crate) fn close(&mut self) -> io::Result<()> {
+        let status = self.wait()?;
+        if self.check_exit_status && !status.success() {
+            return Err(exit_status_error(status));
+        }
+        Ok(())
+    }
+
+    /// If exit-status checking is enabled, wait for the child to exit and
+    /// report an unsuccessful exit as an error.
+    pub(crate) fn check_exit_status(&mut self) -> io::Result<()> {
+        if self.check_exit_status {
+            let status = self.wait()?;
+            if !status.success() {
+                return Err(exit_status_error(status));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Spawn `command` with its standard output piped, and its standard error
+/// directed according to `stderr`.
+pub(crate) fn spawn_stdout_piped(mut command: Command, stderr: StderrMode) -> io::Result<Child> {
+    command.stdout(Stdio::piped());
+    let merged = match stderr {
+        StderrMode::Inherit => {
+            command.stderr(Stdio::inherit());
+            None
+        }
+        StderrMode::Null => {
+            command.stderr(Stdio::null());
+            None
+        }
+        StderrMode::Capture => {
+            command.stderr(Stdio::piped());
+            None
+        }
+        StderrMode::Merge => {
+            let (pipe_reader, pipe_writer) = pipe()?;
+            command.stdout(pipe_writer.try_clone()?);
+            command.stderr(pipe_writer);
+            Some(pipe_reader)
+        }
+    };
+    let mut child = command.spawn()?;
+
+    // Close our copies of the writing end of the pipe, so that reading sees
+    // the end once the child exits, and present the reading end as the
+    // child's standard output.
+    drop(command);
+    if let Some(pipe_reader) = merged {
+        child.stdout = Some(child_stdout_from_pipe(pipe_reader));
+    }
+    Ok(child)
+}
+
+/// Configure `command` with its standard output discarded, and its standard
+/// error directed according to `stderr`.
+pub(crate) fn discard_stdout(command: &mut Command, stderr: StderrMode) {
+    command.stdout(Stdio::null());
+    command.stderr(match stderr {
+        StderrMode::Inherit => Stdio::inherit(),
+        StderrMode::Null | StderrMode::Merge => Stdio::null(),
+        StderrMode::Capture => Stdio::piped(),
+    });
+}
+
+#[cfg(unix)]
+fn child_stdout_from_pipe(pipe_reader: PipeReader) -> ChildStdout {
+    ChildStdout::from(unsafe { OwnedFd::from_raw_fd(pipe_reader.into_raw_fd()) })
+}
+
+#[cfg(windows)]
+fn child_stdout_from_pipe(pipe_reader: PipeReader) -> ChildStdout {
+    ChildStdout::from(unsafe { OwnedHandle::from_raw_handle(pipe_reader.into_raw_handle()) })
+}
+
+/// Combine the exit statuses of the stages of a pipeline, in order, into the
+/// status of the last one to exit unsuccessfully, or of the last one if they
+/// all succeeded.
+fn pipefail(statuses: &[ExitStatus]) -> ExitStatus {
+    let last = *statuses.last().unwrap();
+    statuses
+        .iter()
+        .rev()
+        .find(|status| !status.success())
+        .copied()
+        .unwrap_or(last)
+}
+
+/// Construct the error reported for an unsuccessful exit.
+fn exit_status_error(status: ExitStatus) -> io::Error {
+    io::Error::other(format!("child process exited unsuccessfully: {}", status))
+}
+
+/// Construct the error reported when a child-process operation is requested
+/// on a stream that isn't backed by a child process.
+pub(crate) fn not_a_child() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        "stream is not backed by a child process",
+    )
+}
@@ -0,0 +1,120 @@
+//! Copying between streams, avoiding copies through userspace where possible.
+
+use crate::{ReadHandle, WriteHandle};
+use std::io;
+
+/// Copy the entire contents of `reader` into `writer`, returning the number
+/// of bytes copied.
+///
+/// This is like [`std::io::copy`], except that on Linux, it inspects both
+/// streams and uses `copy_file_range`, `sendfile`, or `splice` to copy the
+/// data within the kernel when the kinds of the underlying file descriptors
+/// allow it. Otherwise, or if the kernel declines, it falls back to a
+/// read/write loop.
+///
+/// The kernel-side copy is skipped when a read or write timeout is set, so
+/// that the timeouts apply. The end of the stream is always observed through
+/// `reader`'s [`std::io::Read`] implementation, so exit-status checking on
+/// command-backed streams works as usual.
+pub fn copy(reader: &mut ReadHandle, writer: &mut WriteHandle) -> io::Result<u64> {
+    #[cfg(target_os = "linux")]
+    let copied = if reader.read_timeout().is_none() && writer.write_timeout().is_none() {
+        linux::copy(reader, writer)?
+    } else {
+        0
+    };
+    #[cfg(not(target_os = "linux"))]
+    let copied = 0;
+
+    Ok(copied + io::copy(reader, writer)?)
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use crate::{ReadHandle, WriteHandle};
+    use std::{
+        io,
+        mem::MaybeUninit,
+        os::unix::io::{AsRawFd, RawFd},
+        ptr,
+    };
+
+    /// The most that Linux transfers in one call.
+    const MAX_CHUNK: usize = 0x7fff_f000;
+
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    enum Kind {
+        File,
+        Pipe,
+        Other,
+    }
+
+    #[derive(Clone, Copy)]
+    enum Method {
+        CopyFileRange,
+        Sendfile,
+        Splice,
+    }
+
+    /// Copy as much as the kernel will copy for us, returning the number of
+    /// bytes copied. This stops at the end of the input, or on any error
+    /// other than `EINTR`; the caller's read/write loop then finishes the job,
+    /// reproducing any error with the handles' own error reporting.
+    pub(super) fn copy(reader: &mut ReadHandle, writer: &mut WriteHandle) -> io::Result<u64> {
+        let in_fd = reader.as_raw_fd();
+        let out_fd = writer.as_raw_fd();
+        let method = match (kind(in_fd)?, kind(out_fd)?) {
+            (Kind::File, Kind::File) => Method::CopyFileRange,
+            (Kind::Pipe, _) | (_, Kind::Pipe) => Method::Splice,
+            (Kind::File, Kind::Other) => Method::Sendfile,
+            (Kind::Other, _) => return Ok(0),
+        };
+
+        let mut copied = 0;
+        loop {
+            let n = unsafe {
+                match method {
+                    Method::CopyFileRange => libc::copy_file_range(
+                        in_fd,
+                        ptr::null_mut(),
+                        out_fd,
+                        ptr::null_mut(),
+                        MAX_CHUNK,
+                        0,
+                    ),
+                    Method::Sendfile => libc::sendfile(out_fd, in_fd, ptr::null_mut(), MAX_CHUNK),
+                    Method::Splice => libc::splice(
+                        in_fd,
+                        ptr::null_mut(),
+                        out_fd,
+                        ptr::null_mut(),
+                        MAX_CHUNK,
+                        0,
+                    ),
+                }
+            };
+            match n {
+                -1 if io::Error::last_os_error().kind() == io::ErrorKind::Interrupted => {}
+                -1 | 0 => return Ok(copied),
+                n => {
+                    // Bytes copied into a piped thread's pipe count toward
+                    // what flushing it waits for, as with `write`.
+                    writer.wrote(n as usize);
+                    copied += n as u64;
+                }
+            }
+        }
+    }
+
+    fn kind(fd: RawFd) -> io::Result<Kind> {
+        let mut stat = MaybeUninit::<libc::stat>::uninit();
+        if unsafe { libc::fstat(fd, stat.as_mut_ptr()) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(match unsafe { stat.assume_init() }.st_mode & libc::S_IFMT {
+            libc::S_IFREG => Kind::File,
+            libc::S_IFIFO => Kind::Pipe,
+            _ => Kind::Other,
+        })
+    }
+}
@@ -7,13 +7,16 @@
 //! useful, because it allows reading and writing from any I/O source that can
 //! logically be read from or written to. So it seems justified.
 
+use crate::seekable::{is_seekable, not_seekable};
 use std::{
     fmt::Arguments,
-    fs::File,
-    io::{self, IoSlice, IoSliceMut, Read, Write},
+    fs::{File, Metadata},
+    io::{self, IoSlice, IoSliceMut, IsTerminal, Read, Seek, SeekFrom, Write},
     mem::ManuallyDrop,
     net::TcpStream,
-    os::windows::io::{FromRawHandle, FromRawSocket, RawHandle, RawSocket},
+    os::windows::io::{
+        AsRawHandle, AsRawSocket, FromRawHandle, FromRawSocket, RawHandle, RawSocket,
+    },
 };
 
 /// The `Descriptor` enum holding either a raw handle or a raw socket, allowing
@@ -41,6 +44,55 @@ impl Descriptor {
     pub(crate) unsafe fn raw_socket(raw_socket: RawSocket) -> Self {
         Self::Socket(ManuallyDrop::new(TcpStream::from_raw_socket(raw_socket)))
     }
+
+    /// Create another `Descriptor` for the same handle or socket.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that the resources held by `self` outlive the
+    /// resulting `Descriptor` instance.
+    #[inline]
+    pub(crate) unsafe fn alias(&self) -> Self {
+        match self {
+            Self::File(file) => Self::raw_handle(file.as_raw_handle()),
+            Self::Socket(socket) => Self::raw_socket(socket.as_raw_socket()),
+        }
+    }
+
+    /// Test whether this is a regular file, and so supports seeking.
+    #[inline]
+    pub(crate) fn is_seekable(&self) -> bool {
+        match self {
+            Self::File(file) => is_seekable(file),
+            Self::Socket(_) => false,
+        }
+    }
+
+    /// Test whether this is a terminal.
+    #[inline]
+    pub(crate) fn is_terminal(&self) -> bool {
+        self.file().is_some_and(IsTerminal::is_terminal)
+    }
+
+    /// Query metadata about the file. Sockets have no file metadata.
+    pub(crate) fn metadata(&self) -> io::Result<Metadata> {
+        match self {
+            Self::File(file) => file.metadata(),
+            Self::Socket(_) => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "sockets have no file metadata",
+            )),
+        }
+    }
+
+    /// Return the file, if this isn't a socket.
+    #[inline]
+    pub(crate) fn file(&self) -> Option<&File> {
+        match self {
+            Self::File(file) => Some(file),
+            Self::Socket(_) => None,
+        }
+    }
 }
 
 impl Read for Descriptor {
@@ -153,3 +205,13 @@ impl Write for Descriptor {
         }
     }
 }
+
+impl Seek for Descriptor {
+    #[inline]
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match self {
+            Self::File(file) => file.seek(pos),
+            Self::Socket(_) => Err(not_seekable()),
+        }
+    }
+}
@@ -0,0 +1,211 @@
+//! Threads which drive a boxed `ReadWrite`, connected to a stream by a pair
+//! of pipes.
+//!
+//! A `ReadWrite` can't be read from and written to at the same time from
+//! different threads, so a single thread owns it and alternates between the
+//! two directions. A helper thread reads from the pipe the stream writes to,
+//! and sends the data over a channel, so that the owning thread can wait for
+//! it with a timeout. When the `ReadWrite` has no data to read, it fails with
+//! `WouldBlock`, and the owning thread waits for data from the stream before
+//! trying it again, backing off while it stays idle. Once the `ReadWrite`
+//! reaches its end, the owning thread just waits for data from the stream.
+//!
+//! This means a `ReadWrite` which blocks in `read` stops data from the stream
+//! reaching it until the `read` returns, so blocking ones aren't supported.
+
+use crate::{piped_thread::JoinGuard, ReadWrite};
+use os_pipe::{pipe, PipeReader, PipeWriter};
+use std::{
+    cmp,
+    io::{self, Read, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError, Sender, TryRecvError},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+/// How long to first wait for data from the stream when the `ReadWrite` has
+/// no data to read, before trying to read from it again.
+const MIN_IDLE_WAIT: Duration = Duration::from_millis(1);
+
+/// The longest wait, which the wait doubles up to while the `ReadWrite`
+/// stays idle.
+const MAX_IDLE_WAIT: Duration = Duration::from_millis(100);
+
+/// The thread which owns a boxed `ReadWrite` and the thread which forwards
+/// data to it, with a flag to tell them that the stream has been closed.
+///
+/// Dropping this tells the threads to stop without waiting for them, so that
+/// a `ReadWrite` blocked in `read` can't hang the drop. Use `close` to wait
+/// for them and observe errors.
+pub(crate) struct DuplexThread {
+    join_guard: JoinGuard,
+    forwarder: JoinGuard,
+    closed: Arc<AtomicBool>,
+}
+
+impl DuplexThread {
+    /// Wait for the owning thread to exit, after it has stopped on its own,
+    /// and return its result.
+    #[inline]
+    pub(crate) fn join(&mut self) -> io::Result<()> {
+        self.join_guard.join()
+    }
+
+    /// Tell the threads that the stream has been closed, wait for them to
+    /// exit, and return their result. The caller should close its ends of
+    /// the pipes first.
+    ///
+    /// Everything written to the stream is written to the `ReadWrite` before
+    /// the owning thread exits, however if the `ReadWrite` is blocked in
+    /// `read`, this waits for it to return.
+    pub(crate) fn close(&mut self) -> io::Result<()> {
+        self.closed.store(true, Ordering::SeqCst);
+        let result = self.join();
+        let forwarded = self.forwarder.join();
+        result.and(forwarded)
+    }
+}
+
+impl Drop for DuplexThread {
+    fn drop(&mut self) {
+        // The threads exit on their own once the pipes are closed, and the
+        // `ReadWrite` returns from any `read` it's blocked in.
+        self.closed.store(true, Ordering::SeqCst);
+        self.join_guard.detach();
+        self.forwarder.detach();
+    }
+}
+
+/// Spawn a thread which drives `boxed_read_write`, writing what it reads to
+/// the returned `PipeReader`, and writing to it what's written to the
+/// returned `PipeWriter`.
+pub(crate) fn spawn_duplex(
+    boxed_read_write: Box<dyn ReadWrite + Send>,
+) -> io::Result<(PipeReader, PipeWriter, DuplexThread)> {
+    let (pipe_reader, output_writer) = pipe()?;
+    let (input_reader, pipe_writer) = pipe()?;
+    let (sender, receiver) = mpsc::channel();
+    let forwarder = thread::Builder::new()
+        .name("piped thread for boxed read-writer input".to_owned())
+        .spawn(move || forward_input(input_reader, sender))?;
+    let closed = Arc::new(AtomicBool::new(false));
+    let thread_closed = Arc::clone(&closed);
+    let join_handle = thread::Builder::new()
+        .name("piped thread for boxed read-writer".to_owned())
+        .spawn(move || drive(boxed_read_write, receiver, output_writer, &thread_closed))?;
+    Ok((
+        pipe_reader,
+        pipe_writer,
+        DuplexThread {
+            join_guard: JoinGuard::new(join_handle),
+            forwarder: JoinGuard::new(forwarder),
+            closed,
+        },
+    ))
+}
+
+/// Send everything written to the stream to the owning thread. This stops
+/// when the stream closes its end of the pipe, or when the owning thread
+/// exits.
+fn forward_input(mut input_reader: PipeReader, sender: Sender<Vec<u8>>) -> io::Result<()> {
+    let mut buf = [0_u8; 8192];
+    loop {
+        match input_reader.read(&mut buf) {
+            Ok(0) => return Ok(()),
+            Ok(size) => {
+                // If the owning thread has exited, it reports why.
+                if sender.send(buf[..size].to_vec()).is_err() {
+                    return Ok(());
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+fn drive(
+    mut boxed_read_write: Box<dyn ReadWrite + Send>,
+    receiver: Receiver<Vec<u8>>,
+    output_writer: PipeWriter,
+    closed: &AtomicBool,
+) -> io::Result<()> {
+    let mut output_writer = Some(output_writer);
+    let mut buf = [0_u8; 8192];
+    let mut input_done = false;
+    // How long to wait before reading from the `ReadWrite` again, if it had
+    // no data to read last time.
+    let mut idle_wait = None;
+    loop {
+        // Once the stream is closed, or the `ReadWrite` has reached its end,
+        // there's nothing more to read.
+        let reading = output_writer.is_some() && !closed.load(Ordering::SeqCst);
+
+        // Forward what's been written to the stream into the `ReadWrite`. If
+        // there's nothing else to do, wait for it; the stream closes its end
+        // of the pipe before it's closed, which ends the wait.
+        if !input_done {
+            let mut next = match idle_wait {
+                _ if !reading => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+                Some(wait) => receiver.recv_timeout(wait),
+                None => try_recv(&receiver),
+            };
+            let mut wrote = false;
+            loop {
+                match next {
+                    Ok(data) => boxed_read_write.write_all(&data)?,
+                    Err(RecvTimeoutError::Timeout) => break,
+                    Err(RecvTimeoutError::Disconnected) => {
+                        input_done = true;
+                        break;
+                    }
+                }
+                wrote = true;
+                next = try_recv(&receiver);
+            }
+            if wrote {
+                boxed_read_write.flush()?;
+                idle_wait = None;
+            }
+        } else if !reading {
+            break;
+        } else if let Some(wait) = idle_wait {
+            thread::sleep(wait);
+        }
+
+        // Forward what the `ReadWrite` has to read to the stream.
+        if let (true, Some(writer)) = (reading, &mut output_writer) {
+            match boxed_read_write.read(&mut buf) {
+                Ok(0) => output_writer = None,
+                Ok(size) => {
+                    match writer.write_all(&buf[..size]) {
+                        // The stream has closed its end, but data written to
+                        // it may still need forwarding.
+                        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => output_writer = None,
+                        result => result?,
+                    }
+                    idle_wait = None;
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    idle_wait = Some(idle_wait.map_or(MIN_IDLE_WAIT, |wait: Duration| {
+                        cmp::min(wait * 2, MAX_IDLE_WAIT)
+                    }));
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => idle_wait = None,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+    Ok(())
+}
+
+fn try_recv(receiver: &Receiver<Vec<u8>>) -> Result<Vec<u8>, RecvTimeoutError> {
+    receiver.try_recv().map_err(|e| match e {
+        TryRecvError::Empty => RecvTimeoutError::Timeout,
+        TryRecvError::Disconnected => RecvTimeoutError::Disconnected,
+    })
+}
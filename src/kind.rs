@@ -0,0 +1,43 @@
+//! The kinds of I/O objects that can back a stream.
+
+/// What kind of I/O object backs a stream, as returned by
+/// [`ReadHandle::kind`], [`WriteHandle::kind`], and
+/// [`ReadWriteHandle::kind`].
+///
+/// This reflects how the stream was constructed. For details about the
+/// underlying file, such as whether a [`File`] stream is a regular file or a
+/// device, use the stream's `metadata`.
+///
+/// [`ReadHandle::kind`]: crate::ReadHandle::kind
+/// [`WriteHandle::kind`]: crate::WriteHandle::kind
+/// [`ReadWriteHandle::kind`]: crate::ReadWriteHandle::kind
+/// [`File`]: Self::File
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum HandleKind {
+    /// An open file.
+    File,
+    /// A TCP socket.
+    TcpStream,
+    /// A Unix-domain socket.
+    #[cfg(unix)]
+    UnixStream,
+    /// A pipe, or a pair of pipes.
+    #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+    Pipe,
+    /// The process' standard input, output, or error, or a combination.
+    Stdio,
+    /// A pipe to or from a thread which drives a boxed `Read`, `Write`, or
+    /// `ReadWrite`.
+    #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+    PipedThread,
+    /// The standard streams of a child process.
+    #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+    Child,
+    /// A character device file.
+    CharDevice,
+    /// The master side of a pseudo-terminal, possibly with a child process
+    /// on the slave side.
+    #[cfg(unix)]
+    Pty,
+}
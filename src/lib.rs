@@ -7,6 +7,21 @@
 //! them in buffering types such as [`std::io::BufReader`], [`std::io::BufWriter`],
 //! [`std::io::LineWriter`], [`BufReaderWriter`], or [`BufReaderLineWriter`].
 //!
+//! [`copy`] copies from a [`ReadHandle`] to a [`WriteHandle`], within the
+//! kernel where the platform and the kinds of streams allow it.
+//!
+//! On Posix-ish platforms, streams can be put in non-blocking mode, reads and
+//! writes can be given timeouts, and [`poll`] waits for any of several
+//! streams to become ready. These aren't available on Windows, whose
+//! anonymous pipes and consoles can't wait for readiness with a timeout.
+//!
+//! With the `tokio` cargo feature enabled, on Unix-family platforms,
+//! [`AsyncReadHandle`], [`AsyncWriteHandle`], and [`AsyncReadWriteHandle`]
+//! adapt these types to tokio's `AsyncRead` and `AsyncWrite`. Similarly, with
+//! the `async-std` cargo feature enabled, [`AsyncStdReadHandle`],
+//! [`AsyncStdWriteHandle`], and [`AsyncStdReadWriteHandle`] adapt them to
+//! async-std's `Read` and `Write`.
+//!
 //! [`BufReader`]: std::io::BufReader
 //! [`BufWriter`]: std::io::BufWriter
 //! [`LineWriter`]: std::io::LineWriter
@@ -19,23 +34,64 @@
 #![cfg_attr(read_initializer, feature(read_initializer))]
 #![cfg_attr(target_os = "wasi", feature(wasi_ext))]
 
+#[cfg(all(unix, any(feature = "tokio", feature = "async-std")))]
+mod async_handles;
+#[cfg(all(unix, feature = "async-std"))]
+mod async_std_handles;
 mod buffered;
+#[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+mod child;
+mod copy;
 #[cfg(windows)]
 mod descriptor;
+#[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+mod duplex_thread;
+mod kind;
 mod lockers;
+#[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+mod piped_thread;
+#[cfg(unix)]
+mod pipeline;
+#[cfg(not(windows))]
+mod poll;
 #[cfg(not(windows))]
 mod posish;
+#[cfg(unix)]
+mod pty;
 mod read_write;
+mod seekable;
+#[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+mod tee;
+#[cfg(unix)]
+mod terminal;
+#[cfg(all(unix, feature = "tokio"))]
+mod tokio_handles;
 #[cfg(windows)]
 mod winx;
 
-pub use buffered::{BufReaderLineWriter, BufReaderWriter, IntoInnerError};
+#[cfg(all(unix, feature = "async-std"))]
+pub use async_std_handles::{AsyncStdReadHandle, AsyncStdReadWriteHandle, AsyncStdWriteHandle};
+pub use buffered::{BufReaderLineWriter, BufReaderWriter, FlushPolicy, IntoInnerError};
+#[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+pub use child::StderrMode;
+#[cfg(unix)]
+pub use child::StdioGuard;
+pub use copy::copy;
+pub use kind::HandleKind;
+#[cfg(unix)]
+pub use pipeline::Pipeline;
 #[cfg(not(windows))]
-pub use posish::{ReadHandle, ReadWriteHandle, WriteHandle};
+pub use poll::{poll, PollFd};
 #[cfg(not(windows))]
-pub use read_write::AsRawReadWriteFd;
+pub use posish::{ReadHandle, ReadWriteHandle, WriteHandle};
 pub use read_write::ReadWrite;
 #[cfg(windows)]
 pub use read_write::{AsRawHandleOrSocket, AsRawReadWriteHandleOrSocket};
+#[cfg(not(windows))]
+pub use read_write::{AsRawReadWriteFd, AsReadWriteFd};
+#[cfg(unix)]
+pub use terminal::TerminalModeGuard;
+#[cfg(all(unix, feature = "tokio"))]
+pub use tokio_handles::{AsyncReadHandle, AsyncReadWriteHandle, AsyncWriteHandle};
 #[cfg(windows)]
 pub use winx::{ReadHandle, ReadWriteHandle, WriteHandle};
@@ -1,4 +1,4 @@
-//! Hold locks for the process' stdin and stdout.
+//! Hold locks for the process' stdin, stdout, and stderr.
 
 use once_cell::sync::Lazy;
 use parking::{Parker, Unparker};
@@ -9,20 +9,22 @@ use std::os::wasi::io::{AsRawFd, RawFd};
 #[cfg(windows)]
 use std::os::windows::io::{AsRawHandle, RawHandle};
 use std::{
-    io::{self, stdin, stdout, Stdin, Stdout},
+    io::{self, stderr, stdin, stdout, Stderr, Stdin, Stdout},
     sync::atomic::{AtomicBool, Ordering::SeqCst},
     thread::{self, JoinHandle},
 };
 
-// Static handles to `stdin()` and `stdout()` so that we can reference
-// them with `StdinLock` and `StdoutLock` with `'static` lifetime
-// parameters.
+// Static handles to `stdin()`, `stdout()`, and `stderr()` so that we can
+// reference them with `StdinLock`, `StdoutLock`, and `StderrLock` with
+// `'static` lifetime parameters.
 static STDIN: Lazy<Stdin> = Lazy::new(stdin);
 static STDOUT: Lazy<Stdout> = Lazy::new(stdout);
+static STDERR: Lazy<Stderr> = Lazy::new(stderr);
 
-// Statically track whether `STDIN` and `STDOUT` are claimed.
+// Statically track whether `STDIN`, `STDOUT`, and `STDERR` are claimed.
 static STDIN_CLAIMED: AtomicBool = AtomicBool::new(false);
 static STDOUT_CLAIMED: AtomicBool = AtomicBool::new(false);
+static STDERR_CLAIMED: AtomicBool = AtomicBool::new(false);
 
 /// This class acquires a lock on `stdin` and prevents applications from
 /// accidentally accessing it through other means.
@@ -38,6 +40,13 @@ pub(crate) struct StdoutLocker {
     handle: Option<JoinHandle<()>>,
 }
 
+/// This class acquires a lock on `stderr` and prevents applications from
+/// accidentally accessing it through other means.
+pub(crate) struct StderrLocker {
+    unparker: Unparker,
+    handle: Option<JoinHandle<()>>,
+}
+
 impl StdinLocker {
     /// An `InputByteStream` can take the value of the process' stdin, in which
     /// case we want it to have exclusive access to `stdin`. Lock the Rust standard
@@ -45,7 +54,10 @@ impl StdinLocker {
     ///
     /// Fails if a `StdinLocker` instance already exists.
     pub(crate) fn new() -> io::Result<Self> {
-        if !STDIN_CLAIMED.compare_and_swap(false, true, SeqCst) {
+        if STDIN_CLAIMED
+            .compare_exchange(false, true, SeqCst, SeqCst)
+            .is_ok()
+        {
             // `StdinLock` is not `Send`. To let `StdinLocker` be send, hold
             // the lock on a parked thread.
             let parker = Parker::new();
@@ -62,10 +74,7 @@ impl StdinLocker {
 
             Ok(Self { unparker, handle })
         } else {
-            Err(io::Error::new(
-                io::ErrorKind::Other,
-                "attempted dual-ownership of stdin",
-            ))
+            Err(io::Error::other("attempted dual-ownership of stdin"))
         }
     }
 }
@@ -77,7 +86,10 @@ impl StdoutLocker {
     ///
     /// Fails if a `StdoutLocker` instance already exists.
     pub(crate) fn new() -> io::Result<Self> {
-        if !STDOUT_CLAIMED.compare_and_swap(false, true, SeqCst) {
+        if STDOUT_CLAIMED
+            .compare_exchange(false, true, SeqCst, SeqCst)
+            .is_ok()
+        {
             // `StdoutLock` is not `Send`. To let `StdoutLocker` be send, hold
             // the lock on a parked thread.
             let parker = Parker::new();
@@ -94,10 +106,39 @@ impl StdoutLocker {
 
             Ok(Self { unparker, handle })
         } else {
-            Err(io::Error::new(
-                io::ErrorKind::Other,
-                "attempted dual-ownership of stdout",
-            ))
+            Err(io::Error::other("attempted dual-ownership of stdout"))
+        }
+    }
+}
+
+impl StderrLocker {
+    /// An `OutputByteStream` can take the value of the process' stderr, in which
+    /// case we want it to have exclusive access to `stderr`. Lock the Rust standard
+    /// library's `stderr` to prevent accidental misuse.
+    ///
+    /// Fails if a `StderrLocker` instance already exists.
+    pub(crate) fn new() -> io::Result<Self> {
+        if STDERR_CLAIMED
+            .compare_exchange(false, true, SeqCst, SeqCst)
+            .is_ok()
+        {
+            // `StderrLock` is not `Send`. To let `StderrLocker` be send, hold
+            // the lock on a parked thread.
+            let parker = Parker::new();
+            let unparker = parker.unparker();
+            let handle = Some(
+                thread::Builder::new()
+                    .name("ensure exclusive access to stderr".to_owned())
+                    .stack_size(64)
+                    .spawn(move || {
+                        let _lock = STDERR.lock();
+                        parker.park()
+                    })?,
+            );
+
+            Ok(Self { unparker, handle })
+        } else {
+            Err(io::Error::other("attempted dual-ownership of stderr"))
         }
     }
 }
@@ -120,6 +161,15 @@ impl Drop for StdoutLocker {
     }
 }
 
+impl Drop for StderrLocker {
+    #[inline]
+    fn drop(&mut self) {
+        self.unparker.unpark();
+        self.handle.take().unwrap().join().unwrap();
+        STDERR_CLAIMED.store(false, SeqCst);
+    }
+}
+
 #[cfg(not(windows))]
 impl AsRawFd for StdinLocker {
     #[inline]
@@ -136,6 +186,14 @@ impl AsRawFd for StdoutLocker {
     }
 }
 
+#[cfg(not(windows))]
+impl AsRawFd for StderrLocker {
+    #[inline]
+    fn as_raw_fd(&self) -> RawFd {
+        STDERR.as_raw_fd()
+    }
+}
+
 #[cfg(windows)]
 impl AsRawHandle for StdinLocker {
     #[inline]
@@ -151,3 +209,11 @@ impl AsRawHandle for StdoutLocker {
         STDOUT.as_raw_handle()
     }
 }
+
+#[cfg(windows)]
+impl AsRawHandle for StderrLocker {
+    #[inline]
+    fn as_raw_handle(&self) -> RawHandle {
+        STDERR.as_raw_handle()
+    }
+}
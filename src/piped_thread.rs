@@ -0,0 +1,158 @@
+//! Threads which write the contents of a pipe to a boxed `Write`, with a
+//! side channel for flushing.
+//!
+//! There's no way to send a flush event through a pipe, so the boxed `Write`
+//! is shared with the thread. The thread counts the bytes it consumes from the
+//! pipe, and a flush waits until the thread has consumed everything written to
+//! the pipe so far, and then flushes the boxed `Write` itself.
+
+use os_pipe::{pipe, PipeReader, PipeWriter};
+use std::{
+    io::{self, Read, Write},
+    sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError},
+    thread::{self, JoinHandle},
+};
+
+struct Shared {
+    state: Mutex<State>,
+    drained: Condvar,
+}
+
+struct State {
+    boxed_write: Box<dyn Write + Send>,
+    consumed: u64,
+    done: bool,
+}
+
+/// The writing side of the flush side channel.
+pub(crate) struct Flusher {
+    shared: Arc<Shared>,
+    written: u64,
+}
+
+impl Flusher {
+    /// Record that `size` bytes were written to the pipe.
+    #[inline]
+    pub(crate) fn wrote(&mut self, size: usize) {
+        self.written += size as u64;
+    }
+
+    /// Wait for the thread to consume everything written to the pipe, and
+    /// flush the boxed `Write`. Returns `false` if the thread exited before
+    /// consuming everything, in which case the thread's result holds the
+    /// error.
+    pub(crate) fn flush(&self) -> io::Result<bool> {
+        let state = self.shared.state.lock().map_err(panicked)?;
+        let mut state = self
+            .shared
+            .drained
+            .wait_while(state, |state| !state.done && state.consumed < self.written)
+            .map_err(panicked)?;
+        if state.consumed < self.written {
+            return Ok(false);
+        }
+        state.boxed_write.flush()?;
+        Ok(true)
+    }
+}
+
+/// A piped thread, which is waited for when this is dropped. Errors can't be
+/// reported from `drop`; use `join` to observe them.
+///
+/// The thread usually exits once its pipe is closed, so the pipe should be
+/// closed before this is dropped.
+pub(crate) struct JoinGuard(Option<JoinHandle<io::Result<()>>>);
+
+impl JoinGuard {
+    #[inline]
+    pub(crate) fn new(join_handle: JoinHandle<io::Result<()>>) -> Self {
+        Self(Some(join_handle))
+    }
+
+    /// Wait for the thread to exit and return its result. A panic in the
+    /// thread is reported as an error rather than propagated. If the thread
+    /// has already been waited for, this returns `Ok(())`.
+    pub(crate) fn join(&mut self) -> io::Result<()> {
+        match self.0.take() {
+            Some(join_handle) => join_handle
+                .join()
+                .unwrap_or_else(|_| Err(io::Error::other("piped thread panicked"))),
+            None => Ok(()),
+        }
+    }
+
+    /// Let the thread run on its own, without waiting for it when dropped.
+    #[inline]
+    pub(crate) fn detach(&mut self) {
+        drop(self.0.take());
+    }
+}
+
+impl Drop for JoinGuard {
+    fn drop(&mut self) {
+        let _ = self.join();
+    }
+}
+
+/// Spawn a thread which writes everything written to the returned
+/// `PipeWriter` to `boxed_write`.
+pub(crate) fn spawn_writer(
+    boxed_write: Box<dyn Write + Send>,
+) -> io::Result<(PipeWriter, JoinGuard, Flusher)> {
+    let (pipe_reader, pipe_writer) = pipe()?;
+    let shared = Arc::new(Shared {
+        state: Mutex::new(State {
+            boxed_write,
+            consumed: 0,
+            done: false,
+        }),
+        drained: Condvar::new(),
+    });
+    let thread_shared = Arc::clone(&shared);
+    let join_handle = thread::Builder::new()
+        .name("piped thread for boxed writer".to_owned())
+        .spawn(move || {
+            // Wake up any flushers when the thread exits, even by panicking.
+            let _done = Done(&thread_shared);
+            drain(pipe_reader, &thread_shared)
+        })?;
+    Ok((
+        pipe_writer,
+        JoinGuard::new(join_handle),
+        Flusher { shared, written: 0 },
+    ))
+}
+
+fn drain(mut pipe_reader: PipeReader, shared: &Shared) -> io::Result<()> {
+    let mut buf = [0_u8; 8192];
+    loop {
+        let size = match pipe_reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(size) => size,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        let mut state = lock(shared);
+        state.boxed_write.write_all(&buf[..size])?;
+        state.consumed += size as u64;
+        shared.drained.notify_all();
+    }
+    lock(shared).boxed_write.flush()
+}
+
+struct Done<'a>(&'a Shared);
+
+impl Drop for Done<'_> {
+    fn drop(&mut self) {
+        lock(self.0).done = true;
+        self.0.drained.notify_all();
+    }
+}
+
+fn lock(shared: &Shared) -> MutexGuard<'_, State> {
+    shared.state.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn panicked<T>(_: PoisonError<T>) -> io::Error {
+    io::Error::other("piped thread panicked")
+}
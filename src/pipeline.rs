@@ -0,0 +1,178 @@
+//! Chaining child processes together, like a shell pipeline.
+
+use crate::{
+    child::{ChildProcess, StdioGuard},
+    ReadHandle, WriteHandle,
+};
+use std::{
+    io,
+    process::{Child, Command, ExitStatus, Stdio},
+};
+
+/// A sequence of commands with the standard output of each connected to the
+/// standard input of the next, like `a | b | c` in a shell.
+///
+/// The first command reads from the stream given to [`stdin`], or from the
+/// null device. The last command's output is either read through the
+/// [`ReadHandle`] returned by [`read`], or written to a [`WriteHandle`] by
+/// [`write_to`].
+///
+/// Exit statuses follow a shell's `pipefail` option: the status of the
+/// pipeline is that of the last command to exit unsuccessfully, or of the
+/// last command if they all succeeded.
+///
+/// # Examples
+///
+/// ```
+/// use io_handles::Pipeline;
+/// use std::{io::Read, process::Command};
+///
+/// fn main() -> std::io::Result<()> {
+///     let mut echo = Command::new("echo");
+///     echo.arg("hello");
+///
+///     let mut output = Pipeline::new()
+///         .command(echo)
+///         .command(Command::new("rev"))
+///         .read()?;
+///
+///     let mut s = String::new();
+///     output.read_to_string(&mut s)?;
+///     assert_eq!(s, "olleh\n");
+///     assert_eq!(output.wait_all()?.len(), 2);
+///     Ok(())
+/// }
+/// ```
+///
+/// [`stdin`]: Self::stdin
+/// [`read`]: Self::read
+/// [`write_to`]: Self::write_to
+#[derive(Debug, Default)]
+pub struct Pipeline {
+    stdin: Option<ReadHandle>,
+    commands: Vec<Command>,
+}
+
+impl Pipeline {
+    /// Construct an empty pipeline.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed the first command from the given stream.
+    ///
+    /// If the stream is a piped thread, or is backed by a child process,
+    /// that's waited for after the commands, and its errors are reported
+    /// along with theirs, as with [`StdioGuard::close`].
+    ///
+    /// [`StdioGuard::close`]: crate::StdioGuard::close
+    #[inline]
+    pub fn stdin(mut self, stdin: ReadHandle) -> Self {
+        self.stdin = Some(stdin);
+        self
+    }
+
+    /// Append a command to the end of the pipeline.
+    ///
+    /// Its standard input and output are replaced by the pipeline's; its
+    /// standard error is left as configured.
+    #[inline]
+    pub fn command(mut self, command: Command) -> Self {
+        self.commands.push(command);
+        self
+    }
+
+    /// Spawn the pipeline and read from the last command's standard output.
+    ///
+    /// Waiting for the returned stream with [`ReadHandle::wait`] and
+    /// [`ReadHandle::try_wait`], or reaching its end or closing it with
+    /// [`ReadHandle::set_check_exit_status`] enabled, covers every command
+    /// in the pipeline. [`ReadHandle::wait_all`] returns each command's exit
+    /// status.
+    ///
+    /// Fails with [`std::io::ErrorKind::InvalidInput`] if the pipeline has no
+    /// commands.
+    pub fn read(self) -> io::Result<ReadHandle> {
+        let (upstream, child, stdin_guard) = self.spawn(Stdio::piped())?;
+        Ok(ReadHandle::pipeline(upstream, child, stdin_guard))
+    }
+
+    /// Spawn the pipeline with the last command writing to `output`, wait
+    /// for every command to exit, and return their exit statuses in pipeline
+    /// order.
+    ///
+    /// If `output` is a piped thread, or is backed by a child process, this
+    /// then waits for it to finish with the output, and reports its errors,
+    /// as with [`StdioGuard::close`].
+    ///
+    /// Fails with [`std::io::ErrorKind::InvalidInput`] if the pipeline has no
+    /// commands.
+    ///
+    /// [`StdioGuard::close`]: crate::StdioGuard::close
+    pub fn write_to(self, output: WriteHandle) -> io::Result<Vec<ExitStatus>> {
+        let (stdout, output_guard) = output.try_into_stdio()?;
+        let (upstream, child, stdin_guard) = self.spawn(stdout)?;
+        let mut pipeline = ChildProcess::pipeline(upstream, child);
+        pipeline.set_stdin_guard(stdin_guard);
+        let statuses = pipeline.wait_all();
+
+        // The commands, which held copies of the output, have been dropped,
+        // so once the last one exits, a piped thread sees the end of it.
+        let closed = output_guard.close();
+        let statuses = statuses?;
+        closed?;
+        Ok(statuses)
+    }
+
+    /// Spawn each command, connecting it to the previous one, and return the
+    /// earlier stages along with the last one, and what the stream feeding
+    /// the first one left behind.
+    fn spawn(self, stdout: Stdio) -> io::Result<(Vec<Child>, Child, StdioGuard)> {
+        if self.commands.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "pipeline has no commands",
+            ));
+        }
+
+        let last = self.commands.len() - 1;
+        let (stdin, stdin_guard) = match self.stdin {
+            Some(stdin) => stdin.try_into_stdio()?,
+            None => (Stdio::null(), StdioGuard::empty()),
+        };
+        let mut stdin = Some(stdin);
+        let mut stdout = Some(stdout);
+        let mut children: Vec<Child> = Vec::with_capacity(self.commands.len());
+        for (index, mut command) in self.commands.into_iter().enumerate() {
+            command.stdin(stdin.take().unwrap());
+            command.stdout(if index == last {
+                stdout.take().unwrap()
+            } else {
+                Stdio::piped()
+            });
+            let mut child = match command.spawn() {
+                Ok(child) => child,
+                Err(e) => {
+                    // Don't leave the earlier stages running, or unwaited-for.
+                    for mut child in children {
+                        let _ = child.kill();
+                        let _ = child.wait();
+                    }
+                    // Nor what fed the first stage. Drop the command first,
+                    // since it may still hold the first stage's input.
+                    drop(command);
+                    let _ = stdin_guard.close();
+                    return Err(e);
+                }
+            };
+            if index != last {
+                stdin = child.stdout.take().map(Stdio::from);
+            }
+            children.push(child);
+        }
+
+        let child = children.pop().unwrap();
+        Ok((children, child, stdin_guard))
+    }
+}
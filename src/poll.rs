@@ -0,0 +1,231 @@
+//! Waiting for readiness on multiple streams with `poll`.
+
+use crate::AsRawReadWriteFd;
+#[cfg(unix)]
+use std::os::unix::io::{AsRawFd, RawFd};
+#[cfg(target_os = "wasi")]
+use std::os::wasi::io::{AsRawFd, RawFd};
+use std::{
+    convert::TryInto,
+    io,
+    marker::PhantomData,
+    time::{Duration, Instant},
+};
+
+/// A stream to wait on with [`poll`], along with the kind of readiness to
+/// wait for.
+///
+/// This borrows the stream, so that its file descriptor remains valid.
+#[repr(transparent)]
+pub struct PollFd<'a> {
+    pollfd: libc::pollfd,
+    _phantom: PhantomData<&'a ()>,
+}
+
+impl<'a> PollFd<'a> {
+    /// Wait for `stream`, such as a [`ReadHandle`], to be readable.
+    ///
+    /// [`ReadHandle`]: crate::ReadHandle
+    #[inline]
+    pub fn readable<S: AsRawFd>(stream: &'a S) -> Self {
+        Self::new(stream.as_raw_fd(), libc::POLLIN)
+    }
+
+    /// Wait for `stream`, such as a [`WriteHandle`], to be writable.
+    ///
+    /// [`WriteHandle`]: crate::WriteHandle
+    #[inline]
+    pub fn writable<S: AsRawFd>(stream: &'a S) -> Self {
+        Self::new(stream.as_raw_fd(), libc::POLLOUT)
+    }
+
+    /// Wait for the reading side of `stream`, such as a [`ReadWriteHandle`],
+    /// to be readable.
+    ///
+    /// [`ReadWriteHandle`]: crate::ReadWriteHandle
+    #[inline]
+    pub fn read_side<S: AsRawReadWriteFd>(stream: &'a S) -> Self {
+        Self::new(stream.as_raw_read_fd(), libc::POLLIN)
+    }
+
+    /// Wait for the writing side of `stream`, such as a [`ReadWriteHandle`],
+    /// to be writable.
+    ///
+    /// [`ReadWriteHandle`]: crate::ReadWriteHandle
+    #[inline]
+    pub fn write_side<S: AsRawReadWriteFd>(stream: &'a S) -> Self {
+        Self::new(stream.as_raw_write_fd(), libc::POLLOUT)
+    }
+
+    #[inline]
+    fn new(fd: RawFd, events: libc::c_short) -> Self {
+        Self {
+            pollfd: libc::pollfd {
+                fd,
+                events,
+                revents: 0,
+            },
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Test whether the most recent call to [`poll`] found this stream
+    /// ready.
+    ///
+    /// A stream is also considered ready when the other end has hung up or an
+    /// error is pending, since the next I/O operation on it won't block.
+    #[inline]
+    pub fn is_ready(&self) -> bool {
+        self.pollfd.revents & (self.pollfd.events | libc::POLLHUP | libc::POLLERR) != 0
+    }
+
+    /// Test whether the most recent call to [`poll`] found that the other
+    /// end of this stream has hung up.
+    #[inline]
+    pub fn is_hangup(&self) -> bool {
+        self.pollfd.revents & libc::POLLHUP != 0
+    }
+}
+
+/// Wait until at least one of the streams in `fds` is ready, or until
+/// `timeout` elapses, and return the number of ready streams. With a
+/// `timeout` of `None`, this waits indefinitely.
+///
+/// Readiness is most useful with streams in non-blocking mode; see
+/// [`ReadHandle::set_nonblocking`] and friends.
+///
+/// If interrupted by a signal, this fails with
+/// [`std::io::ErrorKind::Interrupted`].
+///
+/// [`ReadHandle::set_nonblocking`]: crate::ReadHandle::set_nonblocking
+pub fn poll(fds: &mut [PollFd], timeout: Option<Duration>) -> io::Result<usize> {
+    let timeout = match timeout {
+        // Round up, so that we don't return before the timeout elapses.
+        Some(timeout) => timeout
+            .as_nanos()
+            .div_ceil(1_000_000)
+            .try_into()
+            .unwrap_or(libc::c_int::MAX),
+        None => -1,
+    };
+    for fd in fds.iter_mut() {
+        fd.pollfd.revents = 0;
+    }
+    // `PollFd` is `repr(transparent)`, so a slice of them can be passed as an
+    // array of `pollfd`s.
+    let n = unsafe {
+        libc::poll(
+            fds.as_mut_ptr().cast::<libc::pollfd>(),
+            fds.len() as libc::nfds_t,
+            timeout,
+        )
+    };
+    if n == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(n as usize)
+}
+
+/// Validate a timeout for `set_read_timeout` or `set_write_timeout`. As with
+/// `std`'s sockets, a zero timeout is rejected.
+pub(crate) fn check_timeout(timeout: Option<Duration>) -> io::Result<Option<Duration>> {
+    if timeout == Some(Duration::from_secs(0)) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "cannot set a 0 duration timeout",
+        ));
+    }
+    Ok(timeout)
+}
+
+/// Wait for `raw_fd` to become readable, failing with
+/// [`std::io::ErrorKind::TimedOut`] if `timeout` elapses first.
+pub(crate) fn wait_readable(raw_fd: RawFd, timeout: Duration) -> io::Result<()> {
+    wait_ready(PollFd::new(raw_fd, libc::POLLIN), timeout)
+}
+
+/// Wait for `raw_fd` to become writable and write to it with `write`,
+/// failing with [`std::io::ErrorKind::TimedOut`] if nothing can be written
+/// before `timeout` elapses.
+///
+/// Writability only promises room for some data, such as `PIPE_BUF` bytes
+/// for a pipe, so `write` is called with `O_NONBLOCK` set, to write as much
+/// as fits rather than blocking for the rest.
+pub(crate) fn write_with_timeout(
+    raw_fd: RawFd,
+    timeout: Duration,
+    mut write: impl FnMut() -> io::Result<usize>,
+) -> io::Result<usize> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        wait_ready(PollFd::new(raw_fd, libc::POLLOUT), remaining)?;
+        match with_nonblocking(raw_fd, &mut write) {
+            // Another writer may have filled the space first.
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                if Instant::now() >= deadline {
+                    return Err(timed_out());
+                }
+            }
+            result => return result,
+        }
+    }
+}
+
+fn wait_ready(fd: PollFd, timeout: Duration) -> io::Result<()> {
+    let deadline = Instant::now() + timeout;
+    let mut fds = [fd];
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        match poll(&mut fds, Some(remaining)) {
+            Ok(0) if remaining == Duration::from_secs(0) => return Err(timed_out()),
+            // `poll` may return early, as its timeout has millisecond
+            // granularity, so check the deadline again.
+            Ok(0) => {}
+            Ok(_) => return Ok(()),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Call `f` with `O_NONBLOCK` set on `raw_fd`, restoring its flags
+/// afterward.
+fn with_nonblocking<T>(raw_fd: RawFd, f: impl FnOnce() -> io::Result<T>) -> io::Result<T> {
+    let flags = unsafe { libc::fcntl(raw_fd, libc::F_GETFL) };
+    if flags == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    if flags & libc::O_NONBLOCK != 0 {
+        return f();
+    }
+    if unsafe { libc::fcntl(raw_fd, libc::F_SETFL, flags | libc::O_NONBLOCK) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    let result = f();
+    // If restoring the flags fails, still report what `f` did, as data may
+    // have been written.
+    unsafe { libc::fcntl(raw_fd, libc::F_SETFL, flags) };
+    result
+}
+
+fn timed_out() -> io::Error {
+    io::Error::new(io::ErrorKind::TimedOut, "timed out")
+}
+
+/// Set or clear `O_NONBLOCK` on `raw_fd`.
+pub(crate) fn set_nonblocking(raw_fd: RawFd, nonblocking: bool) -> io::Result<()> {
+    let flags = unsafe { libc::fcntl(raw_fd, libc::F_GETFL) };
+    if flags == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    let new_flags = if nonblocking {
+        flags | libc::O_NONBLOCK
+    } else {
+        flags & !libc::O_NONBLOCK
+    };
+    if new_flags != flags && unsafe { libc::fcntl(raw_fd, libc::F_SETFL, new_flags) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
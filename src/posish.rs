@@ -5,31 +5,58 @@
 //! and we can implement `AsRawFd`. We do need to hold onto additional
 //! resources to keep the file descriptor valid through.
 
+#[cfg(unix)]
+use crate::child::StdioGuard;
+#[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+use crate::child::{discard_stdout, not_a_child, spawn_stdout_piped, ChildProcess, StderrMode};
+#[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+use crate::piped_thread::{spawn_writer, Flusher, JoinGuard};
+#[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+use crate::tee::Tee;
+#[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
 use crate::{
-    lockers::{StdinLocker, StdoutLocker},
-    AsRawReadWriteFd,
+    duplex_thread::{spawn_duplex, DuplexThread},
+    ReadWrite,
+};
+use crate::{
+    kind::HandleKind,
+    lockers::{StderrLocker, StdinLocker, StdoutLocker},
+    poll::{check_timeout, set_nonblocking, wait_readable, write_with_timeout},
+    seekable::{is_seekable, not_seekable, read_at, read_vectored_at, write_at, write_vectored_at},
+    AsRawReadWriteFd, AsReadWriteFd,
 };
-#[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
-use os_pipe::{pipe, PipeReader, PipeWriter};
 #[cfg(unix)]
-use std::os::unix::{
-    io::{AsRawFd, FromRawFd, RawFd},
-    net::UnixStream,
+use crate::{
+    pty::{drain_pty_master, is_hangup, open_pty, set_controlling_terminal},
+    terminal::{terminal_size, Mode, TerminalModeGuard},
 };
+#[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+use os_pipe::{pipe, PipeReader, PipeWriter};
 #[cfg(target_os = "wasi")]
-use std::os::wasi::io::{AsRawFd, FromRawFd, RawFd};
+use std::os::wasi::io::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd, RawFd};
 use std::{
+    convert::TryFrom,
     fmt::{self, Arguments, Debug},
-    fs::File,
-    io::{self, IoSlice, IoSliceMut, Read, Write},
-    mem::ManuallyDrop,
-    net::TcpStream,
+    fs::{File, Metadata},
+    io::{self, IoSlice, IoSliceMut, IsTerminal, Read, Seek, SeekFrom, Write},
+    mem::{self, ManuallyDrop, MaybeUninit},
+    net::{Shutdown, TcpStream},
+    sync::Arc,
+    time::Duration,
 };
 #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
 use std::{
     io::{copy, Cursor},
-    process::{Child, ChildStderr, ChildStdin, ChildStdout, Command, Stdio},
-    thread::{self, JoinHandle},
+    process::{Child, ChildStderr, ChildStdin, ChildStdout, Command, ExitStatus, Stdio},
+    thread,
+};
+#[cfg(unix)]
+use std::{
+    os::unix::{
+        io::{AsFd, AsRawFd, BorrowedFd, FromRawFd, IntoRawFd, OwnedFd, RawFd},
+        net::UnixStream,
+    },
+    path::PathBuf,
 };
 
 /// An unbuffered and unlocked input byte stream, abstracted over the source of
@@ -45,6 +72,7 @@ use std::{
 pub struct ReadHandle {
     descriptor: ManuallyDrop<File>,
     resources: ReadResources,
+    read_timeout: Option<Duration>,
 }
 
 /// An unbuffered and unlocked output byte stream, abstracted over the
@@ -62,6 +90,7 @@ pub struct ReadHandle {
 pub struct WriteHandle {
     descriptor: ManuallyDrop<File>,
     resources: WriteResources,
+    write_timeout: Option<Duration>,
 }
 
 /// An unbuffered and unlocked interactive combination input and output stream.
@@ -79,9 +108,12 @@ pub struct ReadWriteHandle {
     read_descriptor: ManuallyDrop<File>,
     write_descriptor: ManuallyDrop<File>,
     resources: ReadWriteResources,
+    read_timeout: Option<Duration>,
+    write_timeout: Option<Duration>,
 }
 
 /// Additional resources that need to be held in order to keep the stream live.
+#[allow(dead_code)] // Resources are held for their `Drop` impls, not read.
 enum ReadResources {
     File(File),
     TcpStream(TcpStream),
@@ -90,17 +122,22 @@ enum ReadResources {
     #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
     PipeReader(PipeReader),
     Stdin(StdinLocker),
+    // Tuple fields are dropped in order, so the pipe is closed before the
+    // thread is waited for.
     #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
-    PipedThread(Option<(PipeReader, JoinHandle<io::Result<()>>)>),
+    PipedThread(Option<(PipeReader, JoinGuard)>),
     #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
-    Child(Child),
+    Child(ChildProcess),
     #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
     ChildStdout(ChildStdout),
     #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
     ChildStderr(ChildStderr),
+    // Shared with the write half of a split `ReadWriteHandle`.
+    Shared(Arc<ReadWriteResources>),
 }
 
 /// Additional resources that need to be held in order to keep the stream live.
+#[allow(dead_code)] // Resources are held for their `Drop` impls, not read.
 enum WriteResources {
     File(File),
     TcpStream(TcpStream),
@@ -109,27 +146,40 @@ enum WriteResources {
     #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
     PipeWriter(PipeWriter),
     Stdout(StdoutLocker),
+    Stderr(StderrLocker),
+    // As above, the pipe is closed before the thread is waited for.
     #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
-    PipedThread(Option<(PipeWriter, JoinHandle<io::Result<Box<dyn Write + Send>>>)>),
+    PipedThread(Option<(PipeWriter, JoinGuard)>, Flusher),
     #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
-    Child(Child),
+    Child(ChildProcess),
     #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
     ChildStdin(ChildStdin),
+    // Shared with the read half of a split `ReadWriteHandle`.
+    Shared(Arc<ReadWriteResources>),
 }
 
 /// Additional resources that need to be held in order to keep the stream live.
+#[allow(dead_code)] // Resources are held for their `Drop` impls, not read.
 enum ReadWriteResources {
     #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
     PipeReaderWriter((PipeReader, PipeWriter)),
     StdinStdout((StdinLocker, StdoutLocker)),
+    StdinStderr((StdinLocker, StderrLocker)),
     #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
-    Child(Child),
+    Child(ChildProcess),
     #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
     ChildStdoutStdin((ChildStdout, ChildStdin)),
     CharDevice(File),
     TcpStream(TcpStream),
     #[cfg(unix)]
     UnixStream(UnixStream),
+    // As above, the pipes are closed before the thread is waited for.
+    #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+    PipedThread(Option<(PipeReader, PipeWriter, DuplexThread)>),
+    #[cfg(unix)]
+    Pty(File),
+    #[cfg(unix)]
+    PtyChild((File, ChildProcess)),
 }
 
 impl ReadHandle {
@@ -151,6 +201,7 @@ impl ReadHandle {
         Ok(Self {
             descriptor: ManuallyDrop::new(unsafe { File::from_raw_fd(raw_fd) }),
             resources: ReadResources::Stdin(stdin_locker),
+            read_timeout: None,
         })
     }
 
@@ -161,6 +212,7 @@ impl ReadHandle {
         Self {
             descriptor: ManuallyDrop::new(unsafe { File::from_raw_fd(raw_fd) }),
             resources: ReadResources::File(file),
+            read_timeout: None,
         }
     }
 
@@ -171,6 +223,7 @@ impl ReadHandle {
         Self {
             descriptor: ManuallyDrop::new(unsafe { File::from_raw_fd(raw_fd) }),
             resources: ReadResources::TcpStream(tcp_stream),
+            read_timeout: None,
         }
     }
 
@@ -182,6 +235,7 @@ impl ReadHandle {
         Self {
             descriptor: ManuallyDrop::new(unsafe { File::from_raw_fd(raw_fd) }),
             resources: ReadResources::UnixStream(unix_stream),
+            read_timeout: None,
         }
     }
 
@@ -193,20 +247,55 @@ impl ReadHandle {
         Self {
             descriptor: ManuallyDrop::new(unsafe { File::from_raw_fd(raw_fd) }),
             resources: ReadResources::PipeReader(pipe_reader),
+            read_timeout: None,
         }
     }
 
     /// Spawn the given command and read from its standard output.
+    ///
+    /// The child process can be waited for with [`wait`] and [`try_wait`],
+    /// and with [`set_check_exit_status`], reaching the end of the stream
+    /// waits for it and reports an unsuccessful exit as an error.
+    ///
+    /// [`wait`]: Self::wait
+    /// [`try_wait`]: Self::try_wait
+    /// [`set_check_exit_status`]: Self::set_check_exit_status
+    #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
     pub fn read_from_command(mut command: Command) -> io::Result<Self> {
         command.stdin(Stdio::null());
         command.stdout(Stdio::piped());
-        let mut child = command.spawn()?;
-        let child_stdout = child.stdout.take().unwrap();
-        let raw_fd = child_stdout.as_raw_fd();
-        Ok(Self {
+        let child = command.spawn()?;
+        Ok(Self::pipeline(Vec::new(), child, StdioGuard::empty()))
+    }
+
+    /// Like [`read_from_command`], but with the child's standard error
+    /// directed according to `stderr`. With [`StderrMode::Merge`], this
+    /// stream reads both the child's standard output and standard error.
+    ///
+    /// [`read_from_command`]: Self::read_from_command
+    #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+    pub fn read_from_command_with_stderr(
+        mut command: Command,
+        stderr: StderrMode,
+    ) -> io::Result<Self> {
+        command.stdin(Stdio::null());
+        let child = spawn_stdout_piped(command, stderr)?;
+        Ok(Self::pipeline(Vec::new(), child, StdioGuard::empty()))
+    }
+
+    /// Read from the piped standard output of the last stage of a pipeline,
+    /// holding the earlier stages, and what the stream feeding the first
+    /// stage left behind, so that they're waited for along with it.
+    #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+    pub(crate) fn pipeline(upstream: Vec<Child>, child: Child, stdin_guard: StdioGuard) -> Self {
+        let raw_fd = child.stdout.as_ref().unwrap().as_raw_fd();
+        let mut child = ChildProcess::pipeline(upstream, child);
+        child.set_stdin_guard(stdin_guard);
+        Self {
             descriptor: ManuallyDrop::new(unsafe { File::from_raw_fd(raw_fd) }),
             resources: ReadResources::Child(child),
-        })
+            read_timeout: None,
+        }
     }
 
     /// Read from a child process' standard output, taking ownership of it.
@@ -217,6 +306,7 @@ impl ReadHandle {
         Self {
             descriptor: ManuallyDrop::new(unsafe { File::from_raw_fd(raw_fd) }),
             resources: ReadResources::ChildStdout(child_stdout),
+            read_timeout: None,
         }
     }
 
@@ -228,6 +318,7 @@ impl ReadHandle {
         Self {
             descriptor: ManuallyDrop::new(unsafe { File::from_raw_fd(raw_fd) }),
             resources: ReadResources::ChildStderr(child_stderr),
+            read_timeout: None,
         }
     }
 
@@ -243,10 +334,26 @@ impl ReadHandle {
         let raw_fd = pipe_reader.as_raw_fd();
         Ok(Self {
             descriptor: ManuallyDrop::new(unsafe { File::from_raw_fd(raw_fd) }),
-            resources: ReadResources::PipedThread(Some((pipe_reader, join_handle))),
+            resources: ReadResources::PipedThread(Some((pipe_reader, JoinGuard::new(join_handle)))),
+            read_timeout: None,
         })
     }
 
+    /// Read from this stream, while also writing everything read from it to
+    /// each of `sinks`, such as to log the raw bytes of a stream while it's
+    /// being processed.
+    ///
+    /// This launches a thread which reads from this stream and writes to the
+    /// sinks and to a pipe, which the returned stream reads from. The sinks
+    /// are closed at the end of the stream, and an error closing one, such
+    /// as an unsuccessful exit of a child process with exit-status checking
+    /// enabled, is reported when closing the returned stream. The thread stops at the first
+    /// error reading from this stream or writing to a sink.
+    #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+    pub fn tee(self, sinks: Vec<WriteHandle>) -> io::Result<Self> {
+        Self::piped_thread(Box::new(Tee::new(self, sinks)))
+    }
+
     /// Read from the given string.
     #[inline]
     #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
@@ -269,6 +376,7 @@ impl ReadHandle {
             return Ok(Self {
                 descriptor: ManuallyDrop::new(unsafe { File::from_raw_fd(raw_fd) }),
                 resources: ReadResources::PipeReader(pipe_reader),
+                read_timeout: None,
             });
         }
 
@@ -276,16 +384,494 @@ impl ReadHandle {
         Self::piped_thread(Box::new(Cursor::new(bytes.to_vec())))
     }
 
-    fn map_err(&mut self, e: io::Error) -> io::Error {
+    /// If this stream is backed by a child process spawned by
+    /// [`read_from_command`], enable or disable reporting an unsuccessful
+    /// exit of the child as an error when the end of the stream is reached.
+    ///
+    /// Fails with [`std::io::ErrorKind::Unsupported`] if this stream is not
+    /// backed by a child process.
+    ///
+    /// [`read_from_command`]: Self::read_from_command
+    #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+    pub fn set_check_exit_status(&mut self, check: bool) -> io::Result<()> {
+        match &mut self.resources {
+            ReadResources::Child(child) => {
+                child.set_check_exit_status(check);
+                Ok(())
+            }
+            _ => Err(not_a_child()),
+        }
+    }
+
+    /// If this stream is backed by a child process spawned by
+    /// [`read_from_command`], wait for it to exit and return its exit status.
+    ///
+    /// The child's output isn't consumed while waiting, so this should
+    /// typically be called after reading to the end of the stream.
+    ///
+    /// Fails with [`std::io::ErrorKind::Unsupported`] if this stream is not
+    /// backed by a child process.
+    ///
+    /// [`read_from_command`]: Self::read_from_command
+    #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+    pub fn wait(mut self) -> io::Result<ExitStatus> {
+        match &mut self.resources {
+            ReadResources::Child(child) => child.wait(),
+            _ => Err(not_a_child()),
+        }
+    }
+
+    /// If this stream is backed by a child process spawned by
+    /// [`read_from_command`], or by the processes of a [`Pipeline`], wait for
+    /// all of them to exit and return their exit statuses, in pipeline order.
+    ///
+    /// Like [`wait`], the output isn't consumed while waiting.
+    ///
+    /// Fails with [`std::io::ErrorKind::Unsupported`] if this stream is not
+    /// backed by a child process.
+    ///
+    /// [`read_from_command`]: Self::read_from_command
+    /// [`Pipeline`]: crate::Pipeline
+    /// [`wait`]: Self::wait
+    #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+    pub fn wait_all(mut self) -> io::Result<Vec<ExitStatus>> {
+        match &mut self.resources {
+            ReadResources::Child(child) => child.wait_all(),
+            _ => Err(not_a_child()),
+        }
+    }
+
+    /// If this stream is backed by a child process spawned by
+    /// [`read_from_command`], return its exit status if it has exited,
+    /// without blocking.
+    ///
+    /// Fails with [`std::io::ErrorKind::Unsupported`] if this stream is not
+    /// backed by a child process.
+    ///
+    /// [`read_from_command`]: Self::read_from_command
+    #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+    pub fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> {
+        match &mut self.resources {
+            ReadResources::Child(child) => child.try_wait(),
+            _ => Err(not_a_child()),
+        }
+    }
+
+    /// If this stream is backed by a child process whose standard error was
+    /// captured with [`StderrMode::Capture`], take a stream which reads from
+    /// it.
+    ///
+    /// Returns `None` if standard error wasn't captured, or if it has already
+    /// been taken.
+    #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+    pub fn take_stderr(&mut self) -> Option<ReadHandle> {
+        match &mut self.resources {
+            ReadResources::Child(child) => child.take_stderr().map(ReadHandle::child_stderr),
+            _ => None,
+        }
+    }
+
+    /// Move this stream into or out of non-blocking mode.
+    ///
+    /// In non-blocking mode, reads which would block fail with
+    /// [`std::io::ErrorKind::WouldBlock`] instead. Use [`poll`] to wait for
+    /// the stream to become readable.
+    ///
+    /// This sets `O_NONBLOCK` on the open file description, which is shared
+    /// with any duplicates of the file descriptor, including ones held by
+    /// other processes, as is often the case for standard input.
+    ///
+    /// [`poll`]: crate::poll
+    #[inline]
+    pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        set_nonblocking(self.as_raw_fd(), nonblocking)
+    }
+
+    /// Set the timeout for reads from this stream.
+    ///
+    /// If a read can't make progress within the timeout, it fails with
+    /// [`std::io::ErrorKind::TimedOut`]. With a timeout of `None`, reads
+    /// may block indefinitely. This works for all kinds of streams,
+    /// including pipes, terminals, and piped threads, and not just sockets.
+    /// Timeouts aren't available on Windows.
+    ///
+    /// Fails with [`std::io::ErrorKind::InvalidInput`] if `timeout` is zero.
+    #[inline]
+    pub fn set_read_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()> {
+        self.read_timeout = check_timeout(timeout)?;
+        Ok(())
+    }
+
+    /// Return the timeout for reads from this stream.
+    #[inline]
+    pub fn read_timeout(&self) -> Option<Duration> {
+        self.read_timeout
+    }
+
+    /// If a read timeout is set, wait for the stream to become readable.
+    fn ready_to_read(&self) -> io::Result<()> {
+        match self.read_timeout {
+            Some(timeout) => wait_readable(self.as_raw_fd(), timeout),
+            None => Ok(()),
+        }
+    }
+
+    /// Test whether this stream is the read half of the master side of a
+    /// pseudo-terminal.
+    #[cfg(unix)]
+    #[inline]
+    fn is_pty(&self) -> bool {
+        self.kind() == HandleKind::Pty
+    }
+
+    /// Test whether the `Read` functions which loop should be forwarded to
+    /// `read`, to apply the read timeout, or to detect the end of a
+    /// pseudo-terminal's stream.
+    #[inline]
+    fn by_read(&self) -> bool {
+        #[cfg(unix)]
+        if self.is_pty() {
+            return true;
+        }
+        self.read_timeout.is_some()
+    }
+
+    /// Create a new stream for the same underlying I/O object, such as to
+    /// hand the same input to several readers.
+    ///
+    /// This duplicates the file descriptor for files, sockets, and pipes,
+    /// including a child process' standard output and error. The halves of a
+    /// split stream share its resources instead. The new stream has the same
+    /// read timeout.
+    ///
+    /// Fails with [`std::io::ErrorKind::Unsupported`] for other streams, such
+    /// as standard input, piped threads, and streams which own a child
+    /// process.
+    pub fn try_clone(&self) -> io::Result<Self> {
+        let mut clone = match &self.resources {
+            ReadResources::File(file) => Self::file(file.try_clone()?),
+            ReadResources::TcpStream(tcp_stream) => Self::tcp_stream(tcp_stream.try_clone()?),
+            #[cfg(unix)]
+            ReadResources::UnixStream(unix_stream) => Self::unix_stream(unix_stream.try_clone()?),
+            #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+            ReadResources::PipeReader(pipe_reader) => Self::pipe_reader(pipe_reader.try_clone()?),
+            #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+            ReadResources::ChildStdout(child_stdout) => Self::child_stdout(ChildStdout::from(
+                child_stdout.as_fd().try_clone_to_owned()?,
+            )),
+            #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+            ReadResources::ChildStderr(child_stderr) => Self::child_stderr(ChildStderr::from(
+                child_stderr.as_fd().try_clone_to_owned()?,
+            )),
+            ReadResources::Shared(resources) => Self {
+                descriptor: ManuallyDrop::new(unsafe { File::from_raw_fd(self.as_raw_fd()) }),
+                resources: ReadResources::Shared(Arc::clone(resources)),
+                read_timeout: None,
+            },
+            _ => return Err(not_cloneable()),
+        };
+        clone.read_timeout = self.read_timeout;
+        Ok(clone)
+    }
+
+    /// Return what kind of I/O object backs this stream.
+    pub fn kind(&self) -> HandleKind {
+        match &self.resources {
+            ReadResources::File(_) => HandleKind::File,
+            ReadResources::TcpStream(_) => HandleKind::TcpStream,
+            #[cfg(unix)]
+            ReadResources::UnixStream(_) => HandleKind::UnixStream,
+            #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+            ReadResources::PipeReader(_) => HandleKind::Pipe,
+            ReadResources::Stdin(_) => HandleKind::Stdio,
+            #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+            ReadResources::PipedThread(_) => HandleKind::PipedThread,
+            #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+            ReadResources::Child(_)
+            | ReadResources::ChildStdout(_)
+            | ReadResources::ChildStderr(_) => HandleKind::Child,
+            ReadResources::Shared(resources) => resources.kind(),
+        }
+    }
+
+    /// Query metadata about the file this stream reads from or writes to,
+    /// such as whether it's a regular file, using `fstat`.
+    #[inline]
+    pub fn metadata(&self) -> io::Result<Metadata> {
+        self.descriptor.metadata()
+    }
+
+    /// Test whether this stream is a terminal.
+    #[inline]
+    pub fn is_terminal(&self) -> bool {
+        self.descriptor.is_terminal()
+    }
+
+    /// Return the size of the terminal this stream refers to, as columns and
+    /// rows.
+    ///
+    /// Fails if this stream isn't a terminal.
+    #[cfg(unix)]
+    #[inline]
+    pub fn terminal_size(&self) -> io::Result<(u16, u16)> {
+        terminal_size(self.as_raw_fd())
+    }
+
+    /// Put the terminal this stream refers to in raw mode, where input is
+    /// available byte by byte, without echoing, line editing, or signal
+    /// characters, and output isn't post-processed, until the returned guard
+    /// is dropped.
+    ///
+    /// This stream can be used through the guard.
+    #[cfg(unix)]
+    pub fn raw_mode(&mut self) -> io::Result<TerminalModeGuard<'_, Self>> {
+        let raw_fd = self.as_raw_fd();
+        TerminalModeGuard::new(self, raw_fd, Mode::Raw)
+    }
+
+    /// Turn off echoing on the terminal this stream refers to, such as for
+    /// reading a password, until the returned guard is dropped.
+    ///
+    /// This stream can be used through the guard.
+    #[cfg(unix)]
+    pub fn no_echo_mode(&mut self) -> io::Result<TerminalModeGuard<'_, Self>> {
+        let raw_fd = self.as_raw_fd();
+        TerminalModeGuard::new(self, raw_fd, Mode::NoEcho)
+    }
+
+    /// Test whether this stream is backed by a regular file, and so supports
+    /// seeking. For other streams, such as pipes, sockets, and terminals,
+    /// [`Seek`] functions fail with [`std::io::ErrorKind::Unsupported`].
+    ///
+    /// [`Seek`]: std::io::Seek
+    #[inline]
+    pub fn is_seekable(&self) -> bool {
+        is_seekable(&self.descriptor)
+    }
+
+    /// Read from this stream at `offset`, returning the number of bytes read.
+    ///
+    /// This doesn't move the cursor, so a stream can be shared between
+    /// threads which each read at their own offsets.
+    ///
+    /// This fails with [`std::io::ErrorKind::Unsupported`] unless the stream
+    /// is backed by a regular file.
+    #[inline]
+    pub fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        read_at(&self.descriptor, buf, offset)
+    }
+
+    /// Like [`read_at`], but reads into a slice of buffers.
+    ///
+    /// [`read_at`]: Self::read_at
+    #[inline]
+    pub fn read_vectored_at(&self, bufs: &mut [IoSliceMut], offset: u64) -> io::Result<usize> {
+        read_vectored_at(&self.descriptor, bufs, offset)
+    }
+
+    /// Close the stream, reporting any error that would otherwise be
+    /// discarded when it's dropped.
+    ///
+    /// If this stream is a piped thread, this waits for the thread to exit
+    /// and reports an error it encountered, other than a broken pipe caused
+    /// by closing the stream early. If this stream is backed by a child
+    /// process and [`set_check_exit_status`] has enabled exit-status
+    /// checking, this waits for the child to exit and reports an
+    /// unsuccessful exit as an error.
+    ///
+    /// [`set_check_exit_status`]: Self::set_check_exit_status
+    pub fn close(mut self) -> io::Result<()> {
+        match &mut self.resources {
+            #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+            ReadResources::PipedThread(piped_thread) => match piped_thread.take() {
+                Some((pipe_reader, mut join_guard)) => {
+                    drop(pipe_reader);
+                    match join_guard.join() {
+                        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+                        result => result,
+                    }
+                }
+                None => Ok(()),
+            },
+            // `self` is consumed, so closing the child's stdout here is fine.
+            #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+            ReadResources::Child(child) => {
+                child.close_stdout();
+                child.check_exit_status()
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Called when the end of the stream has been reached.
+    fn end_of_stream(&mut self) -> io::Result<()> {
         match &mut self.resources {
+            // The thread has closed its end of the pipe, so it's done.
+            #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+            ReadResources::PipedThread(_) => self.join_piped_thread(),
+            #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+            ReadResources::Child(child) => child.check_exit_status(),
+            _ => Ok(()),
+        }
+    }
+
+    /// Recover the file this stream reads from.
+    ///
+    /// Returns `Err(self)` if this stream isn't backed by one.
+    pub fn into_file(self) -> Result<File, Self> {
+        match self.resources {
+            ReadResources::File(resource) => Ok(resource),
+            _ => Err(self),
+        }
+    }
+
+    /// Recover the TCP socket this stream reads from.
+    ///
+    /// Returns `Err(self)` if this stream isn't backed by one.
+    pub fn into_tcp_stream(self) -> Result<TcpStream, Self> {
+        match self.resources {
+            ReadResources::TcpStream(resource) => Ok(resource),
+            _ => Err(self),
+        }
+    }
+
+    /// Recover the Unix-domain socket this stream reads from.
+    ///
+    /// Returns `Err(self)` if this stream isn't backed by one.
+    #[cfg(unix)]
+    pub fn into_unix_stream(self) -> Result<UnixStream, Self> {
+        match self.resources {
+            ReadResources::UnixStream(resource) => Ok(resource),
+            _ => Err(self),
+        }
+    }
+
+    /// Recover the pipe this stream reads from.
+    ///
+    /// Returns `Err(self)` if this stream isn't backed by one.
+    #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+    pub fn into_pipe_reader(self) -> Result<PipeReader, Self> {
+        match self.resources {
+            ReadResources::PipeReader(resource) => Ok(resource),
+            _ => Err(self),
+        }
+    }
+
+    /// Recover the child process' standard output this stream reads from.
+    ///
+    /// Returns `Err(self)` if this stream isn't backed by one.
+    #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+    pub fn into_child_stdout(self) -> Result<ChildStdout, Self> {
+        match self.resources {
+            ReadResources::ChildStdout(resource) => Ok(resource),
+            _ => Err(self),
+        }
+    }
+
+    /// Recover the child process' standard error this stream reads from.
+    ///
+    /// Returns `Err(self)` if this stream isn't backed by one.
+    #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+    pub fn into_child_stderr(self) -> Result<ChildStderr, Self> {
+        match self.resources {
+            ReadResources::ChildStderr(resource) => Ok(resource),
+            _ => Err(self),
+        }
+    }
+
+    /// Recover the child process spawned by [`read_from_command`], with its
+    /// standard output in [`Child::stdout`].
+    ///
+    /// Returns `Err(self)` if this stream isn't backed by one. This includes
+    /// a stream returned by [`Pipeline::read`], since a single [`Child`]
+    /// can't hold the earlier stages of the pipeline, or the stream feeding
+    /// its first stage.
+    ///
+    /// [`read_from_command`]: Self::read_from_command
+    /// [`Child::stdout`]: std::process::Child::stdout
+    /// [`Pipeline::read`]: crate::Pipeline::read
+    #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+    pub fn into_child(self) -> Result<Child, Self> {
+        match self.resources {
+            ReadResources::Child(child) if !child.is_pipeline() => Ok(child.into_child()),
+            _ => Err(self),
+        }
+    }
+
+    /// Recover the file descriptor this stream reads from, when the stream
+    /// owns it outright, such as for a file, socket, or pipe.
+    ///
+    /// Returns `Err(self)` if the stream holds other resources which the
+    /// descriptor depends on, such as a thread, a child process, or a lock on
+    /// standard input.
+    pub fn into_owned_fd(self) -> Result<OwnedFd, Self> {
+        match self.resources {
+            ReadResources::File(resource) => Ok(resource.into()),
+            ReadResources::TcpStream(resource) => Ok(resource.into()),
+            #[cfg(unix)]
+            ReadResources::UnixStream(resource) => Ok(resource.into()),
+            #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+            ReadResources::PipeReader(resource) => {
+                Ok(unsafe { OwnedFd::from_raw_fd(resource.into_raw_fd()) })
+            }
+            #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+            ReadResources::ChildStdout(resource) => Ok(resource.into()),
             #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
-            ReadResources::PipedThread(piped_thread) => {
-                let (pipe_reader, join_handle) = piped_thread.take().unwrap();
-                drop(pipe_reader);
-                join_handle.join().unwrap().unwrap_err()
+            ReadResources::ChildStderr(resource) => Ok(resource.into()),
+            _ => Err(self),
+        }
+    }
+
+    /// Convert this stream into a [`Stdio`], for use as a child process'
+    /// standard input, such as with [`Command::stdin`], along with a guard
+    /// holding what the child depends on.
+    ///
+    /// If this stream is a piped thread, the guard holds the thread, which
+    /// keeps feeding the child. If it's backed by a child process, the guard
+    /// holds that process, whose output the child reads. If it's our own
+    /// standard input, the child inherits it. If its descriptor is shared,
+    /// such as for the read half of a split [`ReadWriteHandle`], it's
+    /// duplicated.
+    ///
+    /// Use [`StdioGuard::close`] to wait for the thread or process and
+    /// observe its errors.
+    #[cfg(unix)]
+    pub fn try_into_stdio(self) -> io::Result<(Stdio, StdioGuard)> {
+        let handle = match self.into_owned_fd() {
+            Ok(fd) => return Ok((fd.into(), StdioGuard::empty())),
+            Err(handle) => handle,
+        };
+        Ok(match handle.resources {
+            ReadResources::Stdin(_) => (Stdio::inherit(), StdioGuard::empty()),
+            ReadResources::PipedThread(Some((pipe_reader, join_guard))) => {
+                (pipe_reader.into(), StdioGuard::read_thread(join_guard))
+            }
+            ReadResources::Child(mut child) => {
+                let child_stdout = child.take_stdout();
+                (
+                    child_stdout.map_or_else(Stdio::null, Stdio::from),
+                    StdioGuard::child(child),
+                )
+            }
+            _ => (handle.descriptor.try_clone()?.into(), StdioGuard::empty()),
+        })
+    }
+
+    /// Wait for a piped thread to exit and report its result, leaving just
+    /// the pipe, which is at its end.
+    #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+    fn join_piped_thread(&mut self) -> io::Result<()> {
+        if let ReadResources::PipedThread(piped_thread) = &mut self.resources {
+            if let Some((pipe_reader, mut join_guard)) = piped_thread.take() {
+                self.resources = ReadResources::PipeReader(pipe_reader);
+                return join_guard.join();
             }
-            _ => e,
         }
+        Ok(())
+    }
+
+    fn map_err(&mut self, e: io::Error) -> io::Error {
+        e
     }
 }
 
@@ -309,6 +895,30 @@ impl WriteHandle {
         Ok(Self {
             descriptor: ManuallyDrop::new(unsafe { File::from_raw_fd(raw_fd) }),
             resources: WriteResources::Stdout(stdout_locker),
+            write_timeout: None,
+        })
+    }
+
+    /// Write to standard error.
+    ///
+    /// Unlike [`std::io::stderr`], this `stderr` returns a stream which is
+    /// unlocked.
+    ///
+    /// This acquires a [`std::io::StderrLock`] (in a non-recursive way) to
+    /// prevent accesses to `std::io::Stderr`, such as by `eprintln!`, while
+    /// this is live, and fails if a `WriteHandle` or `ReadWriteHandle` for
+    /// standard error already exists.
+    ///
+    /// [`std::io::stderr`]: https://doc.rust-lang.org/std/io/fn.stderr.html`
+    /// [`std::io::StderrLock`]: https://doc.rust-lang.org/std/io/struct.StderrLock.html
+    #[inline]
+    pub fn stderr() -> io::Result<Self> {
+        let stderr_locker = StderrLocker::new()?;
+        let raw_fd = stderr_locker.as_raw_fd();
+        Ok(Self {
+            descriptor: ManuallyDrop::new(unsafe { File::from_raw_fd(raw_fd) }),
+            resources: WriteResources::Stderr(stderr_locker),
+            write_timeout: None,
         })
     }
 
@@ -319,6 +929,7 @@ impl WriteHandle {
         Self {
             descriptor: ManuallyDrop::new(unsafe { File::from_raw_fd(raw_fd) }),
             resources: WriteResources::File(file),
+            write_timeout: None,
         }
     }
 
@@ -329,6 +940,7 @@ impl WriteHandle {
         Self {
             descriptor: ManuallyDrop::new(unsafe { File::from_raw_fd(raw_fd) }),
             resources: WriteResources::TcpStream(tcp_stream),
+            write_timeout: None,
         }
     }
 
@@ -340,6 +952,7 @@ impl WriteHandle {
         Self {
             descriptor: ManuallyDrop::new(unsafe { File::from_raw_fd(raw_fd) }),
             resources: WriteResources::UnixStream(unix_stream),
+            write_timeout: None,
         }
     }
 
@@ -351,6 +964,7 @@ impl WriteHandle {
         Self {
             descriptor: ManuallyDrop::new(unsafe { File::from_raw_fd(raw_fd) }),
             resources: WriteResources::PipeWriter(pipe_writer),
+            write_timeout: None,
         }
     }
 
@@ -364,34 +978,60 @@ impl WriteHandle {
     /// synchronizes with the thread to ensure that is has completed writing
     /// all pending output.
     #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
-    pub fn piped_thread(mut boxed_write: Box<dyn Write + Send>) -> io::Result<Self> {
-        let (mut pipe_reader, pipe_writer) = pipe()?;
-        let join_handle = thread::Builder::new()
-            .name("piped thread for boxed writer".to_owned())
-            .spawn(move || {
-                copy(&mut pipe_reader, &mut *boxed_write)?;
-                boxed_write.flush()?;
-                Ok(boxed_write)
-            })?;
+    pub fn piped_thread(boxed_write: Box<dyn Write + Send>) -> io::Result<Self> {
+        let (pipe_writer, join_guard, flusher) = spawn_writer(boxed_write)?;
         let raw_fd = pipe_writer.as_raw_fd();
         Ok(Self {
             descriptor: ManuallyDrop::new(unsafe { File::from_raw_fd(raw_fd) }),
-            resources: WriteResources::PipedThread(Some((pipe_writer, join_handle))),
+            resources: WriteResources::PipedThread(Some((pipe_writer, join_guard)), flusher),
+            write_timeout: None,
         })
     }
 
     /// Spawn the given command and write to its standard input. Its standard
     /// output is redirected to `Stdio::null()`.
+    ///
+    /// The child process can be waited for with [`wait`] and [`try_wait`],
+    /// and with [`set_check_exit_status`], [`close`] waits for it and reports
+    /// an unsuccessful exit as an error.
+    ///
+    /// [`wait`]: Self::wait
+    /// [`try_wait`]: Self::try_wait
+    /// [`set_check_exit_status`]: Self::set_check_exit_status
+    /// [`close`]: Self::close
+    #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
     pub fn write_to_command(mut command: Command) -> io::Result<Self> {
         command.stdin(Stdio::piped());
         command.stdout(Stdio::null());
-        let mut child = command.spawn()?;
-        let child_stdin = child.stdin.take().unwrap();
-        let raw_fd = child_stdin.as_raw_fd();
-        Ok(Self {
+        let child = command.spawn()?;
+        Ok(Self::child_process(child))
+    }
+
+    /// Like [`write_to_command`], but with the child's standard error
+    /// directed according to `stderr`. Its standard output is still
+    /// discarded, so [`StderrMode::Merge`] discards standard error too.
+    ///
+    /// [`write_to_command`]: Self::write_to_command
+    #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+    pub fn write_to_command_with_stderr(
+        mut command: Command,
+        stderr: StderrMode,
+    ) -> io::Result<Self> {
+        command.stdin(Stdio::piped());
+        discard_stdout(&mut command, stderr);
+        let child = command.spawn()?;
+        Ok(Self::child_process(child))
+    }
+
+    /// Write to the piped standard input of a child process.
+    #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+    fn child_process(child: Child) -> Self {
+        let raw_fd = child.stdin.as_ref().unwrap().as_raw_fd();
+        Self {
             descriptor: ManuallyDrop::new(unsafe { File::from_raw_fd(raw_fd) }),
-            resources: WriteResources::Child(child),
-        })
+            resources: WriteResources::Child(ChildProcess::new(child)),
+            write_timeout: None,
+        }
     }
 
     /// Write to the given child standard input, taking ownership of it.
@@ -402,6 +1042,7 @@ impl WriteHandle {
         Self {
             descriptor: ManuallyDrop::new(unsafe { File::from_raw_fd(raw_fd) }),
             resources: WriteResources::ChildStdin(child_stdin),
+            write_timeout: None,
         }
     }
 
@@ -410,30 +1051,471 @@ impl WriteHandle {
         Ok(Self::file(File::create("/dev/null")?))
     }
 
-    fn map_err(&mut self, e: io::Error) -> io::Error {
+    /// If this stream is backed by a child process spawned by
+    /// [`write_to_command`], enable or disable reporting an unsuccessful exit
+    /// of the child as an error from [`close`].
+    ///
+    /// Fails with [`std::io::ErrorKind::Unsupported`] if this stream is not
+    /// backed by a child process.
+    ///
+    /// [`write_to_command`]: Self::write_to_command
+    /// [`close`]: Self::close
+    #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+    pub fn set_check_exit_status(&mut self, check: bool) -> io::Result<()> {
         match &mut self.resources {
-            #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
-            WriteResources::PipedThread(piped_thread) => {
-                let (pipe_writer, join_handle) = piped_thread.take().unwrap();
-                drop(pipe_writer);
-                join_handle.join().unwrap().map(|_| ()).unwrap_err()
+            WriteResources::Child(child) => {
+                child.set_check_exit_status(check);
+                Ok(())
             }
-            _ => e,
+            _ => Err(not_a_child()),
         }
     }
-}
 
-impl ReadWriteHandle {
-    /// Interact with stdin and stdout, taking ownership of them.
+    /// If this stream is backed by a child process spawned by
+    /// [`write_to_command`], close the child's standard input, wait for it to
+    /// exit, and return its exit status.
     ///
-    /// Unlike [`std::io::stdin`] and [`std::io::stdout`], this `stdin_stdout`
-    /// returns a stream which is unbuffered and unlocked.
-    ///
-    /// This acquires a [`std::io::StdinLock`] and a [`std::io::StdoutLock`] to
-    /// prevent accesses to `std::io::Stdin` and `std::io::Stdout` while this
-    /// is live, and fails if a `ReadHandle` for standard input, a
-    /// `WriteHandle` for standard output, or a `ReadWriteHandle` for standard
-    /// input and standard output already exist.
+    /// Fails with [`std::io::ErrorKind::Unsupported`] if this stream is not
+    /// backed by a child process.
+    ///
+    /// [`write_to_command`]: Self::write_to_command
+    #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+    pub fn wait(mut self) -> io::Result<ExitStatus> {
+        match &mut self.resources {
+            // This closes the child's stdin, which our descriptor refers to,
+            // however `self` is consumed so it's never used again.
+            WriteResources::Child(child) => child.wait(),
+            _ => Err(not_a_child()),
+        }
+    }
+
+    /// If this stream is backed by a child process spawned by
+    /// [`write_to_command`], return its exit status if it has exited,
+    /// without blocking.
+    ///
+    /// Fails with [`std::io::ErrorKind::Unsupported`] if this stream is not
+    /// backed by a child process.
+    ///
+    /// [`write_to_command`]: Self::write_to_command
+    #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+    pub fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> {
+        match &mut self.resources {
+            WriteResources::Child(child) => child.try_wait(),
+            _ => Err(not_a_child()),
+        }
+    }
+
+    /// If this stream is backed by a child process whose standard error was
+    /// captured with [`StderrMode::Capture`], take a stream which reads from
+    /// it.
+    ///
+    /// Returns `None` if standard error wasn't captured, or if it has already
+    /// been taken.
+    #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+    pub fn take_stderr(&mut self) -> Option<ReadHandle> {
+        match &mut self.resources {
+            WriteResources::Child(child) => child.take_stderr().map(ReadHandle::child_stderr),
+            _ => None,
+        }
+    }
+
+    /// Move this stream into or out of non-blocking mode.
+    ///
+    /// In non-blocking mode, writes which would block fail with
+    /// [`std::io::ErrorKind::WouldBlock`] instead. Use [`poll`] to wait for
+    /// the stream to become writable.
+    ///
+    /// This sets `O_NONBLOCK` on the open file description, which is shared
+    /// with any duplicates of the file descriptor, including ones held by
+    /// other processes, as is often the case for standard output.
+    ///
+    /// [`poll`]: crate::poll
+    #[inline]
+    pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        set_nonblocking(self.as_raw_fd(), nonblocking)
+    }
+
+    /// Set the timeout for writes to this stream.
+    ///
+    /// If a write can't make progress within the timeout, it fails with
+    /// [`std::io::ErrorKind::TimedOut`]. With a timeout of `None`, writes
+    /// may block indefinitely. This works for all kinds of streams,
+    /// including pipes, terminals, and piped threads, and not just sockets.
+    /// Timeouts aren't available on Windows.
+    /// Once the stream becomes writable, a write writes as much as fits
+    /// without blocking, so a large write may write only part of its data.
+    /// This briefly sets `O_NONBLOCK` on the open file description, which is
+    /// shared with any duplicates of the file descriptor.
+    ///
+    /// Fails with [`std::io::ErrorKind::InvalidInput`] if `timeout` is zero.
+    #[inline]
+    pub fn set_write_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()> {
+        self.write_timeout = check_timeout(timeout)?;
+        Ok(())
+    }
+
+    /// Return the timeout for writes to this stream.
+    #[inline]
+    pub fn write_timeout(&self) -> Option<Duration> {
+        self.write_timeout
+    }
+
+    /// Create a new stream for the same underlying I/O object, such as to
+    /// hand the same output to a logger and a worker.
+    ///
+    /// This duplicates the file descriptor for files, sockets, and pipes,
+    /// including a child process' standard input. The halves of a split
+    /// stream share its resources instead. The new stream has the same write
+    /// timeout.
+    ///
+    /// Fails with [`std::io::ErrorKind::Unsupported`] for other streams, such
+    /// as standard output and error, piped threads, and streams which own a
+    /// child process.
+    pub fn try_clone(&self) -> io::Result<Self> {
+        let mut clone = match &self.resources {
+            WriteResources::File(file) => Self::file(file.try_clone()?),
+            WriteResources::TcpStream(tcp_stream) => Self::tcp_stream(tcp_stream.try_clone()?),
+            #[cfg(unix)]
+            WriteResources::UnixStream(unix_stream) => Self::unix_stream(unix_stream.try_clone()?),
+            #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+            WriteResources::PipeWriter(pipe_writer) => Self::pipe_writer(pipe_writer.try_clone()?),
+            #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+            WriteResources::ChildStdin(child_stdin) => {
+                Self::child_stdin(ChildStdin::from(child_stdin.as_fd().try_clone_to_owned()?))
+            }
+            WriteResources::Shared(resources) => Self {
+                descriptor: ManuallyDrop::new(unsafe { File::from_raw_fd(self.as_raw_fd()) }),
+                resources: WriteResources::Shared(Arc::clone(resources)),
+                write_timeout: None,
+            },
+            _ => return Err(not_cloneable()),
+        };
+        clone.write_timeout = self.write_timeout;
+        Ok(clone)
+    }
+
+    /// Return what kind of I/O object backs this stream.
+    pub fn kind(&self) -> HandleKind {
+        match &self.resources {
+            WriteResources::File(_) => HandleKind::File,
+            WriteResources::TcpStream(_) => HandleKind::TcpStream,
+            #[cfg(unix)]
+            WriteResources::UnixStream(_) => HandleKind::UnixStream,
+            #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+            WriteResources::PipeWriter(_) => HandleKind::Pipe,
+            WriteResources::Stdout(_) | WriteResources::Stderr(_) => HandleKind::Stdio,
+            #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+            WriteResources::PipedThread(..) => HandleKind::PipedThread,
+            #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+            WriteResources::Child(_) | WriteResources::ChildStdin(_) => HandleKind::Child,
+            WriteResources::Shared(resources) => resources.kind(),
+        }
+    }
+
+    /// Query metadata about the file this stream reads from or writes to,
+    /// such as whether it's a regular file, using `fstat`.
+    #[inline]
+    pub fn metadata(&self) -> io::Result<Metadata> {
+        self.descriptor.metadata()
+    }
+
+    /// Test whether this stream is a terminal.
+    #[inline]
+    pub fn is_terminal(&self) -> bool {
+        self.descriptor.is_terminal()
+    }
+
+    /// Return the size of the terminal this stream refers to, as columns and
+    /// rows.
+    ///
+    /// Fails if this stream isn't a terminal.
+    #[cfg(unix)]
+    #[inline]
+    pub fn terminal_size(&self) -> io::Result<(u16, u16)> {
+        terminal_size(self.as_raw_fd())
+    }
+
+    /// Put the terminal this stream refers to in raw mode, where input is
+    /// available byte by byte, without echoing, line editing, or signal
+    /// characters, and output isn't post-processed, until the returned guard
+    /// is dropped.
+    ///
+    /// This stream can be used through the guard.
+    #[cfg(unix)]
+    pub fn raw_mode(&mut self) -> io::Result<TerminalModeGuard<'_, Self>> {
+        let raw_fd = self.as_raw_fd();
+        TerminalModeGuard::new(self, raw_fd, Mode::Raw)
+    }
+
+    /// Turn off echoing on the terminal this stream refers to, such as for
+    /// reading a password, until the returned guard is dropped.
+    ///
+    /// This stream can be used through the guard.
+    #[cfg(unix)]
+    pub fn no_echo_mode(&mut self) -> io::Result<TerminalModeGuard<'_, Self>> {
+        let raw_fd = self.as_raw_fd();
+        TerminalModeGuard::new(self, raw_fd, Mode::NoEcho)
+    }
+
+    /// Test whether this stream is backed by a regular file, and so supports
+    /// seeking. For other streams, such as pipes, sockets, and terminals,
+    /// [`Seek`] functions fail with [`std::io::ErrorKind::Unsupported`].
+    ///
+    /// [`Seek`]: std::io::Seek
+    #[inline]
+    pub fn is_seekable(&self) -> bool {
+        is_seekable(&self.descriptor)
+    }
+
+    /// Write to this stream at `offset`, returning the number of bytes
+    /// written.
+    ///
+    /// This doesn't move the cursor, so a stream can be shared between
+    /// threads which each write at their own offsets.
+    ///
+    /// This fails with [`std::io::ErrorKind::Unsupported`] unless the stream
+    /// is backed by a regular file.
+    #[inline]
+    pub fn write_at(&self, buf: &[u8], offset: u64) -> io::Result<usize> {
+        write_at(&self.descriptor, buf, offset)
+    }
+
+    /// Like [`write_at`], but writes from a slice of buffers.
+    ///
+    /// [`write_at`]: Self::write_at
+    #[inline]
+    pub fn write_vectored_at(&self, bufs: &[IoSlice], offset: u64) -> io::Result<usize> {
+        write_vectored_at(&self.descriptor, bufs, offset)
+    }
+
+    /// Close the stream, reporting any error that would otherwise be
+    /// discarded when it's dropped.
+    ///
+    /// If this stream is a piped thread, this waits for the thread to drain
+    /// the pipe, flush the boxed writer, and exit, and reports an error it
+    /// encountered. If this stream is backed by a child process and
+    /// [`set_check_exit_status`] has enabled exit-status checking, this waits
+    /// for the child to exit and reports an unsuccessful exit as an error.
+    ///
+    /// [`set_check_exit_status`]: Self::set_check_exit_status
+    pub fn close(mut self) -> io::Result<()> {
+        match &mut self.resources {
+            // As in `wait`, `self` is consumed, so closing the child's stdin
+            // here is fine.
+            #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+            WriteResources::Child(child) => child.check_exit_status(),
+            #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+            WriteResources::PipedThread(piped_thread, _) => match piped_thread.take() {
+                Some((pipe_writer, mut join_guard)) => {
+                    drop(pipe_writer);
+                    join_guard.join()
+                }
+                None => Ok(()),
+            },
+            _ => Ok(()),
+        }
+    }
+
+    /// Write with `write`, applying the write timeout if one is set.
+    fn timed_write(
+        &mut self,
+        mut write: impl FnMut(&mut File) -> io::Result<usize>,
+    ) -> io::Result<usize> {
+        match self.write_timeout {
+            Some(timeout) => {
+                let raw_fd = self.as_raw_fd();
+                write_with_timeout(raw_fd, timeout, || write(&mut self.descriptor))
+            }
+            None => write(&mut self.descriptor),
+        }
+    }
+
+    /// Record that `size` bytes were written, so that flushing a piped
+    /// thread knows how much to wait for.
+    #[inline]
+    pub(crate) fn wrote(&mut self, size: usize) {
+        #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+        if let WriteResources::PipedThread(_, flusher) = &mut self.resources {
+            flusher.wrote(size);
+        }
+        #[cfg(target_os = "wasi")]
+        let _ = size;
+    }
+
+    /// Test whether the `Write` functions which loop should be forwarded to
+    /// `write`, to apply the write timeout, or to count the bytes written.
+    #[inline]
+    fn by_write(&self) -> bool {
+        #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+        if let WriteResources::PipedThread(..) = self.resources {
+            return true;
+        }
+        self.write_timeout.is_some()
+    }
+
+    /// Recover the file this stream writes to.
+    ///
+    /// Returns `Err(self)` if this stream isn't backed by one.
+    pub fn into_file(self) -> Result<File, Self> {
+        match self.resources {
+            WriteResources::File(resource) => Ok(resource),
+            _ => Err(self),
+        }
+    }
+
+    /// Recover the TCP socket this stream writes to.
+    ///
+    /// Returns `Err(self)` if this stream isn't backed by one.
+    pub fn into_tcp_stream(self) -> Result<TcpStream, Self> {
+        match self.resources {
+            WriteResources::TcpStream(resource) => Ok(resource),
+            _ => Err(self),
+        }
+    }
+
+    /// Recover the Unix-domain socket this stream writes to.
+    ///
+    /// Returns `Err(self)` if this stream isn't backed by one.
+    #[cfg(unix)]
+    pub fn into_unix_stream(self) -> Result<UnixStream, Self> {
+        match self.resources {
+            WriteResources::UnixStream(resource) => Ok(resource),
+            _ => Err(self),
+        }
+    }
+
+    /// Recover the pipe this stream writes to.
+    ///
+    /// Returns `Err(self)` if this stream isn't backed by one.
+    #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+    pub fn into_pipe_writer(self) -> Result<PipeWriter, Self> {
+        match self.resources {
+            WriteResources::PipeWriter(resource) => Ok(resource),
+            _ => Err(self),
+        }
+    }
+
+    /// Recover the child process' standard input this stream writes to.
+    ///
+    /// Returns `Err(self)` if this stream isn't backed by one.
+    #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+    pub fn into_child_stdin(self) -> Result<ChildStdin, Self> {
+        match self.resources {
+            WriteResources::ChildStdin(resource) => Ok(resource),
+            _ => Err(self),
+        }
+    }
+
+    /// Recover the child process spawned by [`write_to_command`], with its
+    /// standard input in [`Child::stdin`].
+    ///
+    /// Returns `Err(self)` if this stream isn't backed by one.
+    ///
+    /// [`write_to_command`]: Self::write_to_command
+    /// [`Child::stdin`]: std::process::Child::stdin
+    #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+    pub fn into_child(self) -> Result<Child, Self> {
+        match self.resources {
+            WriteResources::Child(child) => Ok(child.into_child()),
+            _ => Err(self),
+        }
+    }
+
+    /// Recover the file descriptor this stream writes to, when the stream
+    /// owns it outright, such as for a file, socket, or pipe.
+    ///
+    /// Returns `Err(self)` if the stream holds other resources which the
+    /// descriptor depends on, such as a thread, a child process, or a lock on
+    /// standard output.
+    pub fn into_owned_fd(self) -> Result<OwnedFd, Self> {
+        match self.resources {
+            WriteResources::File(resource) => Ok(resource.into()),
+            WriteResources::TcpStream(resource) => Ok(resource.into()),
+            #[cfg(unix)]
+            WriteResources::UnixStream(resource) => Ok(resource.into()),
+            #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+            WriteResources::PipeWriter(resource) => {
+                Ok(unsafe { OwnedFd::from_raw_fd(resource.into_raw_fd()) })
+            }
+            #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+            WriteResources::ChildStdin(resource) => Ok(resource.into()),
+            _ => Err(self),
+        }
+    }
+
+    /// Convert this stream into a [`Stdio`], for use as a child process'
+    /// standard output or error, such as with [`Command::stdout`], along with
+    /// a guard holding what the child depends on.
+    ///
+    /// If this stream is a piped thread, the guard holds the thread, which
+    /// keeps consuming the child's output. If it's backed by a child process,
+    /// the guard holds that process, which reads the child's output. If it's
+    /// our own standard output or error, the child writes to it directly. If
+    /// its descriptor is shared, such as for the write half of a split
+    /// [`ReadWriteHandle`], it's duplicated.
+    ///
+    /// Use [`StdioGuard::close`] to wait for the thread or process and
+    /// observe its errors.
+    #[cfg(unix)]
+    pub fn try_into_stdio(self) -> io::Result<(Stdio, StdioGuard)> {
+        let handle = match self.into_owned_fd() {
+            Ok(fd) => return Ok((fd.into(), StdioGuard::empty())),
+            Err(handle) => handle,
+        };
+        Ok(match handle.resources {
+            WriteResources::Stdout(_) => (io::stdout().into(), StdioGuard::empty()),
+            WriteResources::Stderr(_) => (io::stderr().into(), StdioGuard::empty()),
+            WriteResources::PipedThread(Some((pipe_writer, join_guard)), _) => {
+                (pipe_writer.into(), StdioGuard::write_thread(join_guard))
+            }
+            WriteResources::Child(mut child) => {
+                let child_stdin = child.take_stdin();
+                (
+                    child_stdin.map_or_else(Stdio::null, Stdio::from),
+                    StdioGuard::child(child),
+                )
+            }
+            _ => (handle.descriptor.try_clone()?.into(), StdioGuard::empty()),
+        })
+    }
+
+    /// Wait for a piped thread to exit and report its result, leaving just
+    /// the pipe, which is broken.
+    #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+    fn join_piped_thread(&mut self) -> io::Result<()> {
+        if let WriteResources::PipedThread(piped_thread, _) = &mut self.resources {
+            if let Some((pipe_writer, mut join_guard)) = piped_thread.take() {
+                self.resources = WriteResources::PipeWriter(pipe_writer);
+                return join_guard.join();
+            }
+        }
+        Ok(())
+    }
+
+    fn map_err(&mut self, e: io::Error) -> io::Error {
+        // A broken pipe means the thread has exited; report its error, if it
+        // had one.
+        #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+        if e.kind() == io::ErrorKind::BrokenPipe
+            && matches!(self.resources, WriteResources::PipedThread(..))
+        {
+            return self.join_piped_thread().err().unwrap_or(e);
+        }
+        e
+    }
+}
+
+impl ReadWriteHandle {
+    /// Interact with stdin and stdout, taking ownership of them.
+    ///
+    /// Unlike [`std::io::stdin`] and [`std::io::stdout`], this `stdin_stdout`
+    /// returns a stream which is unbuffered and unlocked.
+    ///
+    /// This acquires a [`std::io::StdinLock`] and a [`std::io::StdoutLock`] to
+    /// prevent accesses to `std::io::Stdin` and `std::io::Stdout` while this
+    /// is live, and fails if a `ReadHandle` for standard input, a
+    /// `WriteHandle` for standard output, or a `ReadWriteHandle` for standard
+    /// input and standard output already exist.
     ///
     /// [`std::io::stdin`]: https://doc.rust-lang.org/std/io/fn.stdin.html`
     /// [`std::io::stdout`]: https://doc.rust-lang.org/std/io/fn.stdout.html`
@@ -449,89 +1531,1067 @@ impl ReadWriteHandle {
             read_descriptor: ManuallyDrop::new(unsafe { File::from_raw_fd(raw_read_fd) }),
             write_descriptor: ManuallyDrop::new(unsafe { File::from_raw_fd(raw_write_fd) }),
             resources: ReadWriteResources::StdinStdout((stdin_locker, stdout_locker)),
+            read_timeout: None,
+            write_timeout: None,
         })
     }
 
-    /// Spawn the given command and interact with its standard input and
-    /// output.
-    pub fn interact_with_command(mut command: Command) -> io::Result<Self> {
-        command.stdin(Stdio::piped());
-        command.stdout(Stdio::piped());
-        let mut child = command.spawn()?;
-        let child_stdin = child.stdin.take().unwrap();
-        let child_stdout = child.stdout.take().unwrap();
-        let raw_read_fd = child_stdout.as_raw_fd();
-        let raw_write_fd = child_stdin.as_raw_fd();
-        Ok(Self {
-            read_descriptor: ManuallyDrop::new(unsafe { File::from_raw_fd(raw_read_fd) }),
-            write_descriptor: ManuallyDrop::new(unsafe { File::from_raw_fd(raw_write_fd) }),
-            resources: ReadWriteResources::Child(child),
-        })
+    /// Interact with stdin and stderr, taking ownership of them.
+    ///
+    /// This is useful for prompting the user while standard output is
+    /// reserved for the program's output.
+    ///
+    /// This acquires a [`std::io::StdinLock`] and a [`std::io::StderrLock`] to
+    /// prevent accesses to `std::io::Stdin` and `std::io::Stderr` while this
+    /// is live, and fails if a `ReadHandle` for standard input, a
+    /// `WriteHandle` for standard error, or a `ReadWriteHandle` for standard
+    /// input already exist.
+    ///
+    /// [`std::io::StdinLock`]: https://doc.rust-lang.org/std/io/struct.StdinLock.html
+    /// [`std::io::StderrLock`]: https://doc.rust-lang.org/std/io/struct.StderrLock.html
+    #[inline]
+    pub fn stdin_stderr() -> io::Result<Self> {
+        let stdin_locker = StdinLocker::new()?;
+        let stderr_locker = StderrLocker::new()?;
+        let raw_read_fd = stdin_locker.as_raw_fd();
+        let raw_write_fd = stderr_locker.as_raw_fd();
+        Ok(Self {
+            read_descriptor: ManuallyDrop::new(unsafe { File::from_raw_fd(raw_read_fd) }),
+            write_descriptor: ManuallyDrop::new(unsafe { File::from_raw_fd(raw_write_fd) }),
+            resources: ReadWriteResources::StdinStderr((stdin_locker, stderr_locker)),
+            read_timeout: None,
+            write_timeout: None,
+        })
+    }
+
+    /// Spawn the given command and interact with its standard input and
+    /// output.
+    ///
+    /// The child process can be waited for with [`wait`] and [`try_wait`],
+    /// and with [`set_check_exit_status`], [`close`] waits for it and reports
+    /// an unsuccessful exit as an error.
+    ///
+    /// [`wait`]: Self::wait
+    /// [`try_wait`]: Self::try_wait
+    /// [`set_check_exit_status`]: Self::set_check_exit_status
+    /// [`close`]: Self::close
+    #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+    pub fn interact_with_command(mut command: Command) -> io::Result<Self> {
+        command.stdin(Stdio::piped());
+        command.stdout(Stdio::piped());
+        let child = command.spawn()?;
+        Ok(Self::child_process(child))
+    }
+
+    /// Like [`interact_with_command`], but with the child's standard error
+    /// directed according to `stderr`. With [`StderrMode::Merge`], this
+    /// stream reads both the child's standard output and standard error.
+    ///
+    /// [`interact_with_command`]: Self::interact_with_command
+    #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+    pub fn interact_with_command_with_stderr(
+        mut command: Command,
+        stderr: StderrMode,
+    ) -> io::Result<Self> {
+        command.stdin(Stdio::piped());
+        let child = spawn_stdout_piped(command, stderr)?;
+        Ok(Self::child_process(child))
+    }
+
+    /// Interact with the piped standard input and output of a child process.
+    #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+    fn child_process(child: Child) -> Self {
+        let raw_read_fd = child.stdout.as_ref().unwrap().as_raw_fd();
+        let raw_write_fd = child.stdin.as_ref().unwrap().as_raw_fd();
+        Self {
+            read_descriptor: ManuallyDrop::new(unsafe { File::from_raw_fd(raw_read_fd) }),
+            write_descriptor: ManuallyDrop::new(unsafe { File::from_raw_fd(raw_write_fd) }),
+            resources: ReadWriteResources::Child(ChildProcess::new(child)),
+            read_timeout: None,
+            write_timeout: None,
+        }
+    }
+
+    /// Create a new pseudo-terminal, returning a stream for its master side,
+    /// along with its slave side and the path of its slave side.
+    ///
+    /// What's written to the stream is input to the terminal, and what's
+    /// written to the terminal can be read from the stream. Reads reach the
+    /// end of the stream once the slave side is closed everywhere.
+    #[cfg(unix)]
+    pub fn pty() -> io::Result<(Self, File, PathBuf)> {
+        let (master, slave, path) = open_pty()?;
+        let raw_fd = master.as_raw_fd();
+        Ok((
+            Self {
+                read_descriptor: ManuallyDrop::new(unsafe { File::from_raw_fd(raw_fd) }),
+                write_descriptor: ManuallyDrop::new(unsafe { File::from_raw_fd(raw_fd) }),
+                resources: ReadWriteResources::Pty(master),
+                read_timeout: None,
+                write_timeout: None,
+            },
+            slave,
+            path,
+        ))
+    }
+
+    /// Spawn the given command in a new session, with a new pseudo-terminal
+    /// as its controlling terminal and its standard input, output, and error,
+    /// and interact with the terminal, so that the command sees that it's
+    /// running in a terminal.
+    ///
+    /// As with any terminal, input is echoed, and line endings may be
+    /// translated, according to the terminal's settings.
+    ///
+    /// The child process can be waited for with [`wait`] and [`try_wait`],
+    /// and with [`set_check_exit_status`], [`close`] waits for it and reports
+    /// an unsuccessful exit as an error.
+    ///
+    /// [`wait`]: Self::wait
+    /// [`try_wait`]: Self::try_wait
+    /// [`set_check_exit_status`]: Self::set_check_exit_status
+    /// [`close`]: Self::close
+    #[cfg(unix)]
+    pub fn interact_with_command_in_pty(mut command: Command) -> io::Result<Self> {
+        let (master, slave, _path) = open_pty()?;
+        command.stdin(slave.try_clone()?);
+        command.stdout(slave.try_clone()?);
+        command.stderr(slave);
+        set_controlling_terminal(&mut command);
+        let child = command.spawn()?;
+        // Close our copies of the slave side, so that reads reach the end of
+        // the stream when the child is done with it.
+        drop(command);
+        let raw_fd = master.as_raw_fd();
+        Ok(Self {
+            read_descriptor: ManuallyDrop::new(unsafe { File::from_raw_fd(raw_fd) }),
+            write_descriptor: ManuallyDrop::new(unsafe { File::from_raw_fd(raw_fd) }),
+            resources: ReadWriteResources::PtyChild((master, ChildProcess::new(child))),
+            read_timeout: None,
+            write_timeout: None,
+        })
+    }
+
+    /// Interact with a child process' stdout and stdin, taking ownership of
+    /// them.
+    #[inline]
+    #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+    pub fn child_stdout_stdin(child_stdout: ChildStdout, child_stdin: ChildStdin) -> Self {
+        let raw_read_fd = child_stdout.as_raw_fd();
+        let raw_write_fd = child_stdin.as_raw_fd();
+        Self {
+            read_descriptor: ManuallyDrop::new(unsafe { File::from_raw_fd(raw_read_fd) }),
+            write_descriptor: ManuallyDrop::new(unsafe { File::from_raw_fd(raw_write_fd) }),
+            resources: ReadWriteResources::ChildStdoutStdin((child_stdout, child_stdin)),
+            read_timeout: None,
+            write_timeout: None,
+        }
+    }
+
+    /// Interact with an open character device, taking ownership of it.
+    #[inline]
+    pub fn char_device(char_device: File) -> Self {
+        let raw_fd = char_device.as_raw_fd();
+        Self {
+            read_descriptor: ManuallyDrop::new(unsafe { File::from_raw_fd(raw_fd) }),
+            write_descriptor: ManuallyDrop::new(unsafe { File::from_raw_fd(raw_fd) }),
+            resources: ReadWriteResources::CharDevice(char_device),
+            read_timeout: None,
+            write_timeout: None,
+        }
+    }
+
+    /// Interact with an open TCP stream, taking ownership of it.
+    #[inline]
+    pub fn tcp_stream(tcp_stream: TcpStream) -> Self {
+        let raw_fd = tcp_stream.as_raw_fd();
+        Self {
+            read_descriptor: ManuallyDrop::new(unsafe { File::from_raw_fd(raw_fd) }),
+            write_descriptor: ManuallyDrop::new(unsafe { File::from_raw_fd(raw_fd) }),
+            resources: ReadWriteResources::TcpStream(tcp_stream),
+            read_timeout: None,
+            write_timeout: None,
+        }
+    }
+
+    /// Interact with an open Unix-domain stream, taking ownership of it.
+    #[cfg(unix)]
+    pub fn unix_stream(unix_stream: UnixStream) -> Self {
+        let raw_fd = unix_stream.as_raw_fd();
+        Self {
+            read_descriptor: ManuallyDrop::new(unsafe { File::from_raw_fd(raw_fd) }),
+            write_descriptor: ManuallyDrop::new(unsafe { File::from_raw_fd(raw_fd) }),
+            resources: ReadWriteResources::UnixStream(unix_stream),
+            read_timeout: None,
+            write_timeout: None,
+        }
+    }
+
+    /// Create a pair of connected streams, where what's written to each can
+    /// be read from the other, such as for testing protocol code without a
+    /// child process or a network connection.
+    ///
+    /// This uses [`UnixStream::pair`].
+    #[cfg(unix)]
+    pub fn pair() -> io::Result<(Self, Self)> {
+        let (a, b) = UnixStream::pair()?;
+        Ok((Self::unix_stream(a), Self::unix_stream(b)))
+    }
+
+    /// Interact a pair of pipe streams, taking ownership of them.
+    #[inline]
+    #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+    pub fn pipe_reader_writer(pipe_reader: PipeReader, pipe_writer: PipeWriter) -> Self {
+        let raw_read_fd = pipe_reader.as_raw_fd();
+        let raw_write_fd = pipe_writer.as_raw_fd();
+        Self {
+            read_descriptor: ManuallyDrop::new(unsafe { File::from_raw_fd(raw_read_fd) }),
+            write_descriptor: ManuallyDrop::new(unsafe { File::from_raw_fd(raw_write_fd) }),
+            resources: ReadWriteResources::PipeReaderWriter((pipe_reader, pipe_writer)),
+            read_timeout: None,
+            write_timeout: None,
+        }
+    }
+
+    /// Interact with an arbitrary [`ReadWrite`], such as an in-memory
+    /// protocol simulator or a TLS stream, by running it on a thread
+    /// connected to the returned stream by a pair of pipes.
+    ///
+    /// A single thread owns `boxed_read_write`, and alternates between
+    /// writing what's written to this stream into it, flushing it after each
+    /// batch, and reading from it. So `boxed_read_write` must be
+    /// non-blocking: when it has no data available, its `read` should fail
+    /// with [`std::io::ErrorKind::WouldBlock`], and the thread then waits for
+    /// more data to be written, for up to 100 milliseconds as it stays idle,
+    /// before trying it again.
+    ///
+    /// Blocking streams, including TLS streams over blocking sockets, aren't
+    /// supported. A `read` which blocks holds up writing to
+    /// `boxed_read_write`, so a protocol which waits for a request before
+    /// responding deadlocks, and [`close`] waits for the `read` to return.
+    /// Put the socket under a TLS stream in non-blocking mode instead.
+    ///
+    /// A `read` returning 0 ends this stream for good, as it does for any
+    /// [`Read`]; one which has no data yet must fail with `WouldBlock`
+    /// instead.
+    ///
+    /// Errors from `boxed_read_write` stop the thread, and are reported by
+    /// [`close`], which waits for everything written to this stream to be
+    /// written to `boxed_read_write`. Dropping this stream doesn't wait for
+    /// the thread.
+    ///
+    /// [`close`]: Self::close
+    #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+    pub fn piped_thread(boxed_read_write: Box<dyn ReadWrite + Send>) -> io::Result<Self> {
+        let (pipe_reader, pipe_writer, duplex_thread) = spawn_duplex(boxed_read_write)?;
+        let raw_read_fd = pipe_reader.as_raw_fd();
+        let raw_write_fd = pipe_writer.as_raw_fd();
+        Ok(Self {
+            read_descriptor: ManuallyDrop::new(unsafe { File::from_raw_fd(raw_read_fd) }),
+            write_descriptor: ManuallyDrop::new(unsafe { File::from_raw_fd(raw_write_fd) }),
+            resources: ReadWriteResources::PipedThread(Some((
+                pipe_reader,
+                pipe_writer,
+                duplex_thread,
+            ))),
+            read_timeout: None,
+            write_timeout: None,
+        })
+    }
+
+    /// If this stream is backed by a child process spawned by
+    /// [`interact_with_command`] or [`interact_with_command_in_pty`], enable
+    /// or disable reporting an unsuccessful exit of the child as an error from
+    /// [`close`].
+    ///
+    /// Fails with [`std::io::ErrorKind::Unsupported`] if this stream is not
+    /// backed by a child process.
+    ///
+    /// [`interact_with_command`]: Self::interact_with_command
+    /// [`interact_with_command_in_pty`]: Self::interact_with_command_in_pty
+    /// [`close`]: Self::close
+    #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+    pub fn set_check_exit_status(&mut self, check: bool) -> io::Result<()> {
+        match &mut self.resources {
+            #[cfg(unix)]
+            ReadWriteResources::PtyChild((_, child)) => {
+                child.set_check_exit_status(check);
+                Ok(())
+            }
+            ReadWriteResources::Child(child) => {
+                child.set_check_exit_status(check);
+                Ok(())
+            }
+            _ => Err(not_a_child()),
+        }
+    }
+
+    /// If this stream is backed by a child process spawned by
+    /// [`interact_with_command`] or [`interact_with_command_in_pty`], wait
+    /// for it to exit, and return its exit status.
+    ///
+    /// For [`interact_with_command`], this closes the child's standard input
+    /// first. For [`interact_with_command_in_pty`], the terminal stays open,
+    /// and any output the child writes to it while we wait is read and
+    /// discarded, so that the child doesn't block writing it.
+    ///
+    /// Fails with [`std::io::ErrorKind::Unsupported`] if this stream is not
+    /// backed by a child process.
+    ///
+    /// [`interact_with_command`]: Self::interact_with_command
+    /// [`interact_with_command_in_pty`]: Self::interact_with_command_in_pty
+    #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+    pub fn wait(mut self) -> io::Result<ExitStatus> {
+        match &mut self.resources {
+            // This closes the child's stdin, which our write descriptor refers
+            // to, however `self` is consumed so it's never used again.
+            ReadWriteResources::Child(child) => child.wait(),
+            // As in `close`, drain the terminal rather than closing it, which
+            // would hang up the child's terminal.
+            #[cfg(unix)]
+            ReadWriteResources::PtyChild((master, child)) => {
+                drain_pty_master(master)?;
+                child.wait()
+            }
+            _ => Err(not_a_child()),
+        }
+    }
+
+    /// If this stream is backed by a child process spawned by
+    /// [`interact_with_command`] or [`interact_with_command_in_pty`], return
+    /// its exit status if it has exited, without blocking.
+    ///
+    /// Fails with [`std::io::ErrorKind::Unsupported`] if this stream is not
+    /// backed by a child process.
+    ///
+    /// [`interact_with_command`]: Self::interact_with_command
+    /// [`interact_with_command_in_pty`]: Self::interact_with_command_in_pty
+    #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+    pub fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> {
+        match &mut self.resources {
+            ReadWriteResources::Child(child) => child.try_wait(),
+            #[cfg(unix)]
+            ReadWriteResources::PtyChild((_, child)) => child.try_wait(),
+            _ => Err(not_a_child()),
+        }
+    }
+
+    /// If this stream is backed by a child process whose standard error was
+    /// captured with [`StderrMode::Capture`], take a stream which reads from
+    /// it.
+    ///
+    /// Returns `None` if standard error wasn't captured, or if it has already
+    /// been taken.
+    #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+    pub fn take_stderr(&mut self) -> Option<ReadHandle> {
+        match &mut self.resources {
+            ReadWriteResources::Child(child) => child.take_stderr().map(ReadHandle::child_stderr),
+            _ => None,
+        }
+    }
+
+    /// Create a new stream for the same underlying I/O objects.
+    ///
+    /// This duplicates the file descriptors for sockets, character devices,
+    /// pseudo-terminals, and pipes, including a child process' standard
+    /// output and input passed to [`child_stdout_stdin`]. The new stream has
+    /// the same timeouts.
+    ///
+    /// Fails with [`std::io::ErrorKind::Unsupported`] for other streams, such
+    /// as standard input and output, piped threads, and streams which own a
+    /// child process.
+    ///
+    /// [`child_stdout_stdin`]: Self::child_stdout_stdin
+    pub fn try_clone(&self) -> io::Result<Self> {
+        let mut clone = match &self.resources {
+            ReadWriteResources::TcpStream(tcp_stream) => Self::tcp_stream(tcp_stream.try_clone()?),
+            #[cfg(unix)]
+            ReadWriteResources::UnixStream(unix_stream) => {
+                Self::unix_stream(unix_stream.try_clone()?)
+            }
+            ReadWriteResources::CharDevice(char_device) => {
+                Self::char_device(char_device.try_clone()?)
+            }
+            #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+            ReadWriteResources::PipeReaderWriter((pipe_reader, pipe_writer)) => {
+                Self::pipe_reader_writer(pipe_reader.try_clone()?, pipe_writer.try_clone()?)
+            }
+            #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+            ReadWriteResources::ChildStdoutStdin((child_stdout, child_stdin)) => {
+                Self::child_stdout_stdin(
+                    ChildStdout::from(child_stdout.as_fd().try_clone_to_owned()?),
+                    ChildStdin::from(child_stdin.as_fd().try_clone_to_owned()?),
+                )
+            }
+            #[cfg(unix)]
+            ReadWriteResources::Pty(master) => {
+                let master = master.try_clone()?;
+                let raw_fd = master.as_raw_fd();
+                Self {
+                    read_descriptor: ManuallyDrop::new(unsafe { File::from_raw_fd(raw_fd) }),
+                    write_descriptor: ManuallyDrop::new(unsafe { File::from_raw_fd(raw_fd) }),
+                    resources: ReadWriteResources::Pty(master),
+                    read_timeout: None,
+                    write_timeout: None,
+                }
+            }
+            _ => return Err(not_cloneable()),
+        };
+        clone.read_timeout = self.read_timeout;
+        clone.write_timeout = self.write_timeout;
+        Ok(clone)
+    }
+
+    /// Return what kind of I/O object backs this stream.
+    #[inline]
+    pub fn kind(&self) -> HandleKind {
+        self.resources.kind()
+    }
+
+    /// Query metadata about the file on the reading side of this stream,
+    /// using `fstat`.
+    #[inline]
+    pub fn read_metadata(&self) -> io::Result<Metadata> {
+        self.read_descriptor.metadata()
+    }
+
+    /// Query metadata about the file on the writing side of this stream,
+    /// using `fstat`.
+    #[inline]
+    pub fn write_metadata(&self) -> io::Result<Metadata> {
+        self.write_descriptor.metadata()
+    }
+
+    /// Test whether both sides of this stream are terminals.
+    #[inline]
+    pub fn is_terminal(&self) -> bool {
+        self.read_descriptor.is_terminal() && self.write_descriptor.is_terminal()
+    }
+
+    /// Return the size of the terminal on the reading side of this stream, as
+    /// columns and rows.
+    ///
+    /// Fails if the reading side of this stream isn't a terminal.
+    #[cfg(unix)]
+    #[inline]
+    pub fn terminal_size(&self) -> io::Result<(u16, u16)> {
+        terminal_size(self.as_raw_read_fd())
+    }
+
+    /// Put the terminal on the reading side of this stream in raw mode, where
+    /// input is available byte by byte, without echoing, line editing, or
+    /// signal characters, and output isn't post-processed, until the returned
+    /// guard is dropped.
+    ///
+    /// This stream can be used through the guard.
+    #[cfg(unix)]
+    pub fn raw_mode(&mut self) -> io::Result<TerminalModeGuard<'_, Self>> {
+        let raw_fd = self.as_raw_read_fd();
+        TerminalModeGuard::new(self, raw_fd, Mode::Raw)
+    }
+
+    /// Turn off echoing on the terminal on the reading side of this stream,
+    /// such as for reading a password, until the returned guard is dropped.
+    ///
+    /// This stream can be used through the guard.
+    #[cfg(unix)]
+    pub fn no_echo_mode(&mut self) -> io::Result<TerminalModeGuard<'_, Self>> {
+        let raw_fd = self.as_raw_read_fd();
+        TerminalModeGuard::new(self, raw_fd, Mode::NoEcho)
+    }
+
+    /// Move both sides of this stream into or out of non-blocking mode.
+    ///
+    /// In non-blocking mode, reads and writes which would block fail with
+    /// [`std::io::ErrorKind::WouldBlock`] instead. Use [`poll`] to wait for
+    /// the stream to become readable or writable.
+    ///
+    /// This sets `O_NONBLOCK` on the open file descriptions, which are shared
+    /// with any duplicates of the file descriptors, including ones held by
+    /// other processes, as is often the case for standard input and output.
+    ///
+    /// [`poll`]: crate::poll
+    pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        set_nonblocking(self.as_raw_read_fd(), nonblocking)?;
+        if self.as_raw_write_fd() != self.as_raw_read_fd() {
+            set_nonblocking(self.as_raw_write_fd(), nonblocking)?;
+        }
+        Ok(())
+    }
+
+    /// Set the timeout for reads from this stream.
+    ///
+    /// If a read can't make progress within the timeout, it fails with
+    /// [`std::io::ErrorKind::TimedOut`]. With a timeout of `None`, reads
+    /// may block indefinitely. This works for all kinds of streams,
+    /// including pipes, terminals, and piped threads, and not just sockets.
+    /// Timeouts aren't available on Windows.
+    ///
+    /// Fails with [`std::io::ErrorKind::InvalidInput`] if `timeout` is zero.
+    #[inline]
+    pub fn set_read_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()> {
+        self.read_timeout = check_timeout(timeout)?;
+        Ok(())
+    }
+
+    /// Return the timeout for reads from this stream.
+    #[inline]
+    pub fn read_timeout(&self) -> Option<Duration> {
+        self.read_timeout
+    }
+
+    /// Set the timeout for writes to this stream.
+    ///
+    /// If a write can't make progress within the timeout, it fails with
+    /// [`std::io::ErrorKind::TimedOut`]. With a timeout of `None`, writes
+    /// may block indefinitely. This works for all kinds of streams,
+    /// including pipes, terminals, and piped threads, and not just sockets.
+    /// Timeouts aren't available on Windows.
+    /// Once the stream becomes writable, a write writes as much as fits
+    /// without blocking, so a large write may write only part of its data.
+    /// This briefly sets `O_NONBLOCK` on the open file description, which is
+    /// shared with any duplicates of the file descriptor.
+    ///
+    /// Fails with [`std::io::ErrorKind::InvalidInput`] if `timeout` is zero.
+    #[inline]
+    pub fn set_write_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()> {
+        self.write_timeout = check_timeout(timeout)?;
+        Ok(())
+    }
+
+    /// Return the timeout for writes to this stream.
+    #[inline]
+    pub fn write_timeout(&self) -> Option<Duration> {
+        self.write_timeout
+    }
+
+    /// Shut down the writing side of this stream, so that the peer sees the
+    /// end of its input, while replies can still be read from this stream.
+    ///
+    /// For sockets, this uses `shutdown` with [`Shutdown::Write`]. For pipes,
+    /// including the standard input of a child process spawned by
+    /// [`interact_with_command`], and piped threads, this closes the pipe.
+    /// Writes to this stream afterward fail with
+    /// [`std::io::ErrorKind::BrokenPipe`].
+    ///
+    /// Fails with [`std::io::ErrorKind::Unsupported`] for other streams, such
+    /// as standard input and output, and terminals.
+    ///
+    /// [`Shutdown::Write`]: std::net::Shutdown::Write
+    /// [`interact_with_command`]: Self::interact_with_command
+    pub fn shutdown_write(&mut self) -> io::Result<()> {
+        let raw_write_fd = match &mut self.resources {
+            ReadWriteResources::TcpStream(tcp_stream) => {
+                return tcp_stream.shutdown(Shutdown::Write)
+            }
+            #[cfg(unix)]
+            ReadWriteResources::UnixStream(unix_stream) => {
+                return unix_stream.shutdown(Shutdown::Write)
+            }
+            #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+            ReadWriteResources::PipeReaderWriter((_, pipe_writer))
+            | ReadWriteResources::PipedThread(Some((_, pipe_writer, _))) => {
+                *pipe_writer = broken_pipe()?;
+                pipe_writer.as_raw_fd()
+            }
+            #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+            ReadWriteResources::ChildStdoutStdin((_, child_stdin)) => {
+                *child_stdin = broken_child_stdin()?;
+                child_stdin.as_raw_fd()
+            }
+            #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+            ReadWriteResources::Child(child) => {
+                let child_stdin = broken_child_stdin()?;
+                let raw_write_fd = child_stdin.as_raw_fd();
+                child.replace_stdin(child_stdin);
+                raw_write_fd
+            }
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "stream doesn't support shutting down just its writing side",
+                ))
+            }
+        };
+        self.write_descriptor = ManuallyDrop::new(unsafe { File::from_raw_fd(raw_write_fd) });
+        Ok(())
+    }
+
+    /// Close the stream.
+    ///
+    /// If this stream is backed by a child process and
+    /// [`set_check_exit_status`] has enabled exit-status checking, this waits
+    /// for the child to exit and reports an unsuccessful exit as an error.
+    ///
+    /// [`set_check_exit_status`]: Self::set_check_exit_status
+    pub fn close(mut self) -> io::Result<()> {
+        match &mut self.resources {
+            // As in `wait`, `self` is consumed, so closing the child's stdin
+            // here is fine. Close its stdout too, so that it doesn't block
+            // writing output that nothing will read while we wait for it.
+            #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+            ReadWriteResources::Child(child) => {
+                child.close_stdout();
+                child.check_exit_status()
+            }
+            // Closing the master would hang up the child's terminal, so drain
+            // it instead, until the child closes its end.
+            #[cfg(unix)]
+            ReadWriteResources::PtyChild((master, child)) => {
+                if child.checks_exit_status() {
+                    drain_pty_master(master)?;
+                }
+                child.check_exit_status()
+            }
+            #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+            ReadWriteResources::PipedThread(piped_thread) => match piped_thread.take() {
+                Some((pipe_reader, pipe_writer, mut duplex_thread)) => {
+                    drop(pipe_reader);
+                    drop(pipe_writer);
+                    match duplex_thread.close() {
+                        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+                        result => result,
+                    }
+                }
+                None => Ok(()),
+            },
+            _ => Ok(()),
+        }
     }
 
-    /// Interact with a child process' stdout and stdin, taking ownership of
-    /// them.
-    #[inline]
-    #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
-    pub fn child_stdout_stdin(child_stdout: ChildStdout, child_stdin: ChildStdin) -> Self {
-        let raw_read_fd = child_stdout.as_raw_fd();
-        let raw_write_fd = child_stdin.as_raw_fd();
-        Self {
-            read_descriptor: ManuallyDrop::new(unsafe { File::from_raw_fd(raw_read_fd) }),
-            write_descriptor: ManuallyDrop::new(unsafe { File::from_raw_fd(raw_write_fd) }),
-            resources: ReadWriteResources::ChildStdoutStdin((child_stdout, child_stdin)),
+    /// If a read timeout is set, wait for the stream to become readable.
+    fn ready_to_read(&self) -> io::Result<()> {
+        match self.read_timeout {
+            Some(timeout) => wait_readable(self.as_raw_read_fd(), timeout),
+            None => Ok(()),
         }
     }
 
-    /// Interact with an open character device, taking ownership of it.
-    #[inline]
-    pub fn char_device(char_device: File) -> Self {
-        let raw_fd = char_device.as_raw_fd();
-        Self {
-            read_descriptor: ManuallyDrop::new(unsafe { File::from_raw_fd(raw_fd) }),
-            write_descriptor: ManuallyDrop::new(unsafe { File::from_raw_fd(raw_fd) }),
-            resources: ReadWriteResources::CharDevice(char_device),
+    /// Write with `write`, applying the write timeout if one is set.
+    fn timed_write(
+        &mut self,
+        mut write: impl FnMut(&mut File) -> io::Result<usize>,
+    ) -> io::Result<usize> {
+        match self.write_timeout {
+            Some(timeout) => {
+                let raw_fd = self.as_raw_write_fd();
+                write_with_timeout(raw_fd, timeout, || write(&mut self.write_descriptor))
+            }
+            None => write(&mut self.write_descriptor),
         }
     }
 
-    /// Interact with an open TCP stream, taking ownership of it.
+    /// Test whether this stream is the master side of a pseudo-terminal.
+    #[cfg(unix)]
     #[inline]
-    pub fn tcp_stream(tcp_stream: TcpStream) -> Self {
-        let raw_fd = tcp_stream.as_raw_fd();
-        Self {
-            read_descriptor: ManuallyDrop::new(unsafe { File::from_raw_fd(raw_fd) }),
-            write_descriptor: ManuallyDrop::new(unsafe { File::from_raw_fd(raw_fd) }),
-            resources: ReadWriteResources::TcpStream(tcp_stream),
+    fn is_pty(&self) -> bool {
+        self.kind() == HandleKind::Pty
+    }
+
+    /// Test whether the `Read` functions which loop should be forwarded to
+    /// `read`, to apply the read timeout, or to detect the end of a
+    /// pseudo-terminal's stream.
+    #[inline]
+    fn by_read(&self) -> bool {
+        #[cfg(unix)]
+        if self.is_pty() {
+            return true;
         }
+        self.read_timeout.is_some()
     }
 
-    /// Interact with an open Unix-domain stream, taking ownership of it.
+    /// Recover the character device file this stream reads from and writes
+    /// to.
+    ///
+    /// Returns `Err(self)` if this stream isn't backed by one.
+    pub fn into_char_device(self) -> Result<File, Self> {
+        match self.resources {
+            ReadWriteResources::CharDevice(resource) => Ok(resource),
+            _ => Err(self),
+        }
+    }
+
+    /// Recover the TCP socket this stream reads from and writes to.
+    ///
+    /// Returns `Err(self)` if this stream isn't backed by one.
+    pub fn into_tcp_stream(self) -> Result<TcpStream, Self> {
+        match self.resources {
+            ReadWriteResources::TcpStream(resource) => Ok(resource),
+            _ => Err(self),
+        }
+    }
+
+    /// Recover the Unix-domain socket this stream reads from and writes to.
+    ///
+    /// Returns `Err(self)` if this stream isn't backed by one.
     #[cfg(unix)]
-    pub fn unix_stream(unix_stream: UnixStream) -> Self {
-        let raw_fd = unix_stream.as_raw_fd();
-        Self {
-            read_descriptor: ManuallyDrop::new(unsafe { File::from_raw_fd(raw_fd) }),
-            write_descriptor: ManuallyDrop::new(unsafe { File::from_raw_fd(raw_fd) }),
-            resources: ReadWriteResources::UnixStream(unix_stream),
+    pub fn into_unix_stream(self) -> Result<UnixStream, Self> {
+        match self.resources {
+            ReadWriteResources::UnixStream(resource) => Ok(resource),
+            _ => Err(self),
         }
     }
 
-    /// Interact a pair of pipe streams, taking ownership of them.
-    #[inline]
+    /// Recover the pipes this stream reads from and writes to.
+    ///
+    /// Returns `Err(self)` if this stream isn't backed by one.
     #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
-    pub fn pipe_reader_writer(pipe_reader: PipeReader, pipe_writer: PipeWriter) -> Self {
-        let raw_read_fd = pipe_reader.as_raw_fd();
-        let raw_write_fd = pipe_writer.as_raw_fd();
-        Self {
-            read_descriptor: ManuallyDrop::new(unsafe { File::from_raw_fd(raw_read_fd) }),
-            write_descriptor: ManuallyDrop::new(unsafe { File::from_raw_fd(raw_write_fd) }),
-            resources: ReadWriteResources::PipeReaderWriter((pipe_reader, pipe_writer)),
+    pub fn into_pipe_reader_writer(self) -> Result<(PipeReader, PipeWriter), Self> {
+        match self.resources {
+            ReadWriteResources::PipeReaderWriter(resource) => Ok(resource),
+            _ => Err(self),
+        }
+    }
+
+    /// Recover the child process' standard output and input this stream
+    /// reads from and writes to.
+    ///
+    /// Returns `Err(self)` if this stream isn't backed by one.
+    #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+    pub fn into_child_stdout_stdin(self) -> Result<(ChildStdout, ChildStdin), Self> {
+        match self.resources {
+            ReadWriteResources::ChildStdoutStdin(resource) => Ok(resource),
+            _ => Err(self),
+        }
+    }
+
+    /// Recover the child process spawned by [`interact_with_command`], with
+    /// its standard output and input in [`Child::stdout`] and
+    /// [`Child::stdin`].
+    ///
+    /// Returns `Err(self)` if this stream isn't backed by one.
+    ///
+    /// [`interact_with_command`]: Self::interact_with_command
+    /// [`Child::stdout`]: std::process::Child::stdout
+    /// [`Child::stdin`]: std::process::Child::stdin
+    #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+    pub fn into_child(self) -> Result<Child, Self> {
+        match self.resources {
+            ReadWriteResources::Child(child) => Ok(child.into_child()),
+            _ => Err(self),
+        }
+    }
+
+    /// Recover the file descriptor this stream reads from and writes to,
+    /// when the stream uses a single descriptor for both and owns it
+    /// outright, such as for a character device, socket, or the master side
+    /// of a pseudo-terminal with no child process.
+    ///
+    /// Returns `Err(self)` otherwise.
+    pub fn into_owned_fd(self) -> Result<OwnedFd, Self> {
+        match self.resources {
+            ReadWriteResources::CharDevice(resource) => Ok(resource.into()),
+            ReadWriteResources::TcpStream(resource) => Ok(resource.into()),
+            #[cfg(unix)]
+            ReadWriteResources::UnixStream(resource) => Ok(resource.into()),
+            #[cfg(unix)]
+            ReadWriteResources::Pty(resource) => Ok(resource.into()),
+            _ => Err(self),
+        }
+    }
+
+    /// Split this stream into a [`ReadHandle`] and a [`WriteHandle`], which
+    /// can be used independently, such as from different threads.
+    ///
+    /// Where the two sides have separate resources, such as a pair of pipes,
+    /// each half takes its own, so dropping the write half closes the writing
+    /// side. A child process spawned by [`interact_with_command`] goes with
+    /// the read half, which can wait for it, while the write half takes its
+    /// standard input. Otherwise, such as for a socket, the halves share the
+    /// resources, which are released once both halves are dropped.
+    ///
+    /// [`interact_with_command`]: Self::interact_with_command
+    pub fn into_split(self) -> (ReadHandle, WriteHandle) {
+        let (read_resources, write_resources) = self.resources.split();
+        (
+            ReadHandle {
+                descriptor: self.read_descriptor,
+                resources: read_resources,
+                read_timeout: self.read_timeout,
+            },
+            WriteHandle {
+                descriptor: self.write_descriptor,
+                resources: write_resources,
+                write_timeout: self.write_timeout,
+            },
+        )
+    }
+
+    /// Put the halves made by [`into_split`] back together.
+    ///
+    /// This also combines other halves whose resources fit together, such as
+    /// a pipe reader and a pipe writer. Otherwise, it returns the halves
+    /// unchanged.
+    ///
+    /// [`into_split`]: Self::into_split
+    #[allow(clippy::result_large_err)] // The halves are handed back on failure.
+    pub fn reunite(
+        read: ReadHandle,
+        write: WriteHandle,
+    ) -> Result<Self, (ReadHandle, WriteHandle)> {
+        match ReadWriteResources::reunite(read.resources, write.resources) {
+            Ok(resources) => Ok(Self {
+                read_descriptor: read.descriptor,
+                write_descriptor: write.descriptor,
+                resources,
+                read_timeout: read.read_timeout,
+                write_timeout: write.write_timeout,
+            }),
+            Err((read_resources, write_resources)) => Err((
+                ReadHandle {
+                    descriptor: read.descriptor,
+                    resources: read_resources,
+                    read_timeout: read.read_timeout,
+                },
+                WriteHandle {
+                    descriptor: write.descriptor,
+                    resources: write_resources,
+                    write_timeout: write.write_timeout,
+                },
+            )),
         }
     }
 
+    /// Wait for a piped thread to exit and report its result, leaving just
+    /// the pipes.
+    #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+    fn join_piped_thread(&mut self) -> io::Result<()> {
+        if let ReadWriteResources::PipedThread(piped_thread) = &mut self.resources {
+            if let Some((pipe_reader, pipe_writer, mut duplex_thread)) = piped_thread.take() {
+                self.resources = ReadWriteResources::PipeReaderWriter((pipe_reader, pipe_writer));
+                return duplex_thread.join();
+            }
+        }
+        Ok(())
+    }
+
     fn map_err(&mut self, e: io::Error) -> io::Error {
-        match &mut self.resources {
-            _ => e,
+        // A broken pipe means the thread has exited; report its error, if it
+        // had one.
+        #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+        if e.kind() == io::ErrorKind::BrokenPipe
+            && matches!(self.resources, ReadWriteResources::PipedThread(..))
+        {
+            return self.join_piped_thread().err().unwrap_or(e);
+        }
+        e
+    }
+}
+
+/// The kinds of file descriptor that `From<OwnedFd>` distinguishes.
+enum FdKind {
+    #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+    Pipe,
+    #[cfg(unix)]
+    TcpStream,
+    #[cfg(unix)]
+    UnixStream,
+    Other,
+}
+
+/// Determine what kind of I/O object `fd` refers to, using `fstat`, and for
+/// sockets, `getsockopt` and `getsockname`.
+fn fd_kind(fd: &OwnedFd) -> FdKind {
+    let raw_fd = fd.as_raw_fd();
+    let mut stat = MaybeUninit::<libc::stat>::uninit();
+    if unsafe { libc::fstat(raw_fd, stat.as_mut_ptr()) } == -1 {
+        return FdKind::Other;
+    }
+    match unsafe { stat.assume_init() }.st_mode & libc::S_IFMT {
+        #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+        libc::S_IFIFO => FdKind::Pipe,
+        #[cfg(unix)]
+        libc::S_IFSOCK => socket_kind(raw_fd),
+        _ => FdKind::Other,
+    }
+}
+
+/// Determine whether the socket `raw_fd` is a TCP stream or a Unix-domain
+/// stream.
+#[cfg(unix)]
+fn socket_kind(raw_fd: RawFd) -> FdKind {
+    let mut socket_type: libc::c_int = 0;
+    let mut len = mem::size_of::<libc::c_int>() as libc::socklen_t;
+    if unsafe {
+        libc::getsockopt(
+            raw_fd,
+            libc::SOL_SOCKET,
+            libc::SO_TYPE,
+            (&mut socket_type as *mut libc::c_int).cast(),
+            &mut len,
+        )
+    } == -1
+        || socket_type != libc::SOCK_STREAM
+    {
+        return FdKind::Other;
+    }
+    let mut addr = MaybeUninit::<libc::sockaddr_storage>::zeroed();
+    let mut len = mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+    if unsafe { libc::getsockname(raw_fd, addr.as_mut_ptr().cast(), &mut len) } == -1 {
+        return FdKind::Other;
+    }
+    match libc::c_int::from(unsafe { addr.assume_init() }.ss_family) {
+        libc::AF_UNIX => FdKind::UnixStream,
+        libc::AF_INET | libc::AF_INET6 => FdKind::TcpStream,
+        _ => FdKind::Other,
+    }
+}
+
+/// Construct the error reported when cloning is requested on a stream that
+/// can't be cloned.
+fn not_cloneable() -> io::Error {
+    io::Error::new(io::ErrorKind::Unsupported, "stream can't be cloned")
+}
+
+/// Create a pipe and close its reading end, to stand in for the writing side
+/// of a stream which has been shut down, so that writes fail with
+/// `BrokenPipe`.
+#[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+fn broken_pipe() -> io::Result<PipeWriter> {
+    let (pipe_reader, pipe_writer) = pipe()?;
+    drop(pipe_reader);
+    Ok(pipe_writer)
+}
+
+/// Like [`broken_pipe`], but for a child process' standard input.
+#[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+fn broken_child_stdin() -> io::Result<ChildStdin> {
+    let raw_fd = broken_pipe()?.into_raw_fd();
+    Ok(ChildStdin::from(unsafe { OwnedFd::from_raw_fd(raw_fd) }))
+}
+
+/// Forward just `read`, so that std's default implementations of the other
+/// `Read` functions are used, which apply the read timeout to each `read`.
+struct ByRead<'a, R: Read>(&'a mut R);
+
+impl<R: Read> Read for ByRead<'_, R> {
+    #[inline]
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+/// Forward just `write` and `flush`, so that std's default implementations of
+/// the other `Write` functions are used, which apply the write timeout to
+/// each `write`.
+struct ByWrite<'a, W: Write>(&'a mut W);
+
+impl<W: Write> Write for ByWrite<'_, W> {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+impl ReadWriteResources {
+    fn kind(&self) -> HandleKind {
+        match self {
+            #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+            Self::PipeReaderWriter(_) => HandleKind::Pipe,
+            Self::StdinStdout(_) | Self::StdinStderr(_) => HandleKind::Stdio,
+            #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+            Self::Child(_) | Self::ChildStdoutStdin(_) => HandleKind::Child,
+            Self::CharDevice(_) => HandleKind::CharDevice,
+            Self::TcpStream(_) => HandleKind::TcpStream,
+            #[cfg(unix)]
+            Self::UnixStream(_) => HandleKind::UnixStream,
+            #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+            Self::PipedThread(_) => HandleKind::PipedThread,
+            #[cfg(unix)]
+            Self::Pty(_) | Self::PtyChild(_) => HandleKind::Pty,
+        }
+    }
+
+    /// Divide these resources between the halves of a split stream.
+    fn split(self) -> (ReadResources, WriteResources) {
+        match self {
+            #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+            Self::PipeReaderWriter((pipe_reader, pipe_writer)) => (
+                ReadResources::PipeReader(pipe_reader),
+                WriteResources::PipeWriter(pipe_writer),
+            ),
+            Self::StdinStdout((stdin_locker, stdout_locker)) => (
+                ReadResources::Stdin(stdin_locker),
+                WriteResources::Stdout(stdout_locker),
+            ),
+            Self::StdinStderr((stdin_locker, stderr_locker)) => (
+                ReadResources::Stdin(stdin_locker),
+                WriteResources::Stderr(stderr_locker),
+            ),
+            #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+            Self::ChildStdoutStdin((child_stdout, child_stdin)) => (
+                ReadResources::ChildStdout(child_stdout),
+                WriteResources::ChildStdin(child_stdin),
+            ),
+            #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+            Self::Child(mut child) => match child.take_stdin() {
+                Some(child_stdin) => (
+                    ReadResources::Child(child),
+                    WriteResources::ChildStdin(child_stdin),
+                ),
+                None => Self::Child(child).share(),
+            },
+            resources => resources.share(),
+        }
+    }
+
+    /// Share these resources between the halves of a split stream.
+    fn share(self) -> (ReadResources, WriteResources) {
+        let shared = Arc::new(self);
+        (
+            ReadResources::Shared(Arc::clone(&shared)),
+            WriteResources::Shared(shared),
+        )
+    }
+
+    /// Combine the resources of the halves of a split stream.
+    #[allow(clippy::result_large_err)] // The resources are handed back on failure.
+    fn reunite(
+        read: ReadResources,
+        write: WriteResources,
+    ) -> Result<Self, (ReadResources, WriteResources)> {
+        match (read, write) {
+            #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+            (ReadResources::PipeReader(pipe_reader), WriteResources::PipeWriter(pipe_writer)) => {
+                Ok(Self::PipeReaderWriter((pipe_reader, pipe_writer)))
+            }
+            (ReadResources::Stdin(stdin_locker), WriteResources::Stdout(stdout_locker)) => {
+                Ok(Self::StdinStdout((stdin_locker, stdout_locker)))
+            }
+            (ReadResources::Stdin(stdin_locker), WriteResources::Stderr(stderr_locker)) => {
+                Ok(Self::StdinStderr((stdin_locker, stderr_locker)))
+            }
+            #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+            (ReadResources::ChildStdout(child_stdout), WriteResources::ChildStdin(child_stdin)) => {
+                Ok(Self::ChildStdoutStdin((child_stdout, child_stdin)))
+            }
+            #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+            (ReadResources::Child(mut child), WriteResources::ChildStdin(child_stdin)) => {
+                match child.restore_stdin(child_stdin) {
+                    Ok(()) => Ok(Self::Child(child)),
+                    Err(child_stdin) => Err((
+                        ReadResources::Child(child),
+                        WriteResources::ChildStdin(child_stdin),
+                    )),
+                }
+            }
+            (ReadResources::Shared(read), WriteResources::Shared(write))
+                if Arc::ptr_eq(&read, &write) =>
+            {
+                drop(write);
+                Arc::try_unwrap(read).map_err(|shared| {
+                    (
+                        ReadResources::Shared(Arc::clone(&shared)),
+                        WriteResources::Shared(shared),
+                    )
+                })
+            }
+            halves => Err(halves),
         }
     }
 }
@@ -539,16 +2599,24 @@ impl ReadWriteHandle {
 impl Read for ReadHandle {
     #[inline]
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.ready_to_read()?;
         match self.descriptor.read(buf) {
+            Ok(0) if !buf.is_empty() => self.end_of_stream().map(|()| 0),
             Ok(size) => Ok(size),
+            #[cfg(unix)]
+            Err(e) if self.is_pty() && is_hangup(&e) => Ok(0),
             Err(e) => Err(self.map_err(e)),
         }
     }
 
     #[inline]
     fn read_vectored(&mut self, bufs: &mut [IoSliceMut]) -> io::Result<usize> {
+        self.ready_to_read()?;
         match self.descriptor.read_vectored(bufs) {
+            Ok(0) if bufs.iter().any(|b| !b.is_empty()) => self.end_of_stream().map(|()| 0),
             Ok(size) => Ok(size),
+            #[cfg(unix)]
+            Err(e) if self.is_pty() && is_hangup(&e) => Ok(0),
             Err(e) => Err(self.map_err(e)),
         }
     }
@@ -561,34 +2629,60 @@ impl Read for ReadHandle {
 
     #[inline]
     fn read_to_end(&mut self, buf: &mut Vec<u8>) -> io::Result<usize> {
+        if self.by_read() {
+            return ByRead(self).read_to_end(buf);
+        }
         match self.descriptor.read_to_end(buf) {
-            Ok(size) => Ok(size),
+            Ok(size) => self.end_of_stream().map(|()| size),
             Err(e) => Err(self.map_err(e)),
         }
     }
 
     #[inline]
     fn read_to_string(&mut self, buf: &mut String) -> io::Result<usize> {
+        if self.by_read() {
+            return ByRead(self).read_to_string(buf);
+        }
         match self.descriptor.read_to_string(buf) {
-            Ok(size) => Ok(size),
+            Ok(size) => self.end_of_stream().map(|()| size),
             Err(e) => Err(self.map_err(e)),
         }
     }
 
     #[inline]
     fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        if self.by_read() {
+            return ByRead(self).read_exact(buf);
+        }
         match self.descriptor.read_exact(buf) {
             Ok(()) => Ok(()),
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                self.end_of_stream()?;
+                Err(e)
+            }
             Err(e) => Err(self.map_err(e)),
         }
     }
 }
 
+impl Seek for ReadHandle {
+    #[inline]
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        if !self.is_seekable() {
+            return Err(not_seekable());
+        }
+        self.descriptor.seek(pos)
+    }
+}
+
 impl Write for WriteHandle {
     #[inline]
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        match self.descriptor.write(buf) {
-            Ok(size) => Ok(size),
+        match self.timed_write(|descriptor| descriptor.write(buf)) {
+            Ok(size) => {
+                self.wrote(size);
+                Ok(size)
+            }
             Err(e) => Err(self.map_err(e)),
         }
     }
@@ -597,17 +2691,13 @@ impl Write for WriteHandle {
     fn flush(&mut self) -> io::Result<()> {
         match self.descriptor.flush() {
             Ok(()) => {
-                // There's no way to send a flush event through a pipe, so for
-                // now, force a flush by closing the pipe, waiting for the
-                // thread to exit, recover the boxed writer, and then wrap it
-                // in a whole new piped thread.
+                // Wait for the thread to drain the pipe and flush the boxed
+                // writer, through the side channel.
                 #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
-                if let WriteResources::PipedThread(piped_thread) = &mut self.resources {
-                    let (mut pipe_writer, join_handle) = piped_thread.take().unwrap();
-                    pipe_writer.flush()?;
-                    drop(pipe_writer);
-                    let boxed_write = join_handle.join().unwrap().unwrap();
-                    *self = Self::piped_thread(boxed_write)?;
+                if let WriteResources::PipedThread(_, flusher) = &self.resources {
+                    if !flusher.flush()? {
+                        return Err(self.map_err(io::ErrorKind::BrokenPipe.into()));
+                    }
                 }
                 Ok(())
             }
@@ -617,8 +2707,11 @@ impl Write for WriteHandle {
 
     #[inline]
     fn write_vectored(&mut self, bufs: &[IoSlice]) -> io::Result<usize> {
-        match self.descriptor.write_vectored(bufs) {
-            Ok(size) => Ok(size),
+        match self.timed_write(|descriptor| descriptor.write_vectored(bufs)) {
+            Ok(size) => {
+                self.wrote(size);
+                Ok(size)
+            }
             Err(e) => Err(self.map_err(e)),
         }
     }
@@ -631,6 +2724,9 @@ impl Write for WriteHandle {
 
     #[inline]
     fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        if self.by_write() {
+            return ByWrite(self).write_all(buf);
+        }
         match self.descriptor.write_all(buf) {
             Ok(()) => Ok(()),
             Err(e) => Err(self.map_err(e)),
@@ -640,6 +2736,9 @@ impl Write for WriteHandle {
     #[cfg(write_all_vectored)]
     #[inline]
     fn write_all_vectored(&mut self, bufs: &mut [IoSlice]) -> io::Result<()> {
+        if self.by_write() {
+            return ByWrite(self).write_all_vectored(bufs);
+        }
         match self.descriptor.write_all_vectored(bufs) {
             Ok(()) => Ok(()),
             Err(e) => Err(self.map_err(e)),
@@ -648,6 +2747,9 @@ impl Write for WriteHandle {
 
     #[inline]
     fn write_fmt(&mut self, fmt: Arguments) -> io::Result<()> {
+        if self.by_write() {
+            return ByWrite(self).write_fmt(fmt);
+        }
         match self.descriptor.write_fmt(fmt) {
             Ok(()) => Ok(()),
             Err(e) => Err(self.map_err(e)),
@@ -655,19 +2757,35 @@ impl Write for WriteHandle {
     }
 }
 
+impl Seek for WriteHandle {
+    #[inline]
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        if !self.is_seekable() {
+            return Err(not_seekable());
+        }
+        self.descriptor.seek(pos)
+    }
+}
+
 impl Read for ReadWriteHandle {
     #[inline]
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.ready_to_read()?;
         match self.read_descriptor.read(buf) {
             Ok(size) => Ok(size),
+            #[cfg(unix)]
+            Err(e) if self.is_pty() && is_hangup(&e) => Ok(0),
             Err(e) => Err(self.map_err(e)),
         }
     }
 
     #[inline]
     fn read_vectored(&mut self, bufs: &mut [IoSliceMut]) -> io::Result<usize> {
+        self.ready_to_read()?;
         match self.read_descriptor.read_vectored(bufs) {
             Ok(size) => Ok(size),
+            #[cfg(unix)]
+            Err(e) if self.is_pty() && is_hangup(&e) => Ok(0),
             Err(e) => Err(self.map_err(e)),
         }
     }
@@ -680,6 +2798,9 @@ impl Read for ReadWriteHandle {
 
     #[inline]
     fn read_to_end(&mut self, buf: &mut Vec<u8>) -> io::Result<usize> {
+        if self.by_read() {
+            return ByRead(self).read_to_end(buf);
+        }
         match self.read_descriptor.read_to_end(buf) {
             Ok(size) => Ok(size),
             Err(e) => Err(self.map_err(e)),
@@ -688,6 +2809,9 @@ impl Read for ReadWriteHandle {
 
     #[inline]
     fn read_to_string(&mut self, buf: &mut String) -> io::Result<usize> {
+        if self.by_read() {
+            return ByRead(self).read_to_string(buf);
+        }
         match self.read_descriptor.read_to_string(buf) {
             Ok(size) => Ok(size),
             Err(e) => Err(self.map_err(e)),
@@ -696,6 +2820,9 @@ impl Read for ReadWriteHandle {
 
     #[inline]
     fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        if self.by_read() {
+            return ByRead(self).read_exact(buf);
+        }
         match self.read_descriptor.read_exact(buf) {
             Ok(()) => Ok(()),
             Err(e) => Err(self.map_err(e)),
@@ -706,7 +2833,7 @@ impl Read for ReadWriteHandle {
 impl Write for ReadWriteHandle {
     #[inline]
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        match self.write_descriptor.write(buf) {
+        match self.timed_write(|descriptor| descriptor.write(buf)) {
             Ok(size) => Ok(size),
             Err(e) => Err(self.map_err(e)),
         }
@@ -722,7 +2849,7 @@ impl Write for ReadWriteHandle {
 
     #[inline]
     fn write_vectored(&mut self, bufs: &[IoSlice]) -> io::Result<usize> {
-        match self.write_descriptor.write_vectored(bufs) {
+        match self.timed_write(|descriptor| descriptor.write_vectored(bufs)) {
             Ok(size) => Ok(size),
             Err(e) => Err(self.map_err(e)),
         }
@@ -736,6 +2863,9 @@ impl Write for ReadWriteHandle {
 
     #[inline]
     fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        if self.write_timeout.is_some() {
+            return ByWrite(self).write_all(buf);
+        }
         match self.write_descriptor.write_all(buf) {
             Ok(()) => Ok(()),
             Err(e) => Err(self.map_err(e)),
@@ -745,6 +2875,9 @@ impl Write for ReadWriteHandle {
     #[cfg(write_all_vectored)]
     #[inline]
     fn write_all_vectored(&mut self, bufs: &mut [IoSlice]) -> io::Result<()> {
+        if self.write_timeout.is_some() {
+            return ByWrite(self).write_all_vectored(bufs);
+        }
         match self.write_descriptor.write_all_vectored(bufs) {
             Ok(()) => Ok(()),
             Err(e) => Err(self.map_err(e)),
@@ -753,6 +2886,9 @@ impl Write for ReadWriteHandle {
 
     #[inline]
     fn write_fmt(&mut self, fmt: Arguments) -> io::Result<()> {
+        if self.write_timeout.is_some() {
+            return ByWrite(self).write_fmt(fmt);
+        }
         match self.write_descriptor.write_fmt(fmt) {
             Ok(()) => Ok(()),
             Err(e) => Err(self.map_err(e)),
@@ -786,43 +2922,166 @@ impl AsRawReadWriteFd for ReadWriteHandle {
     }
 }
 
-impl Drop for ReadResources {
-    fn drop(&mut self) {
-        match self {
+impl AsFd for ReadHandle {
+    #[inline]
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.descriptor.as_fd()
+    }
+}
+
+impl AsFd for WriteHandle {
+    #[inline]
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.descriptor.as_fd()
+    }
+}
+
+impl AsReadWriteFd for ReadWriteHandle {
+    #[inline]
+    fn as_read_fd(&self) -> BorrowedFd<'_> {
+        self.read_descriptor.as_fd()
+    }
+
+    #[inline]
+    fn as_write_fd(&self) -> BorrowedFd<'_> {
+        self.write_descriptor.as_fd()
+    }
+}
+
+/// Read from an open file descriptor, taking ownership of it. The kind of
+/// the stream is determined with `fstat`: pipes are treated as a
+/// [`HandleKind::Pipe`], stream sockets as a [`HandleKind::TcpStream`] or a
+/// [`HandleKind::UnixStream`], and anything else, such as a regular file or
+/// a character device, as a [`HandleKind::File`].
+impl From<OwnedFd> for ReadHandle {
+    fn from(fd: OwnedFd) -> Self {
+        match fd_kind(&fd) {
             #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
-            Self::PipedThread(piped_thread) => {
-                let (pipe_reader, join_handle) = piped_thread.take().unwrap();
-                drop(pipe_reader);
-                join_handle.join().unwrap().unwrap();
-            }
-            _ => {}
+            FdKind::Pipe => Self::pipe_reader(unsafe { PipeReader::from_raw_fd(fd.into_raw_fd()) }),
+            #[cfg(unix)]
+            FdKind::TcpStream => Self::tcp_stream(TcpStream::from(fd)),
+            #[cfg(unix)]
+            FdKind::UnixStream => Self::unix_stream(UnixStream::from(fd)),
+            FdKind::Other => Self::file(File::from(fd)),
         }
     }
 }
 
-impl Drop for WriteResources {
-    fn drop(&mut self) {
-        match self {
+/// Write to an open file descriptor, taking ownership of it. The kind of the
+/// stream is determined with `fstat`: pipes are treated as a
+/// [`HandleKind::Pipe`], stream sockets as a [`HandleKind::TcpStream`] or a
+/// [`HandleKind::UnixStream`], and anything else, such as a regular file or
+/// a character device, as a [`HandleKind::File`].
+impl From<OwnedFd> for WriteHandle {
+    fn from(fd: OwnedFd) -> Self {
+        match fd_kind(&fd) {
             #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
-            Self::PipedThread(piped_thread) => {
-                if let Some((pipe_writer, join_handle)) = piped_thread.take() {
-                    drop(pipe_writer);
-                    join_handle.join().unwrap().unwrap();
-                }
-            }
-            _ => {}
+            FdKind::Pipe => Self::pipe_writer(unsafe { PipeWriter::from_raw_fd(fd.into_raw_fd()) }),
+            #[cfg(unix)]
+            FdKind::TcpStream => Self::tcp_stream(TcpStream::from(fd)),
+            #[cfg(unix)]
+            FdKind::UnixStream => Self::unix_stream(UnixStream::from(fd)),
+            FdKind::Other => Self::file(File::from(fd)),
         }
     }
 }
 
-impl Drop for ReadWriteResources {
-    fn drop(&mut self) {
-        match self {
-            _ => {}
+/// Interact with an open file descriptor, taking ownership of it. The kind
+/// of the stream is determined with `fstat`: stream sockets are treated as a
+/// [`HandleKind::TcpStream`] or a [`HandleKind::UnixStream`], a pipe, such
+/// as a FIFO opened for reading and writing, as a [`HandleKind::Pipe`], and
+/// anything else, such as a character device, as a
+/// [`HandleKind::CharDevice`].
+impl From<OwnedFd> for ReadWriteHandle {
+    fn from(fd: OwnedFd) -> Self {
+        match fd_kind(&fd) {
+            // A pipe needs a descriptor for each side; if it can't be
+            // duplicated, fall back to treating it as a character device.
+            #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+            FdKind::Pipe => match fd.try_clone() {
+                Ok(write_fd) => Self::pipe_reader_writer(
+                    unsafe { PipeReader::from_raw_fd(fd.into_raw_fd()) },
+                    unsafe { PipeWriter::from_raw_fd(write_fd.into_raw_fd()) },
+                ),
+                Err(_) => Self::char_device(File::from(fd)),
+            },
+            #[cfg(unix)]
+            FdKind::TcpStream => Self::tcp_stream(TcpStream::from(fd)),
+            #[cfg(unix)]
+            FdKind::UnixStream => Self::unix_stream(UnixStream::from(fd)),
+            FdKind::Other => Self::char_device(File::from(fd)),
         }
     }
 }
 
+/// Use a stream as a child process' standard input, such as with
+/// [`Command::stdin`]; see [`ReadHandle::try_into_stdio`].
+///
+/// A piped thread keeps feeding the child on its own, and a child process
+/// backing the stream is waited for on a background thread. Their errors,
+/// including an unsuccessful exit, aren't reported; use `try_into_stdio` and
+/// close its guard to observe them. Fails if a shared descriptor can't be
+/// duplicated.
+#[cfg(unix)]
+impl TryFrom<ReadHandle> for Stdio {
+    type Error = io::Error;
+
+    fn try_from(handle: ReadHandle) -> io::Result<Self> {
+        let (stdio, guard) = handle.try_into_stdio()?;
+        guard.detach();
+        Ok(stdio)
+    }
+}
+
+/// Use a stream as a child process' standard output or error, such as with
+/// [`Command::stdout`]; see [`WriteHandle::try_into_stdio`].
+///
+/// A piped thread keeps consuming the child's output on its own, and a child
+/// process backing the stream is waited for on a background thread. Their
+/// errors, including an unsuccessful exit, aren't reported; use
+/// `try_into_stdio` and close its guard to observe them. Fails if a shared
+/// descriptor can't be duplicated.
+#[cfg(unix)]
+impl TryFrom<WriteHandle> for Stdio {
+    type Error = io::Error;
+
+    fn try_from(handle: WriteHandle) -> io::Result<Self> {
+        let (stdio, guard) = handle.try_into_stdio()?;
+        guard.detach();
+        Ok(stdio)
+    }
+}
+
+/// See [`ReadHandle::into_owned_fd`].
+impl TryFrom<ReadHandle> for OwnedFd {
+    type Error = ReadHandle;
+
+    #[inline]
+    fn try_from(handle: ReadHandle) -> Result<Self, ReadHandle> {
+        handle.into_owned_fd()
+    }
+}
+
+/// See [`WriteHandle::into_owned_fd`].
+impl TryFrom<WriteHandle> for OwnedFd {
+    type Error = WriteHandle;
+
+    #[inline]
+    fn try_from(handle: WriteHandle) -> Result<Self, WriteHandle> {
+        handle.into_owned_fd()
+    }
+}
+
+/// See [`ReadWriteHandle::into_owned_fd`].
+impl TryFrom<ReadWriteHandle> for OwnedFd {
+    type Error = ReadWriteHandle;
+
+    #[inline]
+    fn try_from(handle: ReadWriteHandle) -> Result<Self, ReadWriteHandle> {
+        handle.into_owned_fd()
+    }
+}
+
 impl Debug for ReadHandle {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let mut b = f.debug_struct("ReadHandle");
@@ -832,8 +3091,8 @@ impl Debug for ReadHandle {
         // unavailable to safe Rust code.
         b.field("raw_fd", &self.as_raw_fd());
 
-        // Don't print the resources, as we don't want to leak that
-        // information through our abstraction.
+        // Don't print the resources themselves, just what kind they are.
+        b.field("kind", &self.kind());
 
         b.finish()
     }
@@ -848,8 +3107,8 @@ impl Debug for WriteHandle {
         // unavailable to safe Rust code.
         b.field("raw_fd", &self.as_raw_fd());
 
-        // Don't print the resources, as we don't want to leak that
-        // information through our abstraction.
+        // Don't print the resources themselves, just what kind they are.
+        b.field("kind", &self.kind());
 
         b.finish()
     }
@@ -865,8 +3124,8 @@ impl Debug for ReadWriteHandle {
         b.field("raw_read_fd", &self.as_raw_read_fd());
         b.field("raw_write_fd", &self.as_raw_write_fd());
 
-        // Don't print the resources, as we don't want to leak that
-        // information through our abstraction.
+        // Don't print the resources themselves, just what kind they are.
+        b.field("kind", &self.kind());
 
         b.finish()
     }
@@ -0,0 +1,94 @@
+//! Pseudo-terminals.
+
+use std::{
+    ffi::{CStr, OsStr},
+    fs::{File, OpenOptions},
+    io,
+    os::unix::{
+        ffi::OsStrExt,
+        fs::OpenOptionsExt,
+        io::{AsRawFd, FromRawFd},
+        process::CommandExt,
+    },
+    path::PathBuf,
+    process::Command,
+};
+
+/// Open a new pseudo-terminal, returning its master side, its slave side,
+/// and the path of its slave side.
+pub(crate) fn open_pty() -> io::Result<(File, File, PathBuf)> {
+    let raw_fd = unsafe { libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY) };
+    if raw_fd == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    let master = unsafe { File::from_raw_fd(raw_fd) };
+    if unsafe { libc::fcntl(raw_fd, libc::F_SETFD, libc::FD_CLOEXEC) } == -1
+        || unsafe { libc::grantpt(raw_fd) } == -1
+        || unsafe { libc::unlockpt(raw_fd) } == -1
+    {
+        return Err(io::Error::last_os_error());
+    }
+    let path = slave_path(&master)?;
+    let slave = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .custom_flags(libc::O_NOCTTY)
+        .open(&path)?;
+    Ok((master, slave, path))
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn slave_path(master: &File) -> io::Result<PathBuf> {
+    let mut buf = [0 as libc::c_char; 128];
+    let err = unsafe { libc::ptsname_r(master.as_raw_fd(), buf.as_mut_ptr(), buf.len()) };
+    if err != 0 {
+        return Err(io::Error::from_raw_os_error(err));
+    }
+    let name = unsafe { CStr::from_ptr(buf.as_ptr()) };
+    Ok(PathBuf::from(OsStr::from_bytes(name.to_bytes())))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn slave_path(master: &File) -> io::Result<PathBuf> {
+    use std::sync::Mutex;
+
+    // `ptsname` returns a pointer to a static buffer, so serialize calls.
+    static PTSNAME: Mutex<()> = Mutex::new(());
+    let _guard = PTSNAME.lock().unwrap_or_else(|e| e.into_inner());
+    let name = unsafe { libc::ptsname(master.as_raw_fd()) };
+    if name.is_null() {
+        return Err(io::Error::last_os_error());
+    }
+    let name = unsafe { CStr::from_ptr(name) };
+    Ok(PathBuf::from(OsStr::from_bytes(name.to_bytes())))
+}
+
+/// Arrange for `command` to run in a new session, with the terminal on its
+/// standard input as its controlling terminal.
+pub(crate) fn set_controlling_terminal(command: &mut Command) {
+    // Safety: `setsid` and `ioctl` are async-signal-safe.
+    unsafe {
+        command.pre_exec(|| {
+            if libc::setsid() == -1 || libc::ioctl(0, libc::TIOCSCTTY as _, 0) == -1 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+}
+
+/// On Linux, reading from the master side of a pseudo-terminal fails with
+/// `EIO` once the slave side is closed everywhere, rather than returning 0.
+pub(crate) fn is_hangup(e: &io::Error) -> bool {
+    e.raw_os_error() == Some(libc::EIO)
+}
+
+/// Read and discard the output of a pseudo-terminal until the slave side is
+/// closed everywhere, so that a child process writing to it doesn't block
+/// while we wait for it to exit.
+pub(crate) fn drain_pty_master(mut master: &File) -> io::Result<()> {
+    match io::copy(&mut master, &mut io::sink()) {
+        Err(e) if !is_hangup(&e) => Err(e),
+        _ => Ok(()),
+    }
+}
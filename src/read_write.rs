@@ -1,8 +1,8 @@
 use std::io::{Read, Write};
 #[cfg(unix)]
-use std::os::unix::io::RawFd;
+use std::os::unix::io::{BorrowedFd, RawFd};
 #[cfg(target_os = "wasi")]
-use std::os::wasi::io::RawFd;
+use std::os::wasi::io::{BorrowedFd, RawFd};
 #[cfg(windows)]
 use std::os::windows::io::{RawHandle, RawSocket};
 
@@ -37,6 +37,30 @@ pub trait AsRawReadWriteFd {
     fn as_raw_write_fd(&self) -> RawFd;
 }
 
+/// Like [`std::os::unix::io::AsFd`], but specifically for use with
+/// [`ReadWrite`] implementations which may contain both reading and writing
+/// file descriptors.
+///
+/// [`std::os::unix::io::AsFd`]: https://doc.rust-lang.org/std/os/unix/io/trait.AsFd.html
+#[cfg(not(windows))]
+pub trait AsReadWriteFd {
+    /// Borrows the file descriptor for reading.
+    ///
+    /// Like [`std::os::unix::io::AsFd::as_fd`], but returns the reading file
+    /// descriptor of a [`ReadWrite`] implementation.
+    ///
+    /// [`std::os::unix::io::AsFd::as_fd`]: https://doc.rust-lang.org/std/os/unix/io/trait.AsFd.html#tymethod.as_fd
+    fn as_read_fd(&self) -> BorrowedFd<'_>;
+
+    /// Borrows the file descriptor for writing.
+    ///
+    /// Like [`std::os::unix::io::AsFd::as_fd`], but returns the writing file
+    /// descriptor of a [`ReadWrite`] implementation.
+    ///
+    /// [`std::os::unix::io::AsFd::as_fd`]: https://doc.rust-lang.org/std/os/unix/io/trait.AsFd.html#tymethod.as_fd
+    fn as_write_fd(&self) -> BorrowedFd<'_>;
+}
+
 /// Like [`std::os::windows::io::AsRawHandle`] and
 /// [`std::os::windows::io::AsRawSocket`], but for types which may or may not
 /// contain a raw handle or raw socket at runtime.
@@ -0,0 +1,137 @@
+//! Seeking and positional I/O on streams backed by regular files.
+
+#[cfg(unix)]
+use std::os::unix::fs::FileExt;
+#[cfg(target_os = "linux")]
+use std::os::unix::io::AsRawFd;
+#[cfg(target_os = "wasi")]
+use std::os::wasi::fs::FileExt;
+#[cfg(windows)]
+use std::os::windows::fs::FileExt;
+use std::{
+    fs::File,
+    io::{self, IoSlice, IoSliceMut},
+};
+
+/// Test whether `file` is a regular file, and so supports seeking.
+#[inline]
+pub(crate) fn is_seekable(file: &File) -> bool {
+    file.metadata().map(|m| m.is_file()).unwrap_or(false)
+}
+
+/// Construct the error reported when seeking or positional I/O is requested
+/// on a stream that isn't backed by a regular file.
+pub(crate) fn not_seekable() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        "stream is not backed by a regular file",
+    )
+}
+
+/// Read from `file` at `offset`.
+pub(crate) fn read_at(file: &File, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+    if !is_seekable(file) {
+        return Err(not_seekable());
+    }
+    #[cfg(not(windows))]
+    {
+        file.read_at(buf, offset)
+    }
+    #[cfg(windows)]
+    {
+        file.seek_read(buf, offset)
+    }
+}
+
+/// Write to `file` at `offset`.
+pub(crate) fn write_at(file: &File, buf: &[u8], offset: u64) -> io::Result<usize> {
+    if !is_seekable(file) {
+        return Err(not_seekable());
+    }
+    #[cfg(not(windows))]
+    {
+        file.write_at(buf, offset)
+    }
+    #[cfg(windows)]
+    {
+        file.seek_write(buf, offset)
+    }
+}
+
+/// Read from `file` at `offset` into `bufs`. Where there's no `preadv`, this
+/// reads into the first non-empty buffer, like the default
+/// `Read::read_vectored`.
+pub(crate) fn read_vectored_at(
+    file: &File,
+    bufs: &mut [IoSliceMut],
+    offset: u64,
+) -> io::Result<usize> {
+    #[cfg(target_os = "linux")]
+    {
+        if !is_seekable(file) {
+            return Err(not_seekable());
+        }
+        // `IoSliceMut` is ABI-compatible with `iovec`.
+        let n = unsafe {
+            libc::preadv(
+                file.as_raw_fd(),
+                bufs.as_ptr().cast::<libc::iovec>(),
+                iov_count(bufs.len()),
+                offset as libc::off_t,
+            )
+        };
+        if n == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(n as usize)
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let buf = bufs
+            .iter_mut()
+            .find(|b| !b.is_empty())
+            .map_or(&mut [][..], |b| &mut **b);
+        read_at(file, buf, offset)
+    }
+}
+
+/// Write to `file` at `offset` from `bufs`. Where there's no `pwritev`, this
+/// writes from the first non-empty buffer, like the default
+/// `Write::write_vectored`.
+pub(crate) fn write_vectored_at(file: &File, bufs: &[IoSlice], offset: u64) -> io::Result<usize> {
+    #[cfg(target_os = "linux")]
+    {
+        if !is_seekable(file) {
+            return Err(not_seekable());
+        }
+        // `IoSlice` is ABI-compatible with `iovec`.
+        let n = unsafe {
+            libc::pwritev(
+                file.as_raw_fd(),
+                bufs.as_ptr().cast::<libc::iovec>(),
+                iov_count(bufs.len()),
+                offset as libc::off_t,
+            )
+        };
+        if n == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(n as usize)
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let buf = bufs
+            .iter()
+            .find(|b| !b.is_empty())
+            .map_or(&[][..], |b| &**b);
+        write_at(file, buf, offset)
+    }
+}
+
+/// Clamp a number of buffers to what `preadv` and `pwritev` accept; as with
+/// `read_vectored` and `write_vectored`, they may do a partial transfer.
+#[cfg(target_os = "linux")]
+#[inline]
+fn iov_count(len: usize) -> libc::c_int {
+    len.min(libc::UIO_MAXIOV as usize) as libc::c_int
+}
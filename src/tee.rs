@@ -0,0 +1,41 @@
+//! Duplicating a stream's input to additional outputs.
+
+use crate::{ReadHandle, WriteHandle};
+use std::io::{self, Read, Write};
+
+/// A reader which writes everything it reads from `source` to each of
+/// `sinks`, and closes them at the end of the stream.
+pub(crate) struct Tee {
+    source: ReadHandle,
+    sinks: Vec<WriteHandle>,
+}
+
+impl Tee {
+    #[inline]
+    pub(crate) fn new(source: ReadHandle, sinks: Vec<WriteHandle>) -> Self {
+        Self { source, sinks }
+    }
+}
+
+impl Read for Tee {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let size = self.source.read(buf)?;
+        if size == 0 {
+            // Close every sink, so that piped threads and child processes
+            // are waited for, and report the first error.
+            let mut result = Ok(());
+            for sink in self.sinks.drain(..) {
+                let closed = sink.close();
+                if result.is_ok() {
+                    result = closed;
+                }
+            }
+            result?;
+        } else {
+            for sink in &mut self.sinks {
+                sink.write_all(&buf[..size])?;
+            }
+        }
+        Ok(size)
+    }
+}
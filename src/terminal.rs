@@ -0,0 +1,97 @@
+//! Terminal sizes and modes.
+
+use std::{
+    fmt, io,
+    mem::MaybeUninit,
+    ops::{Deref, DerefMut},
+    os::unix::io::RawFd,
+};
+
+/// Return the size of the terminal `raw_fd` refers to, as columns and rows.
+pub(crate) fn terminal_size(raw_fd: RawFd) -> io::Result<(u16, u16)> {
+    let mut winsize = MaybeUninit::<libc::winsize>::uninit();
+    if unsafe { libc::ioctl(raw_fd, libc::TIOCGWINSZ, winsize.as_mut_ptr()) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    let winsize = unsafe { winsize.assume_init() };
+    Ok((winsize.ws_col, winsize.ws_row))
+}
+
+/// A mode for [`TerminalModeGuard`] to put a terminal in.
+#[derive(Clone, Copy)]
+pub(crate) enum Mode {
+    /// Input is available byte by byte, without echoing, line editing, or
+    /// signal characters, and output isn't post-processed.
+    Raw,
+    /// Like the original mode, but without echoing.
+    NoEcho,
+}
+
+/// A guard which holds a stream, whose terminal has been put in a different
+/// mode, such as by [`ReadHandle::raw_mode`]. The stream can be used through
+/// the guard, and dropping the guard, including when unwinding from a panic,
+/// restores the terminal's original mode.
+///
+/// [`ReadHandle::raw_mode`]: crate::ReadHandle::raw_mode
+pub struct TerminalModeGuard<'a, T> {
+    stream: &'a mut T,
+    raw_fd: RawFd,
+    original: libc::termios,
+}
+
+impl<'a, T> TerminalModeGuard<'a, T> {
+    pub(crate) fn new(stream: &'a mut T, raw_fd: RawFd, mode: Mode) -> io::Result<Self> {
+        let mut termios = MaybeUninit::<libc::termios>::uninit();
+        if unsafe { libc::tcgetattr(raw_fd, termios.as_mut_ptr()) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        let original = unsafe { termios.assume_init() };
+        let mut termios = original;
+        match mode {
+            Mode::Raw => unsafe { libc::cfmakeraw(&mut termios) },
+            Mode::NoEcho => termios.c_lflag &= !(libc::ECHO | libc::ECHONL),
+        }
+        if unsafe { libc::tcsetattr(raw_fd, libc::TCSANOW, &termios) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self {
+            stream,
+            raw_fd,
+            original,
+        })
+    }
+}
+
+impl<T> Deref for TerminalModeGuard<'_, T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        self.stream
+    }
+}
+
+impl<T> DerefMut for TerminalModeGuard<'_, T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        self.stream
+    }
+}
+
+impl<T> Drop for TerminalModeGuard<'_, T> {
+    fn drop(&mut self) {
+        // Let pending output drain first, so that it's processed in the mode
+        // it was written in. Errors can't be reported from `drop`.
+        unsafe {
+            libc::tcsetattr(self.raw_fd, libc::TCSADRAIN, &self.original);
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for TerminalModeGuard<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TerminalModeGuard")
+            .field("stream", &self.stream)
+            .finish()
+    }
+}
@@ -0,0 +1,311 @@
+//! Asynchronous adapters for [`ReadHandle`], [`WriteHandle`], and
+//! [`ReadWriteHandle`], implementing tokio's [`AsyncRead`] and
+//! [`AsyncWrite`].
+
+use crate::{
+    async_handles::{Direction, Fd, Inner, Runtime},
+    AsRawReadWriteFd, ReadHandle, ReadWriteHandle, WriteHandle,
+};
+use std::{
+    fmt::{self, Debug},
+    future::Future,
+    io,
+    os::unix::io::{AsRawFd, RawFd},
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+use tokio::{
+    io::{unix::AsyncFd, AsyncRead, AsyncWrite, Interest, ReadBuf},
+    task::{spawn_blocking, JoinHandle},
+};
+
+/// An asynchronous adapter for [`ReadHandle`], implementing tokio's
+/// [`AsyncRead`].
+///
+/// If the underlying descriptor can be polled, it's put in non-blocking mode
+/// for as long as this adapter is live. Otherwise, and for piped threads and
+/// child processes, which can wait for the thread or process at the end of
+/// the stream, reads are performed on tokio's blocking thread pool. The
+/// handle's read timeout is cleared; use tokio's timers instead.
+pub struct AsyncReadHandle {
+    raw_fd: RawFd,
+    inner: Inner<ReadHandle, Tokio>,
+}
+
+/// An asynchronous adapter for [`WriteHandle`], implementing tokio's
+/// [`AsyncWrite`].
+///
+/// If the underlying descriptor can be polled, it's put in non-blocking mode
+/// for as long as this adapter is live. Otherwise, and for piped threads,
+/// whose flush waits for the thread, writes are performed on tokio's blocking
+/// thread pool. In that case, as with tokio's `File`, a write is reported as
+/// complete once it has been handed off to the pool, and any error it
+/// encounters is reported by the next write or flush. The handle's write
+/// timeout is cleared; use tokio's timers instead.
+///
+/// [`AsyncWrite::poll_shutdown`] closes the handle, on the blocking thread
+/// pool, so that the other end sees the end of the stream, and reports any
+/// error [`WriteHandle::close`] does.
+pub struct AsyncWriteHandle {
+    raw_fd: RawFd,
+    inner: Inner<WriteHandle, Tokio>,
+}
+
+/// An asynchronous adapter for [`ReadWriteHandle`], implementing tokio's
+/// [`AsyncRead`] and [`AsyncWrite`].
+///
+/// If the underlying descriptors can both be polled, they're put in
+/// non-blocking mode for as long as this adapter is live. Otherwise, reads
+/// and writes are performed on tokio's blocking thread pool, one at a time,
+/// so a pending read delays any subsequent write until it completes. The
+/// handle's timeouts are cleared; use tokio's timers instead.
+///
+/// [`AsyncWrite::poll_shutdown`] shuts down the writing side with
+/// [`ReadWriteHandle::shutdown_write`], so that the other end sees the end of
+/// the stream, while reading continues.
+pub struct AsyncReadWriteHandle {
+    raw_read_fd: RawFd,
+    raw_write_fd: RawFd,
+    inner: Inner<ReadWriteHandle, Tokio>,
+}
+
+impl AsyncReadHandle {
+    /// Create a new `AsyncReadHandle` for the given `ReadHandle`.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if it isn't called within a tokio runtime.
+    pub fn new(handle: ReadHandle) -> io::Result<Self> {
+        let raw_fd = handle.as_raw_fd();
+        let inner = Inner::<ReadHandle, Tokio>::new(handle)?;
+        Ok(Self { raw_fd, inner })
+    }
+}
+
+impl AsyncWriteHandle {
+    /// Create a new `AsyncWriteHandle` for the given `WriteHandle`.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if it isn't called within a tokio runtime.
+    pub fn new(handle: WriteHandle) -> io::Result<Self> {
+        let raw_fd = handle.as_raw_fd();
+        let inner = Inner::<WriteHandle, Tokio>::new(handle)?;
+        Ok(Self { raw_fd, inner })
+    }
+}
+
+impl AsyncReadWriteHandle {
+    /// Create a new `AsyncReadWriteHandle` for the given `ReadWriteHandle`.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if it isn't called within a tokio runtime.
+    pub fn new(handle: ReadWriteHandle) -> io::Result<Self> {
+        let raw_read_fd = handle.as_raw_read_fd();
+        let raw_write_fd = handle.as_raw_write_fd();
+        let inner = Inner::<ReadWriteHandle, Tokio>::new(handle)?;
+        Ok(Self {
+            raw_read_fd,
+            raw_write_fd,
+            inner,
+        })
+    }
+}
+
+/// The tokio runtime.
+pub(crate) enum Tokio {}
+
+impl Runtime for Tokio {
+    type Registration = AsyncFd<Fd>;
+    type JoinHandle<T> = Join<T>;
+
+    fn register(fd: Fd, direction: Direction) -> io::Result<Option<AsyncFd<Fd>>> {
+        let interest = match direction {
+            Direction::Read => Interest::READABLE,
+            Direction::Write => Interest::WRITABLE,
+            Direction::ReadWrite => Interest::READABLE | Interest::WRITABLE,
+        };
+        match AsyncFd::with_interest(fd, interest) {
+            Ok(async_fd) => Ok(Some(async_fd)),
+            // epoll fails with `EPERM` for descriptors which don't support
+            // polling, such as regular files.
+            Err(e) if e.raw_os_error() == Some(libc::EPERM) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn poll_io<T>(
+        async_fd: &AsyncFd<Fd>,
+        cx: &mut Context,
+        direction: Direction,
+        mut io: impl FnMut() -> io::Result<T>,
+    ) -> Poll<io::Result<T>> {
+        loop {
+            let mut guard = ready!(match direction {
+                Direction::Read => async_fd.poll_read_ready(cx),
+                Direction::Write | Direction::ReadWrite => async_fd.poll_write_ready(cx),
+            })?;
+            if let Ok(result) = guard.try_io(|_| io()) {
+                return Poll::Ready(result);
+            }
+        }
+    }
+
+    #[inline]
+    fn spawn_blocking<T, F>(f: F) -> Join<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        Join(spawn_blocking(f))
+    }
+}
+
+/// An operation on tokio's blocking thread pool, whose panic or cancellation
+/// is reported as an error.
+pub(crate) struct Join<T>(JoinHandle<T>);
+
+impl<T> Future for Join<T> {
+    type Output = io::Result<T>;
+
+    #[inline]
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<T>> {
+        Pin::new(&mut self.0).poll(cx).map_err(io::Error::other)
+    }
+}
+
+/// Read into the unfilled part of `buf`, and advance it over what's read.
+fn poll_read_buf<H>(
+    inner: &mut Inner<H, Tokio>,
+    cx: &mut Context,
+    buf: &mut ReadBuf,
+) -> Poll<io::Result<()>>
+where
+    H: io::Read + Send + 'static,
+{
+    let size = ready!(inner.poll_read(cx, buf.initialize_unfilled()))?;
+    buf.advance(size);
+    Poll::Ready(Ok(()))
+}
+
+impl AsyncRead for AsyncReadHandle {
+    #[inline]
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+        buf: &mut ReadBuf,
+    ) -> Poll<io::Result<()>> {
+        poll_read_buf(&mut self.get_mut().inner, cx, buf)
+    }
+}
+
+impl AsyncWrite for AsyncWriteHandle {
+    #[inline]
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+        self.get_mut().inner.poll_write(cx, buf)
+    }
+
+    #[inline]
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        self.get_mut().inner.poll_flush(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        self.get_mut().inner.poll_close(cx)
+    }
+}
+
+impl AsyncRead for AsyncReadWriteHandle {
+    #[inline]
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+        buf: &mut ReadBuf,
+    ) -> Poll<io::Result<()>> {
+        poll_read_buf(&mut self.get_mut().inner, cx, buf)
+    }
+}
+
+impl AsyncWrite for AsyncReadWriteHandle {
+    #[inline]
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+        self.get_mut().inner.poll_write(cx, buf)
+    }
+
+    #[inline]
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        self.get_mut().inner.poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.inner.poll_shutdown_write(cx))?;
+        // Shutting down a pipe replaces the write descriptor.
+        if let Some(handle) = this.inner.handle() {
+            this.raw_write_fd = handle.as_raw_write_fd();
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsRawFd for AsyncReadHandle {
+    #[inline]
+    fn as_raw_fd(&self) -> RawFd {
+        self.raw_fd
+    }
+}
+
+impl AsRawFd for AsyncWriteHandle {
+    #[inline]
+    fn as_raw_fd(&self) -> RawFd {
+        self.raw_fd
+    }
+}
+
+impl AsRawReadWriteFd for AsyncReadWriteHandle {
+    #[inline]
+    fn as_raw_read_fd(&self) -> RawFd {
+        self.raw_read_fd
+    }
+
+    #[inline]
+    fn as_raw_write_fd(&self) -> RawFd {
+        self.raw_write_fd
+    }
+}
+
+impl Debug for AsyncReadHandle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut b = f.debug_struct("AsyncReadHandle");
+
+        // As with `ReadHandle`, just print the fd number.
+        b.field("raw_fd", &self.as_raw_fd());
+
+        b.finish()
+    }
+}
+
+impl Debug for AsyncWriteHandle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut b = f.debug_struct("AsyncWriteHandle");
+
+        // As with `WriteHandle`, just print the fd number.
+        b.field("raw_fd", &self.as_raw_fd());
+
+        b.finish()
+    }
+}
+
+impl Debug for AsyncReadWriteHandle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut b = f.debug_struct("AsyncReadWriteHandle");
+
+        // As with `ReadWriteHandle`, just print the fd numbers.
+        b.field("raw_read_fd", &self.as_raw_read_fd());
+        b.field("raw_write_fd", &self.as_raw_write_fd());
+
+        b.finish()
+    }
+}
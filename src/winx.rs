@@ -8,19 +8,29 @@
 //! can fail. Similarly there's an `as_raw_socket` which returns an `Option`.
 
 use crate::{
+    child::{discard_stdout, not_a_child, spawn_stdout_piped, ChildProcess, StderrMode},
     descriptor::Descriptor,
-    lockers::{StdinLocker, StdoutLocker},
-    AsRawHandleOrSocket, AsRawReadWriteHandleOrSocket,
+    duplex_thread::{spawn_duplex, DuplexThread},
+    kind::HandleKind,
+    lockers::{StderrLocker, StdinLocker, StdoutLocker},
+    piped_thread::{spawn_writer, Flusher, JoinGuard},
+    seekable::{not_seekable, read_at, read_vectored_at, write_at, write_vectored_at},
+    tee::Tee,
+    AsRawHandleOrSocket, AsRawReadWriteHandleOrSocket, ReadWrite,
 };
 use os_pipe::{pipe, PipeReader, PipeWriter};
 use std::{
     fmt::{self, Arguments, Debug},
-    fs::File,
-    io::{self, copy, Cursor, IoSlice, IoSliceMut, Read, Write},
-    net::TcpStream,
-    os::windows::io::{AsRawHandle, AsRawSocket, RawHandle, RawSocket},
-    process::{Child, ChildStderr, ChildStdin, ChildStdout, Command, Stdio},
-    thread::{self, JoinHandle},
+    fs::{File, Metadata},
+    io::{self, copy, Cursor, IoSlice, IoSliceMut, Read, Seek, SeekFrom, Write},
+    net::{Shutdown, TcpStream},
+    os::windows::io::{
+        AsHandle, AsRawHandle, AsRawSocket, FromRawHandle, IntoRawHandle, OwnedHandle, RawHandle,
+        RawSocket,
+    },
+    process::{Child, ChildStderr, ChildStdin, ChildStdout, Command, ExitStatus, Stdio},
+    sync::Arc,
+    thread,
 };
 
 /// An unbuffered and unlocked input byte stream, abstracted over the source of
@@ -67,36 +77,50 @@ pub struct ReadWriteHandle {
 }
 
 /// Additional resources that need to be held in order to keep the stream live.
+#[allow(dead_code)] // Resources are held for their `Drop` impls, not read.
 enum ReadResources {
     File(File),
     TcpStream(TcpStream),
     PipeReader(PipeReader),
     Stdin(StdinLocker),
-    PipedThread(Option<(PipeReader, JoinHandle<io::Result<()>>)>),
-    Child(Child),
+    // Tuple fields are dropped in order, so the pipe is closed before the
+    // thread is waited for.
+    PipedThread(Option<(PipeReader, JoinGuard)>),
+    Child(ChildProcess),
     ChildStdout(ChildStdout),
     ChildStderr(ChildStderr),
+    // Shared with the write half of a split `ReadWriteHandle`.
+    Shared(Arc<ReadWriteResources>),
 }
 
 /// Additional resources that need to be held in order to keep the stream live.
+#[allow(dead_code)] // Resources are held for their `Drop` impls, not read.
 enum WriteResources {
     File(File),
     TcpStream(TcpStream),
     PipeWriter(PipeWriter),
     Stdout(StdoutLocker),
-    PipedThread(Option<(PipeWriter, JoinHandle<io::Result<Box<dyn Write + Send>>>)>),
-    Child(Child),
+    Stderr(StderrLocker),
+    // As above, the pipe is closed before the thread is waited for.
+    PipedThread(Option<(PipeWriter, JoinGuard)>, Flusher),
+    Child(ChildProcess),
     ChildStdin(ChildStdin),
+    // Shared with the read half of a split `ReadWriteHandle`.
+    Shared(Arc<ReadWriteResources>),
 }
 
 /// Additional resources that need to be held in order to keep the stream live.
+#[allow(dead_code)] // Resources are held for their `Drop` impls, not read.
 enum ReadWriteResources {
     PipeReaderWriter((PipeReader, PipeWriter)),
     StdinStdout((StdinLocker, StdoutLocker)),
-    Child(Child),
+    StdinStderr((StdinLocker, StderrLocker)),
+    Child(ChildProcess),
     ChildStdoutStdin((ChildStdout, ChildStdin)),
     CharDevice(File),
     TcpStream(TcpStream),
+    // As above, the pipes are closed before the thread is waited for.
+    PipedThread(Option<(PipeReader, PipeWriter, DuplexThread)>),
 }
 
 impl ReadHandle {
@@ -148,16 +172,42 @@ impl ReadHandle {
     }
 
     /// Spawn the given command and read from its standard output.
+    ///
+    /// The child process can be waited for with [`wait`] and [`try_wait`],
+    /// and with [`set_check_exit_status`], reaching the end of the stream
+    /// waits for it and reports an unsuccessful exit as an error.
+    ///
+    /// [`wait`]: Self::wait
+    /// [`try_wait`]: Self::try_wait
+    /// [`set_check_exit_status`]: Self::set_check_exit_status
     pub fn read_from_command(mut command: Command) -> io::Result<Self> {
         command.stdin(Stdio::null());
         command.stdout(Stdio::piped());
-        let mut child = command.spawn()?;
-        let child_stdout = child.stdout.take().unwrap();
-        let raw_handle = child_stdout.as_raw_handle();
-        Ok(Self {
+        let child = command.spawn()?;
+        Ok(Self::child_process(child))
+    }
+
+    /// Like [`read_from_command`], but with the child's standard error
+    /// directed according to `stderr`. With [`StderrMode::Merge`], this
+    /// stream reads both the child's standard output and standard error.
+    ///
+    /// [`read_from_command`]: Self::read_from_command
+    pub fn read_from_command_with_stderr(
+        mut command: Command,
+        stderr: StderrMode,
+    ) -> io::Result<Self> {
+        command.stdin(Stdio::null());
+        let child = spawn_stdout_piped(command, stderr)?;
+        Ok(Self::child_process(child))
+    }
+
+    /// Read from the piped standard output of a child process.
+    fn child_process(child: Child) -> Self {
+        let raw_handle = child.stdout.as_ref().unwrap().as_raw_handle();
+        Self {
             descriptor: unsafe { Descriptor::raw_handle(raw_handle) },
-            resources: ReadResources::Child(child),
-        })
+            resources: ReadResources::Child(ChildProcess::new(child)),
+        }
     }
 
     /// Read from a child process' standard output, taking ownership of it.
@@ -188,10 +238,24 @@ impl ReadHandle {
             .spawn(move || copy(&mut *boxed_read, &mut pipe_writer).map(|_size| ()))?;
         Ok(Self {
             descriptor: unsafe { Descriptor::raw_handle(pipe_reader.as_raw_handle()) },
-            resources: ReadResources::PipedThread(Some((pipe_reader, join_handle))),
+            resources: ReadResources::PipedThread(Some((pipe_reader, JoinGuard::new(join_handle)))),
         })
     }
 
+    /// Read from this stream, while also writing everything read from it to
+    /// each of `sinks`, such as to log the raw bytes of a stream while it's
+    /// being processed.
+    ///
+    /// This launches a thread which reads from this stream and writes to the
+    /// sinks and to a pipe, which the returned stream reads from. The sinks
+    /// are closed at the end of the stream, and an error closing one, such
+    /// as an unsuccessful exit of a child process with exit-status checking
+    /// enabled, is reported when closing the returned stream. The thread stops at the first
+    /// error reading from this stream or writing to a sink.
+    pub fn tee(self, sinks: Vec<WriteHandle>) -> io::Result<Self> {
+        Self::piped_thread(Box::new(Tee::new(self, sinks)))
+    }
+
     /// Read from the given string.
     #[inline]
     pub fn str<S: AsRef<str>>(s: S) -> io::Result<Self> {
@@ -204,16 +268,305 @@ impl ReadHandle {
         Self::piped_thread(Box::new(Cursor::new(bytes.to_vec())))
     }
 
-    fn map_err(&mut self, e: io::Error) -> io::Error {
+    /// If this stream is backed by a child process spawned by
+    /// [`read_from_command`], enable or disable reporting an unsuccessful
+    /// exit of the child as an error when the end of the stream is reached.
+    ///
+    /// Fails with [`std::io::ErrorKind::Unsupported`] if this stream is not
+    /// backed by a child process.
+    ///
+    /// [`read_from_command`]: Self::read_from_command
+    pub fn set_check_exit_status(&mut self, check: bool) -> io::Result<()> {
         match &mut self.resources {
-            ReadResources::PipedThread(piped_thread) => {
-                let (pipe_reader, join_handle) = piped_thread.take().unwrap();
-                drop(pipe_reader);
-                join_handle.join().unwrap().unwrap_err()
+            ReadResources::Child(child) => {
+                child.set_check_exit_status(check);
+                Ok(())
             }
-            _ => e,
+            _ => Err(not_a_child()),
+        }
+    }
+
+    /// If this stream is backed by a child process spawned by
+    /// [`read_from_command`], wait for it to exit and return its exit status.
+    ///
+    /// The child's output isn't consumed while waiting, so this should
+    /// typically be called after reading to the end of the stream.
+    ///
+    /// Fails with [`std::io::ErrorKind::Unsupported`] if this stream is not
+    /// backed by a child process.
+    ///
+    /// [`read_from_command`]: Self::read_from_command
+    pub fn wait(mut self) -> io::Result<ExitStatus> {
+        match &mut self.resources {
+            ReadResources::Child(child) => child.wait(),
+            _ => Err(not_a_child()),
+        }
+    }
+
+    /// If this stream is backed by a child process spawned by
+    /// [`read_from_command`], wait for it to exit and return its exit status
+    /// in a `Vec`, like a pipeline of one command on Unix-family platforms.
+    ///
+    /// Like [`wait`], the output isn't consumed while waiting.
+    ///
+    /// Fails with [`std::io::ErrorKind::Unsupported`] if this stream is not
+    /// backed by a child process.
+    ///
+    /// [`read_from_command`]: Self::read_from_command
+    /// [`wait`]: Self::wait
+    pub fn wait_all(mut self) -> io::Result<Vec<ExitStatus>> {
+        match &mut self.resources {
+            ReadResources::Child(child) => child.wait_all(),
+            _ => Err(not_a_child()),
+        }
+    }
+
+    /// If this stream is backed by a child process spawned by
+    /// [`read_from_command`], return its exit status if it has exited,
+    /// without blocking.
+    ///
+    /// Fails with [`std::io::ErrorKind::Unsupported`] if this stream is not
+    /// backed by a child process.
+    ///
+    /// [`read_from_command`]: Self::read_from_command
+    pub fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> {
+        match &mut self.resources {
+            ReadResources::Child(child) => child.try_wait(),
+            _ => Err(not_a_child()),
         }
     }
+
+    /// If this stream is backed by a child process whose standard error was
+    /// captured with [`StderrMode::Capture`], take a stream which reads from
+    /// it.
+    ///
+    /// Returns `None` if standard error wasn't captured, or if it has already
+    /// been taken.
+    pub fn take_stderr(&mut self) -> Option<ReadHandle> {
+        match &mut self.resources {
+            ReadResources::Child(child) => child.take_stderr().map(ReadHandle::child_stderr),
+            _ => None,
+        }
+    }
+
+    /// Create a new stream for the same underlying I/O object, such as to
+    /// hand the same input to several readers.
+    ///
+    /// This duplicates the handle or socket for files, sockets, and pipes,
+    /// including a child process' standard output and error. The halves of a
+    /// split stream share its resources instead.
+    ///
+    /// Fails with [`std::io::ErrorKind::Unsupported`] for other streams, such
+    /// as standard input, piped threads, and streams which own a child
+    /// process.
+    pub fn try_clone(&self) -> io::Result<Self> {
+        let clone = match &self.resources {
+            ReadResources::File(file) => Self::file(file.try_clone()?),
+            ReadResources::TcpStream(tcp_stream) => Self::tcp_stream(tcp_stream.try_clone()?),
+            ReadResources::PipeReader(pipe_reader) => Self::pipe_reader(pipe_reader.try_clone()?),
+            ReadResources::ChildStdout(child_stdout) => Self::child_stdout(ChildStdout::from(
+                child_stdout.as_handle().try_clone_to_owned()?,
+            )),
+            ReadResources::ChildStderr(child_stderr) => Self::child_stderr(ChildStderr::from(
+                child_stderr.as_handle().try_clone_to_owned()?,
+            )),
+            ReadResources::Shared(resources) => Self {
+                descriptor: unsafe { self.descriptor.alias() },
+                resources: ReadResources::Shared(Arc::clone(resources)),
+            },
+            _ => return Err(not_cloneable()),
+        };
+        Ok(clone)
+    }
+
+    /// Return what kind of I/O object backs this stream.
+    pub fn kind(&self) -> HandleKind {
+        match &self.resources {
+            ReadResources::File(_) => HandleKind::File,
+            ReadResources::TcpStream(_) => HandleKind::TcpStream,
+            ReadResources::PipeReader(_) => HandleKind::Pipe,
+            ReadResources::Stdin(_) => HandleKind::Stdio,
+            ReadResources::PipedThread(_) => HandleKind::PipedThread,
+            ReadResources::Child(_)
+            | ReadResources::ChildStdout(_)
+            | ReadResources::ChildStderr(_) => HandleKind::Child,
+            ReadResources::Shared(resources) => resources.kind(),
+        }
+    }
+
+    /// Query metadata about the file this stream reads from or writes to,
+    /// such as whether it's a regular file.
+    ///
+    /// Fails with [`std::io::ErrorKind::Unsupported`] if this stream is a
+    /// socket.
+    #[inline]
+    pub fn metadata(&self) -> io::Result<Metadata> {
+        self.descriptor.metadata()
+    }
+
+    /// Test whether this stream is a terminal.
+    #[inline]
+    pub fn is_terminal(&self) -> bool {
+        self.descriptor.is_terminal()
+    }
+
+    /// Test whether this stream is backed by a regular file, and so supports
+    /// seeking. For other streams, such as pipes, sockets, and terminals,
+    /// [`Seek`] functions fail with [`std::io::ErrorKind::Unsupported`].
+    ///
+    /// [`Seek`]: std::io::Seek
+    #[inline]
+    pub fn is_seekable(&self) -> bool {
+        self.descriptor.is_seekable()
+    }
+
+    /// Read from this stream at `offset`, returning the number of bytes read.
+    ///
+    /// Unlike on other platforms, this moves the cursor, as Windows'
+    /// positional I/O does.
+    ///
+    /// This fails with [`std::io::ErrorKind::Unsupported`] unless the stream
+    /// is backed by a regular file.
+    #[inline]
+    pub fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        match self.descriptor.file() {
+            Some(file) => read_at(file, buf, offset),
+            None => Err(not_seekable()),
+        }
+    }
+
+    /// Like [`read_at`], but reads into a slice of buffers.
+    ///
+    /// [`read_at`]: Self::read_at
+    #[inline]
+    pub fn read_vectored_at(&self, bufs: &mut [IoSliceMut], offset: u64) -> io::Result<usize> {
+        match self.descriptor.file() {
+            Some(file) => read_vectored_at(file, bufs, offset),
+            None => Err(not_seekable()),
+        }
+    }
+
+    /// Close the stream, reporting any error that would otherwise be
+    /// discarded when it's dropped.
+    ///
+    /// If this stream is a piped thread, this waits for the thread to exit
+    /// and reports an error it encountered, other than a broken pipe caused
+    /// by closing the stream early. If this stream is backed by a child
+    /// process and [`set_check_exit_status`] has enabled exit-status
+    /// checking, this waits for the child to exit and reports an
+    /// unsuccessful exit as an error.
+    ///
+    /// [`set_check_exit_status`]: Self::set_check_exit_status
+    pub fn close(mut self) -> io::Result<()> {
+        match &mut self.resources {
+            ReadResources::PipedThread(piped_thread) => match piped_thread.take() {
+                Some((pipe_reader, mut join_guard)) => {
+                    drop(pipe_reader);
+                    match join_guard.join() {
+                        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+                        result => result,
+                    }
+                }
+                None => Ok(()),
+            },
+            // `self` is consumed, so closing the child's stdout here is fine.
+            ReadResources::Child(child) => {
+                child.close_stdout();
+                child.check_exit_status()
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Called when the end of the stream has been reached.
+    fn end_of_stream(&mut self) -> io::Result<()> {
+        match &mut self.resources {
+            // The thread has closed its end of the pipe, so it's done.
+            ReadResources::PipedThread(_) => self.join_piped_thread(),
+            ReadResources::Child(child) => child.check_exit_status(),
+            _ => Ok(()),
+        }
+    }
+
+    /// Recover the file this stream reads from.
+    ///
+    /// Returns `Err(self)` if this stream isn't backed by one.
+    pub fn into_file(self) -> Result<File, Self> {
+        match self.resources {
+            ReadResources::File(resource) => Ok(resource),
+            _ => Err(self),
+        }
+    }
+
+    /// Recover the TCP socket this stream reads from.
+    ///
+    /// Returns `Err(self)` if this stream isn't backed by one.
+    pub fn into_tcp_stream(self) -> Result<TcpStream, Self> {
+        match self.resources {
+            ReadResources::TcpStream(resource) => Ok(resource),
+            _ => Err(self),
+        }
+    }
+
+    /// Recover the pipe this stream reads from.
+    ///
+    /// Returns `Err(self)` if this stream isn't backed by one.
+    pub fn into_pipe_reader(self) -> Result<PipeReader, Self> {
+        match self.resources {
+            ReadResources::PipeReader(resource) => Ok(resource),
+            _ => Err(self),
+        }
+    }
+
+    /// Recover the child process' standard output this stream reads from.
+    ///
+    /// Returns `Err(self)` if this stream isn't backed by one.
+    pub fn into_child_stdout(self) -> Result<ChildStdout, Self> {
+        match self.resources {
+            ReadResources::ChildStdout(resource) => Ok(resource),
+            _ => Err(self),
+        }
+    }
+
+    /// Recover the child process' standard error this stream reads from.
+    ///
+    /// Returns `Err(self)` if this stream isn't backed by one.
+    pub fn into_child_stderr(self) -> Result<ChildStderr, Self> {
+        match self.resources {
+            ReadResources::ChildStderr(resource) => Ok(resource),
+            _ => Err(self),
+        }
+    }
+
+    /// Recover the child process spawned by [`read_from_command`], with its
+    /// standard output in [`Child::stdout`].
+    ///
+    /// Returns `Err(self)` if this stream isn't backed by one.
+    ///
+    /// [`read_from_command`]: Self::read_from_command
+    /// [`Child::stdout`]: std::process::Child::stdout
+    pub fn into_child(self) -> Result<Child, Self> {
+        match self.resources {
+            ReadResources::Child(child) => Ok(child.into_child()),
+            _ => Err(self),
+        }
+    }
+
+    /// Wait for a piped thread to exit and report its result, leaving just
+    /// the pipe, which is at its end.
+    fn join_piped_thread(&mut self) -> io::Result<()> {
+        if let ReadResources::PipedThread(piped_thread) = &mut self.resources {
+            if let Some((pipe_reader, mut join_guard)) = piped_thread.take() {
+                self.resources = ReadResources::PipeReader(pipe_reader);
+                return join_guard.join();
+            }
+        }
+        Ok(())
+    }
+
+    fn map_err(&mut self, e: io::Error) -> io::Error {
+        e
+    }
 }
 
 impl WriteHandle {
@@ -238,6 +591,27 @@ impl WriteHandle {
         })
     }
 
+    /// Write to standard error.
+    ///
+    /// Unlike [`std::io::stderr`], this `stderr` returns a stream which is
+    /// unlocked.
+    ///
+    /// This acquires a [`std::io::StderrLock`] (in a non-recursive way) to
+    /// prevent accesses to `std::io::Stderr`, such as by `eprintln!`, while
+    /// this is live, and fails if a `WriteHandle` or `ReadWriteHandle` for
+    /// standard error already exists.
+    ///
+    /// [`std::io::stderr`]: https://doc.rust-lang.org/std/io/fn.stderr.html`
+    /// [`std::io::StderrLock`]: https://doc.rust-lang.org/std/io/struct.StderrLock.html
+    #[inline]
+    pub fn stderr() -> io::Result<Self> {
+        let stderr_locker = StderrLocker::new()?;
+        Ok(Self {
+            descriptor: unsafe { Descriptor::raw_handle(stderr_locker.as_raw_handle()) },
+            resources: WriteResources::Stderr(stderr_locker),
+        })
+    }
+
     /// Write to an open file, taking ownership of it.
     #[inline]
     pub fn file(file: File) -> Self {
@@ -274,33 +648,54 @@ impl WriteHandle {
     /// the thread and into the boxed `Write` implementation, call `flush()`,
     /// which synchronizes with the thread to ensure that is has completed
     /// writing all pending output.
-    pub fn piped_thread(mut boxed_write: Box<dyn Write + Send>) -> io::Result<Self> {
-        let (mut pipe_reader, pipe_writer) = pipe()?;
-        let join_handle = thread::Builder::new()
-            .name("piped thread for boxed writer".to_owned())
-            .spawn(move || {
-                copy(&mut pipe_reader, &mut *boxed_write)?;
-                boxed_write.flush()?;
-                Ok(boxed_write)
-            })?;
+    pub fn piped_thread(boxed_write: Box<dyn Write + Send>) -> io::Result<Self> {
+        let (pipe_writer, join_guard, flusher) = spawn_writer(boxed_write)?;
         Ok(Self {
             descriptor: unsafe { Descriptor::raw_handle(pipe_writer.as_raw_handle()) },
-            resources: WriteResources::PipedThread(Some((pipe_writer, join_handle))),
+            resources: WriteResources::PipedThread(Some((pipe_writer, join_guard)), flusher),
         })
     }
 
     /// Spawn the given command and write to its standard input. Its standard
     /// output is redirected to `Stdio::null()`.
+    ///
+    /// The child process can be waited for with [`wait`] and [`try_wait`],
+    /// and with [`set_check_exit_status`], [`close`] waits for it and reports
+    /// an unsuccessful exit as an error.
+    ///
+    /// [`wait`]: Self::wait
+    /// [`try_wait`]: Self::try_wait
+    /// [`set_check_exit_status`]: Self::set_check_exit_status
+    /// [`close`]: Self::close
     pub fn write_to_command(mut command: Command) -> io::Result<Self> {
         command.stdin(Stdio::piped());
         command.stdout(Stdio::null());
-        let mut child = command.spawn()?;
-        let child_stdin = child.stdin.take().unwrap();
-        let raw_handle = child_stdin.as_raw_handle();
-        Ok(Self {
+        let child = command.spawn()?;
+        Ok(Self::child_process(child))
+    }
+
+    /// Like [`write_to_command`], but with the child's standard error
+    /// directed according to `stderr`. Its standard output is still
+    /// discarded, so [`StderrMode::Merge`] discards standard error too.
+    ///
+    /// [`write_to_command`]: Self::write_to_command
+    pub fn write_to_command_with_stderr(
+        mut command: Command,
+        stderr: StderrMode,
+    ) -> io::Result<Self> {
+        command.stdin(Stdio::piped());
+        discard_stdout(&mut command, stderr);
+        let child = command.spawn()?;
+        Ok(Self::child_process(child))
+    }
+
+    /// Write to the piped standard input of a child process.
+    fn child_process(child: Child) -> Self {
+        let raw_handle = child.stdin.as_ref().unwrap().as_raw_handle();
+        Self {
             descriptor: unsafe { Descriptor::raw_handle(raw_handle) },
-            resources: WriteResources::Child(child),
-        })
+            resources: WriteResources::Child(ChildProcess::new(child)),
+        }
     }
 
     /// Write to the given child standard input, taking ownership of it.
@@ -317,15 +712,280 @@ impl WriteHandle {
         Ok(Self::file(File::create("NUL")?))
     }
 
-    fn map_err(&mut self, e: io::Error) -> io::Error {
+    /// If this stream is backed by a child process spawned by
+    /// [`write_to_command`], enable or disable reporting an unsuccessful exit
+    /// of the child as an error from [`close`].
+    ///
+    /// Fails with [`std::io::ErrorKind::Unsupported`] if this stream is not
+    /// backed by a child process.
+    ///
+    /// [`write_to_command`]: Self::write_to_command
+    /// [`close`]: Self::close
+    pub fn set_check_exit_status(&mut self, check: bool) -> io::Result<()> {
+        match &mut self.resources {
+            WriteResources::Child(child) => {
+                child.set_check_exit_status(check);
+                Ok(())
+            }
+            _ => Err(not_a_child()),
+        }
+    }
+
+    /// If this stream is backed by a child process spawned by
+    /// [`write_to_command`], close the child's standard input, wait for it to
+    /// exit, and return its exit status.
+    ///
+    /// Fails with [`std::io::ErrorKind::Unsupported`] if this stream is not
+    /// backed by a child process.
+    ///
+    /// [`write_to_command`]: Self::write_to_command
+    pub fn wait(mut self) -> io::Result<ExitStatus> {
+        match &mut self.resources {
+            // This closes the child's stdin, which our descriptor refers to,
+            // however `self` is consumed so it's never used again.
+            WriteResources::Child(child) => child.wait(),
+            _ => Err(not_a_child()),
+        }
+    }
+
+    /// If this stream is backed by a child process spawned by
+    /// [`write_to_command`], return its exit status if it has exited,
+    /// without blocking.
+    ///
+    /// Fails with [`std::io::ErrorKind::Unsupported`] if this stream is not
+    /// backed by a child process.
+    ///
+    /// [`write_to_command`]: Self::write_to_command
+    pub fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> {
+        match &mut self.resources {
+            WriteResources::Child(child) => child.try_wait(),
+            _ => Err(not_a_child()),
+        }
+    }
+
+    /// If this stream is backed by a child process whose standard error was
+    /// captured with [`StderrMode::Capture`], take a stream which reads from
+    /// it.
+    ///
+    /// Returns `None` if standard error wasn't captured, or if it has already
+    /// been taken.
+    pub fn take_stderr(&mut self) -> Option<ReadHandle> {
+        match &mut self.resources {
+            WriteResources::Child(child) => child.take_stderr().map(ReadHandle::child_stderr),
+            _ => None,
+        }
+    }
+
+    /// Create a new stream for the same underlying I/O object, such as to
+    /// hand the same output to a logger and a worker.
+    ///
+    /// This duplicates the handle or socket for files, sockets, and pipes,
+    /// including a child process' standard input. The halves of a split
+    /// stream share its resources instead.
+    ///
+    /// Fails with [`std::io::ErrorKind::Unsupported`] for other streams, such
+    /// as standard output and error, piped threads, and streams which own a
+    /// child process.
+    pub fn try_clone(&self) -> io::Result<Self> {
+        let clone = match &self.resources {
+            WriteResources::File(file) => Self::file(file.try_clone()?),
+            WriteResources::TcpStream(tcp_stream) => Self::tcp_stream(tcp_stream.try_clone()?),
+            WriteResources::PipeWriter(pipe_writer) => Self::pipe_writer(pipe_writer.try_clone()?),
+            WriteResources::ChildStdin(child_stdin) => Self::child_stdin(ChildStdin::from(
+                child_stdin.as_handle().try_clone_to_owned()?,
+            )),
+            WriteResources::Shared(resources) => Self {
+                descriptor: unsafe { self.descriptor.alias() },
+                resources: WriteResources::Shared(Arc::clone(resources)),
+            },
+            _ => return Err(not_cloneable()),
+        };
+        Ok(clone)
+    }
+
+    /// Return what kind of I/O object backs this stream.
+    pub fn kind(&self) -> HandleKind {
+        match &self.resources {
+            WriteResources::File(_) => HandleKind::File,
+            WriteResources::TcpStream(_) => HandleKind::TcpStream,
+            WriteResources::PipeWriter(_) => HandleKind::Pipe,
+            WriteResources::Stdout(_) | WriteResources::Stderr(_) => HandleKind::Stdio,
+            WriteResources::PipedThread(..) => HandleKind::PipedThread,
+            WriteResources::Child(_) | WriteResources::ChildStdin(_) => HandleKind::Child,
+            WriteResources::Shared(resources) => resources.kind(),
+        }
+    }
+
+    /// Query metadata about the file this stream reads from or writes to,
+    /// such as whether it's a regular file.
+    ///
+    /// Fails with [`std::io::ErrorKind::Unsupported`] if this stream is a
+    /// socket.
+    #[inline]
+    pub fn metadata(&self) -> io::Result<Metadata> {
+        self.descriptor.metadata()
+    }
+
+    /// Test whether this stream is a terminal.
+    #[inline]
+    pub fn is_terminal(&self) -> bool {
+        self.descriptor.is_terminal()
+    }
+
+    /// Test whether this stream is backed by a regular file, and so supports
+    /// seeking. For other streams, such as pipes, sockets, and terminals,
+    /// [`Seek`] functions fail with [`std::io::ErrorKind::Unsupported`].
+    ///
+    /// [`Seek`]: std::io::Seek
+    #[inline]
+    pub fn is_seekable(&self) -> bool {
+        self.descriptor.is_seekable()
+    }
+
+    /// Write to this stream at `offset`, returning the number of bytes
+    /// written.
+    ///
+    /// Unlike on other platforms, this moves the cursor, as Windows'
+    /// positional I/O does.
+    ///
+    /// This fails with [`std::io::ErrorKind::Unsupported`] unless the stream
+    /// is backed by a regular file.
+    #[inline]
+    pub fn write_at(&self, buf: &[u8], offset: u64) -> io::Result<usize> {
+        match self.descriptor.file() {
+            Some(file) => write_at(file, buf, offset),
+            None => Err(not_seekable()),
+        }
+    }
+
+    /// Like [`write_at`], but writes from a slice of buffers.
+    ///
+    /// [`write_at`]: Self::write_at
+    #[inline]
+    pub fn write_vectored_at(&self, bufs: &[IoSlice], offset: u64) -> io::Result<usize> {
+        match self.descriptor.file() {
+            Some(file) => write_vectored_at(file, bufs, offset),
+            None => Err(not_seekable()),
+        }
+    }
+
+    /// Close the stream, reporting any error that would otherwise be
+    /// discarded when it's dropped.
+    ///
+    /// If this stream is a piped thread, this waits for the thread to drain
+    /// the pipe, flush the boxed writer, and exit, and reports an error it
+    /// encountered. If this stream is backed by a child process and
+    /// [`set_check_exit_status`] has enabled exit-status checking, this waits
+    /// for the child to exit and reports an unsuccessful exit as an error.
+    ///
+    /// [`set_check_exit_status`]: Self::set_check_exit_status
+    pub fn close(mut self) -> io::Result<()> {
         match &mut self.resources {
-            WriteResources::PipedThread(piped_thread) => {
-                let (pipe_writer, join_handle) = piped_thread.take().unwrap();
-                drop(pipe_writer);
-                join_handle.join().unwrap().map(|_| ()).unwrap_err()
+            // As in `wait`, `self` is consumed, so closing the child's stdin
+            // here is fine.
+            WriteResources::Child(child) => child.check_exit_status(),
+            WriteResources::PipedThread(piped_thread, _) => match piped_thread.take() {
+                Some((pipe_writer, mut join_guard)) => {
+                    drop(pipe_writer);
+                    join_guard.join()
+                }
+                None => Ok(()),
+            },
+            _ => Ok(()),
+        }
+    }
+
+    /// Record that `size` bytes were written, so that flushing a piped
+    /// thread knows how much to wait for.
+    #[inline]
+    fn wrote(&mut self, size: usize) {
+        if let WriteResources::PipedThread(_, flusher) = &mut self.resources {
+            flusher.wrote(size);
+        }
+    }
+
+    /// Test whether the `Write` functions which loop should be forwarded to
+    /// `write`, to count the bytes written.
+    #[inline]
+    fn by_write(&self) -> bool {
+        matches!(self.resources, WriteResources::PipedThread(..))
+    }
+
+    /// Recover the file this stream writes to.
+    ///
+    /// Returns `Err(self)` if this stream isn't backed by one.
+    pub fn into_file(self) -> Result<File, Self> {
+        match self.resources {
+            WriteResources::File(resource) => Ok(resource),
+            _ => Err(self),
+        }
+    }
+
+    /// Recover the TCP socket this stream writes to.
+    ///
+    /// Returns `Err(self)` if this stream isn't backed by one.
+    pub fn into_tcp_stream(self) -> Result<TcpStream, Self> {
+        match self.resources {
+            WriteResources::TcpStream(resource) => Ok(resource),
+            _ => Err(self),
+        }
+    }
+
+    /// Recover the pipe this stream writes to.
+    ///
+    /// Returns `Err(self)` if this stream isn't backed by one.
+    pub fn into_pipe_writer(self) -> Result<PipeWriter, Self> {
+        match self.resources {
+            WriteResources::PipeWriter(resource) => Ok(resource),
+            _ => Err(self),
+        }
+    }
+
+    /// Recover the child process' standard input this stream writes to.
+    ///
+    /// Returns `Err(self)` if this stream isn't backed by one.
+    pub fn into_child_stdin(self) -> Result<ChildStdin, Self> {
+        match self.resources {
+            WriteResources::ChildStdin(resource) => Ok(resource),
+            _ => Err(self),
+        }
+    }
+
+    /// Recover the child process spawned by [`write_to_command`], with its
+    /// standard input in [`Child::stdin`].
+    ///
+    /// Returns `Err(self)` if this stream isn't backed by one.
+    ///
+    /// [`write_to_command`]: Self::write_to_command
+    /// [`Child::stdin`]: std::process::Child::stdin
+    pub fn into_child(self) -> Result<Child, Self> {
+        match self.resources {
+            WriteResources::Child(child) => Ok(child.into_child()),
+            _ => Err(self),
+        }
+    }
+
+    /// Wait for a piped thread to exit and report its result, leaving just
+    /// the pipe, which is broken.
+    fn join_piped_thread(&mut self) -> io::Result<()> {
+        if let WriteResources::PipedThread(piped_thread, _) = &mut self.resources {
+            if let Some((pipe_writer, mut join_guard)) = piped_thread.take() {
+                self.resources = WriteResources::PipeWriter(pipe_writer);
+                return join_guard.join();
             }
-            _ => e,
         }
+        Ok(())
+    }
+
+    fn map_err(&mut self, e: io::Error) -> io::Error {
+        // A broken pipe means the thread has exited; report its error, if it
+        // had one.
+        if e.kind() == io::ErrorKind::BrokenPipe
+            && matches!(self.resources, WriteResources::PipedThread(..))
+        {
+            return self.join_piped_thread().err().unwrap_or(e);
+        }
+        e
     }
 }
 
@@ -356,21 +1016,71 @@ impl ReadWriteHandle {
         })
     }
 
+    /// Interact with stdin and stderr, taking ownership of them.
+    ///
+    /// This is useful for prompting the user while standard output is
+    /// reserved for the program's output.
+    ///
+    /// This acquires a [`std::io::StdinLock`] and a [`std::io::StderrLock`] to
+    /// prevent accesses to `std::io::Stdin` and `std::io::Stderr` while this
+    /// is live, and fails if a `ReadHandle` for standard input, a
+    /// `WriteHandle` for standard error, or a `ReadWriteHandle` for standard
+    /// input already exist.
+    ///
+    /// [`std::io::StdinLock`]: https://doc.rust-lang.org/std/io/struct.StdinLock.html
+    /// [`std::io::StderrLock`]: https://doc.rust-lang.org/std/io/struct.StderrLock.html
+    #[inline]
+    pub fn stdin_stderr() -> io::Result<Self> {
+        let stdin_locker = StdinLocker::new()?;
+        let stderr_locker = StderrLocker::new()?;
+        Ok(Self {
+            read_descriptor: unsafe { Descriptor::raw_handle(stdin_locker.as_raw_handle()) },
+            write_descriptor: unsafe { Descriptor::raw_handle(stderr_locker.as_raw_handle()) },
+            resources: ReadWriteResources::StdinStderr((stdin_locker, stderr_locker)),
+        })
+    }
+
     /// Spawn the given command and interact with its standard input and
     /// output.
+    ///
+    /// The child process can be waited for with [`wait`] and [`try_wait`],
+    /// and with [`set_check_exit_status`], [`close`] waits for it and reports
+    /// an unsuccessful exit as an error.
+    ///
+    /// [`wait`]: Self::wait
+    /// [`try_wait`]: Self::try_wait
+    /// [`set_check_exit_status`]: Self::set_check_exit_status
+    /// [`close`]: Self::close
     pub fn interact_with_command(mut command: Command) -> io::Result<Self> {
         command.stdin(Stdio::piped());
         command.stdout(Stdio::piped());
-        let mut child = command.spawn()?;
-        let child_stdin = child.stdin.take().unwrap();
-        let child_stdout = child.stdout.take().unwrap();
-        let raw_read_handle = child_stdout.as_raw_handle();
-        let raw_write_handle = child_stdin.as_raw_handle();
-        Ok(Self {
+        let child = command.spawn()?;
+        Ok(Self::child_process(child))
+    }
+
+    /// Like [`interact_with_command`], but with the child's standard error
+    /// directed according to `stderr`. With [`StderrMode::Merge`], this
+    /// stream reads both the child's standard output and standard error.
+    ///
+    /// [`interact_with_command`]: Self::interact_with_command
+    pub fn interact_with_command_with_stderr(
+        mut command: Command,
+        stderr: StderrMode,
+    ) -> io::Result<Self> {
+        command.stdin(Stdio::piped());
+        let child = spawn_stdout_piped(command, stderr)?;
+        Ok(Self::child_process(child))
+    }
+
+    /// Interact with the piped standard input and output of a child process.
+    fn child_process(child: Child) -> Self {
+        let raw_read_handle = child.stdout.as_ref().unwrap().as_raw_handle();
+        let raw_write_handle = child.stdin.as_ref().unwrap().as_raw_handle();
+        Self {
             read_descriptor: unsafe { Descriptor::raw_handle(raw_read_handle) },
             write_descriptor: unsafe { Descriptor::raw_handle(raw_write_handle) },
-            resources: ReadWriteResources::Child(child),
-        })
+            resources: ReadWriteResources::Child(ChildProcess::new(child)),
+        }
     }
 
     /// Interact with a child process' stdout and stdin, taking ownership of
@@ -406,6 +1116,20 @@ impl ReadWriteHandle {
         }
     }
 
+    /// Create a pair of connected streams, where what's written to each can
+    /// be read from the other, such as for testing protocol code without a
+    /// child process or a network connection.
+    ///
+    /// This uses a pair of pipes.
+    pub fn pair() -> io::Result<(Self, Self)> {
+        let (a_reader, b_writer) = pipe()?;
+        let (b_reader, a_writer) = pipe()?;
+        Ok((
+            Self::pipe_reader_writer(a_reader, a_writer),
+            Self::pipe_reader_writer(b_reader, b_writer),
+        ))
+    }
+
     /// Interact a pair of pipe streams, taking ownership of them.
     #[inline]
     pub fn pipe_reader_writer(pipe_reader: PipeReader, pipe_writer: PipeWriter) -> Self {
@@ -416,9 +1140,477 @@ impl ReadWriteHandle {
         }
     }
 
-    fn map_err(&mut self, e: io::Error) -> io::Error {
+    /// Interact with an arbitrary [`ReadWrite`], such as an in-memory
+    /// protocol simulator or a TLS stream, by running it on a thread
+    /// connected to the returned stream by a pair of pipes.
+    ///
+    /// A single thread owns `boxed_read_write`, and alternates between
+    /// writing what's written to this stream into it, flushing it after each
+    /// batch, and reading from it. So `boxed_read_write` must be
+    /// non-blocking: when it has no data available, its `read` should fail
+    /// with [`std::io::ErrorKind::WouldBlock`], and the thread then waits for
+    /// more data to be written, for up to 100 milliseconds as it stays idle,
+    /// before trying it again.
+    ///
+    /// Blocking streams, including TLS streams over blocking sockets, aren't
+    /// supported. A `read` which blocks holds up writing to
+    /// `boxed_read_write`, so a protocol which waits for a request before
+    /// responding deadlocks, and [`close`] waits for the `read` to return.
+    /// Put the socket under a TLS stream in non-blocking mode instead.
+    ///
+    /// A `read` returning 0 ends this stream for good, as it does for any
+    /// [`Read`]; one which has no data yet must fail with `WouldBlock`
+    /// instead.
+    ///
+    /// Errors from `boxed_read_write` stop the thread, and are reported by
+    /// [`close`], which waits for everything written to this stream to be
+    /// written to `boxed_read_write`. Dropping this stream doesn't wait for
+    /// the thread.
+    ///
+    /// [`close`]: Self::close
+    pub fn piped_thread(boxed_read_write: Box<dyn ReadWrite + Send>) -> io::Result<Self> {
+        let (pipe_reader, pipe_writer, duplex_thread) = spawn_duplex(boxed_read_write)?;
+        Ok(Self {
+            read_descriptor: unsafe { Descriptor::raw_handle(pipe_reader.as_raw_handle()) },
+            write_descriptor: unsafe { Descriptor::raw_handle(pipe_writer.as_raw_handle()) },
+            resources: ReadWriteResources::PipedThread(Some((
+                pipe_reader,
+                pipe_writer,
+                duplex_thread,
+            ))),
+        })
+    }
+
+    /// Create a new stream for the same underlying I/O objects.
+    ///
+    /// This duplicates the handles or sockets for sockets, character
+    /// devices, and pipes, including a child process' standard output and
+    /// input passed to [`child_stdout_stdin`].
+    ///
+    /// Fails with [`std::io::ErrorKind::Unsupported`] for other streams, such
+    /// as standard input and output, piped threads, and streams which own a
+    /// child process.
+    ///
+    /// [`child_stdout_stdin`]: Self::child_stdout_stdin
+    pub fn try_clone(&self) -> io::Result<Self> {
+        let clone = match &self.resources {
+            ReadWriteResources::TcpStream(tcp_stream) => Self::tcp_stream(tcp_stream.try_clone()?),
+            ReadWriteResources::CharDevice(char_device) => {
+                Self::char_device(char_device.try_clone()?)
+            }
+            ReadWriteResources::PipeReaderWriter((pipe_reader, pipe_writer)) => {
+                Self::pipe_reader_writer(pipe_reader.try_clone()?, pipe_writer.try_clone()?)
+            }
+            ReadWriteResources::ChildStdoutStdin((child_stdout, child_stdin)) => {
+                Self::child_stdout_stdin(
+                    ChildStdout::from(child_stdout.as_handle().try_clone_to_owned()?),
+                    ChildStdin::from(child_stdin.as_handle().try_clone_to_owned()?),
+                )
+            }
+            _ => return Err(not_cloneable()),
+        };
+        Ok(clone)
+    }
+
+    /// Return what kind of I/O object backs this stream.
+    #[inline]
+    pub fn kind(&self) -> HandleKind {
+        self.resources.kind()
+    }
+
+    /// Query metadata about the file on the reading side of this stream.
+    ///
+    /// Fails with [`std::io::ErrorKind::Unsupported`] if this side is a
+    /// socket.
+    #[inline]
+    pub fn read_metadata(&self) -> io::Result<Metadata> {
+        self.read_descriptor.metadata()
+    }
+
+    /// Query metadata about the file on the writing side of this stream.
+    ///
+    /// Fails with [`std::io::ErrorKind::Unsupported`] if this side is a
+    /// socket.
+    #[inline]
+    pub fn write_metadata(&self) -> io::Result<Metadata> {
+        self.write_descriptor.metadata()
+    }
+
+    /// Test whether both sides of this stream are terminals.
+    #[inline]
+    pub fn is_terminal(&self) -> bool {
+        self.read_descriptor.is_terminal() && self.write_descriptor.is_terminal()
+    }
+
+    /// If this stream is backed by a child process spawned by
+    /// [`interact_with_command`], enable or disable reporting an unsuccessful
+    /// exit of the child as an error from [`close`].
+    ///
+    /// Fails with [`std::io::ErrorKind::Unsupported`] if this stream is not
+    /// backed by a child process.
+    ///
+    /// [`interact_with_command`]: Self::interact_with_command
+    /// [`close`]: Self::close
+    pub fn set_check_exit_status(&mut self, check: bool) -> io::Result<()> {
         match &mut self.resources {
-            _ => e,
+            ReadWriteResources::Child(child) => {
+                child.set_check_exit_status(check);
+                Ok(())
+            }
+            _ => Err(not_a_child()),
+        }
+    }
+
+    /// If this stream is backed by a child process spawned by
+    /// [`interact_with_command`], close the child's standard input, wait for
+    /// it to exit, and return its exit status.
+    ///
+    /// Fails with [`std::io::ErrorKind::Unsupported`] if this stream is not
+    /// backed by a child process.
+    ///
+    /// [`interact_with_command`]: Self::interact_with_command
+    pub fn wait(mut self) -> io::Result<ExitStatus> {
+        match &mut self.resources {
+            // This closes the child's stdin, which our write descriptor refers
+            // to, however `self` is consumed so it's never used again.
+            ReadWriteResources::Child(child) => child.wait(),
+            _ => Err(not_a_child()),
+        }
+    }
+
+    /// If this stream is backed by a child process spawned by
+    /// [`interact_with_command`], return its exit status if it has exited,
+    /// without blocking.
+    ///
+    /// Fails with [`std::io::ErrorKind::Unsupported`] if this stream is not
+    /// backed by a child process.
+    ///
+    /// [`interact_with_command`]: Self::interact_with_command
+    pub fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> {
+        match &mut self.resources {
+            ReadWriteResources::Child(child) => child.try_wait(),
+            _ => Err(not_a_child()),
+        }
+    }
+
+    /// If this stream is backed by a child process whose standard error was
+    /// captured with [`StderrMode::Capture`], take a stream which reads from
+    /// it.
+    ///
+    /// Returns `None` if standard error wasn't captured, or if it has already
+    /// been taken.
+    pub fn take_stderr(&mut self) -> Option<ReadHandle> {
+        match &mut self.resources {
+            ReadWriteResources::Child(child) => child.take_stderr().map(ReadHandle::child_stderr),
+            _ => None,
+        }
+    }
+
+    /// Shut down the writing side of this stream, so that the peer sees the
+    /// end of its input, while replies can still be read from this stream.
+    ///
+    /// For sockets, this uses `shutdown` with [`Shutdown::Write`]. For pipes,
+    /// including the standard input of a child process spawned by
+    /// [`interact_with_command`], and piped threads, this closes the pipe.
+    /// Writes to this stream afterward fail with
+    /// [`std::io::ErrorKind::BrokenPipe`].
+    ///
+    /// Fails with [`std::io::ErrorKind::Unsupported`] for other streams, such
+    /// as standard input and output, and terminals.
+    ///
+    /// [`Shutdown::Write`]: std::net::Shutdown::Write
+    /// [`interact_with_command`]: Self::interact_with_command
+    pub fn shutdown_write(&mut self) -> io::Result<()> {
+        let raw_write_handle = match &mut self.resources {
+            ReadWriteResources::TcpStream(tcp_stream) => {
+                return tcp_stream.shutdown(Shutdown::Write)
+            }
+            ReadWriteResources::PipeReaderWriter((_, pipe_writer))
+            | ReadWriteResources::PipedThread(Some((_, pipe_writer, _))) => {
+                *pipe_writer = broken_pipe()?;
+                pipe_writer.as_raw_handle()
+            }
+            ReadWriteResources::ChildStdoutStdin((_, child_stdin)) => {
+                *child_stdin = broken_child_stdin()?;
+                child_stdin.as_raw_handle()
+            }
+            ReadWriteResources::Child(child) => {
+                let child_stdin = broken_child_stdin()?;
+                let raw_write_handle = child_stdin.as_raw_handle();
+                child.replace_stdin(child_stdin);
+                raw_write_handle
+            }
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "stream doesn't support shutting down just its writing side",
+                ))
+            }
+        };
+        self.write_descriptor = unsafe { Descriptor::raw_handle(raw_write_handle) };
+        Ok(())
+    }
+
+    /// Close the stream.
+    ///
+    /// If this stream is backed by a child process and
+    /// [`set_check_exit_status`] has enabled exit-status checking, this waits
+    /// for the child to exit and reports an unsuccessful exit as an error.
+    ///
+    /// [`set_check_exit_status`]: Self::set_check_exit_status
+    pub fn close(mut self) -> io::Result<()> {
+        match &mut self.resources {
+            // As in `wait`, `self` is consumed, so closing the child's stdin
+            // here is fine. Close its stdout too, so that it doesn't block
+            // writing output that nothing will read while we wait for it.
+            ReadWriteResources::Child(child) => {
+                child.close_stdout();
+                child.check_exit_status()
+            }
+            ReadWriteResources::PipedThread(piped_thread) => match piped_thread.take() {
+                Some((pipe_reader, pipe_writer, mut duplex_thread)) => {
+                    drop(pipe_reader);
+                    drop(pipe_writer);
+                    match duplex_thread.close() {
+                        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+                        result => result,
+                    }
+                }
+                None => Ok(()),
+            },
+            _ => Ok(()),
+        }
+    }
+
+    /// Recover the character device file this stream reads from and writes
+    /// to.
+    ///
+    /// Returns `Err(self)` if this stream isn't backed by one.
+    pub fn into_char_device(self) -> Result<File, Self> {
+        match self.resources {
+            ReadWriteResources::CharDevice(resource) => Ok(resource),
+            _ => Err(self),
+        }
+    }
+
+    /// Recover the TCP socket this stream reads from and writes to.
+    ///
+    /// Returns `Err(self)` if this stream isn't backed by one.
+    pub fn into_tcp_stream(self) -> Result<TcpStream, Self> {
+        match self.resources {
+            ReadWriteResources::TcpStream(resource) => Ok(resource),
+            _ => Err(self),
+        }
+    }
+
+    /// Recover the pipes this stream reads from and writes to.
+    ///
+    /// Returns `Err(self)` if this stream isn't backed by one.
+    pub fn into_pipe_reader_writer(self) -> Result<(PipeReader, PipeWriter), Self> {
+        match self.resources {
+            ReadWriteResources::PipeReaderWriter(resource) => Ok(resource),
+            _ => Err(self),
+        }
+    }
+
+    /// Recover the child process' standard output and input this stream
+    /// reads from and writes to.
+    ///
+    /// Returns `Err(self)` if this stream isn't backed by one.
+    pub fn into_child_stdout_stdin(self) -> Result<(ChildStdout, ChildStdin), Self> {
+        match self.resources {
+            ReadWriteResources::ChildStdoutStdin(resource) => Ok(resource),
+            _ => Err(self),
+        }
+    }
+
+    /// Recover the child process spawned by [`interact_with_command`], with
+    /// its standard output and input in [`Child::stdout`] and
+    /// [`Child::stdin`].
+    ///
+    /// Returns `Err(self)` if this stream isn't backed by one.
+    ///
+    /// [`interact_with_command`]: Self::interact_with_command
+    /// [`Child::stdout`]: std::process::Child::stdout
+    /// [`Child::stdin`]: std::process::Child::stdin
+    pub fn into_child(self) -> Result<Child, Self> {
+        match self.resources {
+            ReadWriteResources::Child(child) => Ok(child.into_child()),
+            _ => Err(self),
+        }
+    }
+
+    /// Split this stream into a [`ReadHandle`] and a [`WriteHandle`], which
+    /// can be used independently, such as from different threads.
+    ///
+    /// Where the two sides have separate resources, such as a pair of pipes,
+    /// each half takes its own, so dropping the write half closes the writing
+    /// side. A child process spawned by [`interact_with_command`] goes with
+    /// the read half, which can wait for it, while the write half takes its
+    /// standard input. Otherwise, such as for a socket, the halves share the
+    /// resources, which are released once both halves are dropped.
+    ///
+    /// [`interact_with_command`]: Self::interact_with_command
+    pub fn into_split(self) -> (ReadHandle, WriteHandle) {
+        let (read_resources, write_resources) = self.resources.split();
+        (
+            ReadHandle {
+                descriptor: self.read_descriptor,
+                resources: read_resources,
+            },
+            WriteHandle {
+                descriptor: self.write_descriptor,
+                resources: write_resources,
+            },
+        )
+    }
+
+    /// Put the halves made by [`into_split`] back together.
+    ///
+    /// This also combines other halves whose resources fit together, such as
+    /// a pipe reader and a pipe writer. Otherwise, it returns the halves
+    /// unchanged.
+    ///
+    /// [`into_split`]: Self::into_split
+    #[allow(clippy::result_large_err)] // The halves are handed back on failure.
+    pub fn reunite(
+        read: ReadHandle,
+        write: WriteHandle,
+    ) -> Result<Self, (ReadHandle, WriteHandle)> {
+        match ReadWriteResources::reunite(read.resources, write.resources) {
+            Ok(resources) => Ok(Self {
+                read_descriptor: read.descriptor,
+                write_descriptor: write.descriptor,
+                resources,
+            }),
+            Err((read_resources, write_resources)) => Err((
+                ReadHandle {
+                    descriptor: read.descriptor,
+                    resources: read_resources,
+                },
+                WriteHandle {
+                    descriptor: write.descriptor,
+                    resources: write_resources,
+                },
+            )),
+        }
+    }
+
+    /// Wait for a piped thread to exit and report its result, leaving just
+    /// the pipes.
+    fn join_piped_thread(&mut self) -> io::Result<()> {
+        if let ReadWriteResources::PipedThread(piped_thread) = &mut self.resources {
+            if let Some((pipe_reader, pipe_writer, mut duplex_thread)) = piped_thread.take() {
+                self.resources = ReadWriteResources::PipeReaderWriter((pipe_reader, pipe_writer));
+                return duplex_thread.join();
+            }
+        }
+        Ok(())
+    }
+
+    fn map_err(&mut self, e: io::Error) -> io::Error {
+        // A broken pipe means the thread has exited; report its error, if it
+        // had one.
+        if e.kind() == io::ErrorKind::BrokenPipe
+            && matches!(self.resources, ReadWriteResources::PipedThread(..))
+        {
+            return self.join_piped_thread().err().unwrap_or(e);
+        }
+        e
+    }
+}
+
+impl ReadWriteResources {
+    fn kind(&self) -> HandleKind {
+        match self {
+            Self::PipeReaderWriter(_) => HandleKind::Pipe,
+            Self::StdinStdout(_) | Self::StdinStderr(_) => HandleKind::Stdio,
+            Self::Child(_) | Self::ChildStdoutStdin(_) => HandleKind::Child,
+            Self::CharDevice(_) => HandleKind::CharDevice,
+            Self::TcpStream(_) => HandleKind::TcpStream,
+            Self::PipedThread(_) => HandleKind::PipedThread,
+        }
+    }
+
+    /// Divide these resources between the halves of a split stream.
+    fn split(self) -> (ReadResources, WriteResources) {
+        match self {
+            Self::PipeReaderWriter((pipe_reader, pipe_writer)) => (
+                ReadResources::PipeReader(pipe_reader),
+                WriteResources::PipeWriter(pipe_writer),
+            ),
+            Self::StdinStdout((stdin_locker, stdout_locker)) => (
+                ReadResources::Stdin(stdin_locker),
+                WriteResources::Stdout(stdout_locker),
+            ),
+            Self::StdinStderr((stdin_locker, stderr_locker)) => (
+                ReadResources::Stdin(stdin_locker),
+                WriteResources::Stderr(stderr_locker),
+            ),
+            Self::ChildStdoutStdin((child_stdout, child_stdin)) => (
+                ReadResources::ChildStdout(child_stdout),
+                WriteResources::ChildStdin(child_stdin),
+            ),
+            Self::Child(mut child) => match child.take_stdin() {
+                Some(child_stdin) => (
+                    ReadResources::Child(child),
+                    WriteResources::ChildStdin(child_stdin),
+                ),
+                None => Self::Child(child).share(),
+            },
+            resources => resources.share(),
+        }
+    }
+
+    /// Share these resources between the halves of a split stream.
+    fn share(self) -> (ReadResources, WriteResources) {
+        let shared = Arc::new(self);
+        (
+            ReadResources::Shared(Arc::clone(&shared)),
+            WriteResources::Shared(shared),
+        )
+    }
+
+    /// Combine the resources of the halves of a split stream.
+    #[allow(clippy::result_large_err)] // The resources are handed back on failure.
+    fn reunite(
+        read: ReadResources,
+        write: WriteResources,
+    ) -> Result<Self, (ReadResources, WriteResources)> {
+        match (read, write) {
+            (ReadResources::PipeReader(pipe_reader), WriteResources::PipeWriter(pipe_writer)) => {
+                Ok(Self::PipeReaderWriter((pipe_reader, pipe_writer)))
+            }
+            (ReadResources::Stdin(stdin_locker), WriteResources::Stdout(stdout_locker)) => {
+                Ok(Self::StdinStdout((stdin_locker, stdout_locker)))
+            }
+            (ReadResources::Stdin(stdin_locker), WriteResources::Stderr(stderr_locker)) => {
+                Ok(Self::StdinStderr((stdin_locker, stderr_locker)))
+            }
+            (ReadResources::ChildStdout(child_stdout), WriteResources::ChildStdin(child_stdin)) => {
+                Ok(Self::ChildStdoutStdin((child_stdout, child_stdin)))
+            }
+            (ReadResources::Child(mut child), WriteResources::ChildStdin(child_stdin)) => {
+                match child.restore_stdin(child_stdin) {
+                    Ok(()) => Ok(Self::Child(child)),
+                    Err(child_stdin) => Err((
+                        ReadResources::Child(child),
+                        WriteResources::ChildStdin(child_stdin),
+                    )),
+                }
+            }
+            (ReadResources::Shared(read), WriteResources::Shared(write))
+                if Arc::ptr_eq(&read, &write) =>
+            {
+                drop(write);
+                Arc::try_unwrap(read).map_err(|shared| {
+                    (
+                        ReadResources::Shared(Arc::clone(&shared)),
+                        WriteResources::Shared(shared),
+                    )
+                })
+            }
+            halves => Err(halves),
         }
     }
 }
@@ -427,6 +1619,7 @@ impl Read for ReadHandle {
     #[inline]
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
         match self.descriptor.read(buf) {
+            Ok(0) if !buf.is_empty() => self.end_of_stream().map(|()| 0),
             Ok(size) => Ok(size),
             Err(e) => Err(self.map_err(e)),
         }
@@ -435,6 +1628,7 @@ impl Read for ReadHandle {
     #[inline]
     fn read_vectored(&mut self, bufs: &mut [IoSliceMut]) -> io::Result<usize> {
         match self.descriptor.read_vectored(bufs) {
+            Ok(0) if bufs.iter().any(|b| !b.is_empty()) => self.end_of_stream().map(|()| 0),
             Ok(size) => Ok(size),
             Err(e) => Err(self.map_err(e)),
         }
@@ -449,7 +1643,7 @@ impl Read for ReadHandle {
     #[inline]
     fn read_to_end(&mut self, buf: &mut Vec<u8>) -> io::Result<usize> {
         match self.descriptor.read_to_end(buf) {
-            Ok(size) => Ok(size),
+            Ok(size) => self.end_of_stream().map(|()| size),
             Err(e) => Err(self.map_err(e)),
         }
     }
@@ -457,7 +1651,7 @@ impl Read for ReadHandle {
     #[inline]
     fn read_to_string(&mut self, buf: &mut String) -> io::Result<usize> {
         match self.descriptor.read_to_string(buf) {
-            Ok(size) => Ok(size),
+            Ok(size) => self.end_of_stream().map(|()| size),
             Err(e) => Err(self.map_err(e)),
         }
     }
@@ -466,16 +1660,33 @@ impl Read for ReadHandle {
     fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
         match self.descriptor.read_exact(buf) {
             Ok(()) => Ok(()),
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                self.end_of_stream()?;
+                Err(e)
+            }
             Err(e) => Err(self.map_err(e)),
         }
     }
 }
 
+impl Seek for ReadHandle {
+    #[inline]
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        if !self.is_seekable() {
+            return Err(not_seekable());
+        }
+        self.descriptor.seek(pos)
+    }
+}
+
 impl Write for WriteHandle {
     #[inline]
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
         match self.descriptor.write(buf) {
-            Ok(size) => Ok(size),
+            Ok(size) => {
+                self.wrote(size);
+                Ok(size)
+            }
             Err(e) => Err(self.map_err(e)),
         }
     }
@@ -484,16 +1695,12 @@ impl Write for WriteHandle {
     fn flush(&mut self) -> io::Result<()> {
         match self.descriptor.flush() {
             Ok(()) => {
-                // There's no way to send a flush event through a pipe, so for
-                // now, force a flush by closing the pipe, waiting for the
-                // thread to exit, recover the boxed writer, and then wrap it
-                // in a whole new piped thread.
-                if let WriteResources::PipedThread(piped_thread) = &mut self.resources {
-                    let (mut pipe_writer, join_handle) = piped_thread.take().unwrap();
-                    pipe_writer.flush()?;
-                    drop(pipe_writer);
-                    let boxed_write = join_handle.join().unwrap().unwrap();
-                    *self = Self::piped_thread(boxed_write)?;
+                // Wait for the thread to drain the pipe and flush the boxed
+                // writer, through the side channel.
+                if let WriteResources::PipedThread(_, flusher) = &self.resources {
+                    if !flusher.flush()? {
+                        return Err(self.map_err(io::ErrorKind::BrokenPipe.into()));
+                    }
                 }
                 Ok(())
             }
@@ -504,7 +1711,10 @@ impl Write for WriteHandle {
     #[inline]
     fn write_vectored(&mut self, bufs: &[IoSlice]) -> io::Result<usize> {
         match self.descriptor.write_vectored(bufs) {
-            Ok(size) => Ok(size),
+            Ok(size) => {
+                self.wrote(size);
+                Ok(size)
+            }
             Err(e) => Err(self.map_err(e)),
         }
     }
@@ -517,6 +1727,9 @@ impl Write for WriteHandle {
 
     #[inline]
     fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        if self.by_write() {
+            return ByWrite(self).write_all(buf);
+        }
         match self.descriptor.write_all(buf) {
             Ok(()) => Ok(()),
             Err(e) => Err(self.map_err(e)),
@@ -526,6 +1739,9 @@ impl Write for WriteHandle {
     #[cfg(write_all_vectored)]
     #[inline]
     fn write_all_vectored(&mut self, bufs: &mut [IoSlice]) -> io::Result<()> {
+        if self.by_write() {
+            return ByWrite(self).write_all_vectored(bufs);
+        }
         match self.descriptor.write_all_vectored(bufs) {
             Ok(()) => Ok(()),
             Err(e) => Err(self.map_err(e)),
@@ -534,6 +1750,9 @@ impl Write for WriteHandle {
 
     #[inline]
     fn write_fmt(&mut self, fmt: Arguments) -> io::Result<()> {
+        if self.by_write() {
+            return ByWrite(self).write_fmt(fmt);
+        }
         match self.descriptor.write_fmt(fmt) {
             Ok(()) => Ok(()),
             Err(e) => Err(self.map_err(e)),
@@ -541,6 +1760,16 @@ impl Write for WriteHandle {
     }
 }
 
+impl Seek for WriteHandle {
+    #[inline]
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        if !self.is_seekable() {
+            return Err(not_seekable());
+        }
+        self.descriptor.seek(pos)
+    }
+}
+
 impl Read for ReadWriteHandle {
     #[inline]
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
@@ -714,38 +1943,43 @@ impl AsRawReadWriteHandleOrSocket for ReadWriteHandle {
     }
 }
 
-impl Drop for ReadResources {
-    fn drop(&mut self) {
-        match self {
-            Self::PipedThread(piped_thread) => {
-                let (pipe_reader, join_handle) = piped_thread.take().unwrap();
-                drop(pipe_reader);
-                join_handle.join().unwrap().unwrap();
-            }
-            _ => {}
-        }
-    }
+/// Construct the error reported when cloning is requested on a stream that
+/// can't be cloned.
+fn not_cloneable() -> io::Error {
+    io::Error::new(io::ErrorKind::Unsupported, "stream can't be cloned")
 }
 
-impl Drop for WriteResources {
-    fn drop(&mut self) {
-        match self {
-            Self::PipedThread(piped_thread) => {
-                if let Some((pipe_writer, join_handle)) = piped_thread.take() {
-                    drop(pipe_writer);
-                    join_handle.join().unwrap().unwrap();
-                }
-            }
-            _ => {}
-        }
-    }
+/// Create a pipe and close its reading end, to stand in for the writing side
+/// of a stream which has been shut down, so that writes fail with
+/// `BrokenPipe`.
+fn broken_pipe() -> io::Result<PipeWriter> {
+    let (pipe_reader, pipe_writer) = pipe()?;
+    drop(pipe_reader);
+    Ok(pipe_writer)
 }
 
-impl Drop for ReadWriteResources {
-    fn drop(&mut self) {
-        match self {
-            _ => {}
-        }
+/// Like [`broken_pipe`], but for a child process' standard input.
+fn broken_child_stdin() -> io::Result<ChildStdin> {
+    let raw_handle = broken_pipe()?.into_raw_handle();
+    Ok(ChildStdin::from(unsafe {
+        OwnedHandle::from_raw_handle(raw_handle)
+    }))
+}
+
+/// Forward just `write` and `flush`, so that std's default implementations of
+/// the other `Write` functions are used, which count the bytes passed to each
+/// `write`.
+struct ByWrite<'a, W: Write>(&'a mut W);
+
+impl<W: Write> Write for ByWrite<'_, W> {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
     }
 }
 
@@ -759,8 +1993,8 @@ impl Debug for ReadHandle {
         b.field("raw_handle", &self.as_raw_handle());
         b.field("raw_socket", &self.as_raw_socket());
 
-        // Don't print the resources, as we don't want to leak that
-        // information through our abstraction.
+        // Don't print the resources themselves, just what kind they are.
+        b.field("kind", &self.kind());
 
         b.finish()
     }
@@ -776,8 +2010,8 @@ impl Debug for WriteHandle {
         b.field("raw_handle", &self.as_raw_handle());
         b.field("raw_socket", &self.as_raw_socket());
 
-        // Don't print the resources, as we don't want to leak that
-        // information through our abstraction.
+        // Don't print the resources themselves, just what kind they are.
+        b.field("kind", &self.kind());
 
         b.finish()
     }
@@ -795,8 +2029,8 @@ impl Debug for ReadWriteHandle {
         b.field("raw_write_handle", &self.as_raw_write_handle());
         b.field("raw_write_socket", &self.as_raw_write_socket());
 
-        // Don't print the resources, as we don't want to leak that
-        // information through our abstraction.
+        // Don't print the resources themselves, just what kind they are.
+        b.field("kind", &self.kind());
 
         b.finish()
     }
@@ -0,0 +1,177 @@
+#![cfg(all(unix, feature = "tokio"))]
+
+use io_handles::{
+    AsyncReadHandle, AsyncReadWriteHandle, AsyncWriteHandle, ReadHandle, ReadWriteHandle,
+    WriteHandle,
+};
+use std::{
+    fs::File,
+    io::{Read, Write},
+    os::unix::net::UnixStream,
+    process::Command,
+    thread,
+    time::{Duration, Instant},
+};
+use tempfile::tempdir;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+#[tokio::test]
+async fn test_async_pipe() -> anyhow::Result<()> {
+    let mut input = AsyncReadHandle::new(ReadHandle::str("hello, world")?)?;
+    let mut s = String::new();
+    input.read_to_string(&mut s).await?;
+    assert_eq!(s, "hello, world");
+
+    let big = "x".repeat(100_000);
+    let mut input = AsyncReadHandle::new(ReadHandle::str(&big)?)?;
+    let mut s = String::new();
+    input.read_to_string(&mut s).await?;
+    assert_eq!(s, big);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_async_file() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let path = dir.path().join("file.txt");
+
+    let mut output = AsyncWriteHandle::new(WriteHandle::file(File::create(&path)?))?;
+    output.write_all(b"hello, ").await?;
+    output.write_all(b"world").await?;
+    output.flush().await?;
+    drop(output);
+
+    let mut s = String::new();
+    File::open(&path)?.read_to_string(&mut s)?;
+    assert_eq!(s, "hello, world");
+
+    let mut input = AsyncReadHandle::new(ReadHandle::file(File::open(&path)?))?;
+    let mut buf = [0_u8; 5];
+    input.read_exact(&mut buf).await?;
+    assert_eq!(&buf, b"hello");
+    let mut s = String::new();
+    input.read_to_string(&mut s).await?;
+    assert_eq!(s, ", world");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_async_unix_stream() -> anyhow::Result<()> {
+    let (a, mut b) = UnixStream::pair()?;
+    let mut interact = AsyncReadWriteHandle::new(ReadWriteHandle::unix_stream(a))?;
+
+    let peer = std::thread::spawn(move || -> std::io::Result<()> {
+        let mut buf = [0_u8; 4];
+        b.read_exact(&mut buf)?;
+        assert_eq!(&buf, b"ping");
+        b.write_all(b"pong")
+    });
+
+    interact.write_all(b"ping").await?;
+    interact.flush().await?;
+    let mut buf = [0_u8; 4];
+    interact.read_exact(&mut buf).await?;
+    assert_eq!(&buf, b"pong");
+    peer.join().unwrap()?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_async_shutdown() -> anyhow::Result<()> {
+    // Shutting down a `WriteHandle` closes it, so the reader sees the end.
+    let (mut reader, writer) = os_pipe::pipe()?;
+    let mut output = AsyncWriteHandle::new(WriteHandle::pipe_writer(writer))?;
+    output.write_all(b"hello").await?;
+    output.shutdown().await?;
+    let mut s = String::new();
+    reader.read_to_string(&mut s)?;
+    assert_eq!(s, "hello");
+    assert!(output.write_all(b"more").await.is_err());
+
+    // And reports errors from closing it.
+    struct FailingFlush;
+
+    impl Write for FailingFlush {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Err(std::io::Error::other("flush failed"))
+        }
+    }
+
+    let mut output = AsyncWriteHandle::new(WriteHandle::piped_thread(Box::new(FailingFlush))?)?;
+    output.write_all(b"hello").await?;
+    let err = output.shutdown().await.unwrap_err();
+    assert_eq!(err.to_string(), "flush failed");
+
+    // Shutting down a `ReadWriteHandle` just shuts down its writing side.
+    let (a, mut b) = UnixStream::pair()?;
+    let mut interact = AsyncReadWriteHandle::new(ReadWriteHandle::unix_stream(a))?;
+    let peer = thread::spawn(move || -> std::io::Result<()> {
+        let mut s = String::new();
+        b.read_to_string(&mut s)?;
+        b.write_all(s.to_uppercase().as_bytes())
+    });
+    interact.write_all(b"shout").await?;
+    interact.shutdown().await?;
+    let mut s = String::new();
+    interact.read_to_string(&mut s).await?;
+    assert_eq!(s, "SHOUT");
+    peer.join().unwrap()?;
+
+    let (reader, mut peer_writer) = os_pipe::pipe()?;
+    let (mut peer_reader, writer) = os_pipe::pipe()?;
+    let mut interact =
+        AsyncReadWriteHandle::new(ReadWriteHandle::pipe_reader_writer(reader, writer))?;
+    interact.write_all(b"ping").await?;
+    interact.shutdown().await?;
+    let mut s = String::new();
+    peer_reader.read_to_string(&mut s)?;
+    assert_eq!(s, "ping");
+    peer_writer.write_all(b"pong")?;
+    drop(peer_writer);
+    let mut s = String::new();
+    interact.read_to_string(&mut s).await?;
+    assert_eq!(s, "pong");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_async_child_does_not_block() -> anyhow::Result<()> {
+    // The child closes its output long before it exits, and with exit-status
+    // checking enabled, the end of its output waits for it to exit. That
+    // mustn't hold up other tasks on this single-threaded runtime.
+    let mut sh = Command::new("sh");
+    sh.arg("-c").arg("echo hello; exec >&-; sleep 2");
+    let mut child = ReadHandle::read_from_command(sh)?;
+    child.set_check_exit_status(true)?;
+    let mut child = AsyncReadHandle::new(child)?;
+
+    let (reader, mut writer) = os_pipe::pipe()?;
+    let mut other = AsyncReadHandle::new(ReadHandle::pipe_reader(reader))?;
+    let writing = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(100));
+        writer.write_all(b"other")
+    });
+
+    let start = Instant::now();
+    let (from_child, from_other) = tokio::join!(
+        async {
+            let mut s = String::new();
+            child.read_to_string(&mut s).await.map(|_| s)
+        },
+        async {
+            let mut s = String::new();
+            other.read_to_string(&mut s).await?;
+            Ok::<_, std::io::Error>((s, start.elapsed()))
+        },
+    );
+    assert_eq!(from_child?, "hello\n");
+    let (s, elapsed) = from_other?;
+    assert_eq!(s, "other");
+    assert!(elapsed < Duration::from_secs(1), "{:?}", elapsed);
+    writing.join().unwrap()?;
+    Ok(())
+}
@@ -0,0 +1,130 @@
+#![cfg(all(unix, feature = "async-std"))]
+
+use async_std::io::{ReadExt, Write as AsyncWrite, WriteExt};
+use io_handles::{
+    AsyncStdReadHandle, AsyncStdReadWriteHandle, AsyncStdWriteHandle, ReadHandle, ReadWriteHandle,
+    WriteHandle,
+};
+use std::{
+    fs::File,
+    future::poll_fn,
+    io::{Read, Write},
+    os::unix::net::UnixStream,
+    pin::Pin,
+    thread,
+};
+use tempfile::tempdir;
+
+/// async-std's `WriteExt` doesn't provide `close`.
+async fn close<W: AsyncWrite + Unpin>(writer: &mut W) -> std::io::Result<()> {
+    poll_fn(|cx| Pin::new(&mut *writer).poll_close(cx)).await
+}
+
+#[async_std::test]
+async fn test_async_std_pipe() -> anyhow::Result<()> {
+    let mut input = AsyncStdReadHandle::new(ReadHandle::str("hello, world")?)?;
+    let mut s = String::new();
+    input.read_to_string(&mut s).await?;
+    assert_eq!(s, "hello, world");
+
+    let big = "x".repeat(100_000);
+    let (reader, mut writer) = os_pipe::pipe()?;
+    let writing = {
+        let big = big.clone();
+        thread::spawn(move || writer.write_all(big.as_bytes()))
+    };
+    let mut input = AsyncStdReadHandle::new(ReadHandle::pipe_reader(reader))?;
+    let mut s = String::new();
+    input.read_to_string(&mut s).await?;
+    assert_eq!(s, big);
+    writing.join().unwrap()?;
+    Ok(())
+}
+
+#[async_std::test]
+async fn test_async_std_file() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let path = dir.path().join("file.txt");
+
+    let mut output = AsyncStdWriteHandle::new(WriteHandle::file(File::create(&path)?))?;
+    output.write_all(b"hello, ").await?;
+    output.write_all(b"world").await?;
+    close(&mut output).await?;
+
+    let mut s = String::new();
+    File::open(&path)?.read_to_string(&mut s)?;
+    assert_eq!(s, "hello, world");
+
+    let mut input = AsyncStdReadHandle::new(ReadHandle::file(File::open(&path)?))?;
+    let mut buf = [0_u8; 5];
+    input.read_exact(&mut buf).await?;
+    assert_eq!(&buf, b"hello");
+    let mut s = String::new();
+    input.read_to_string(&mut s).await?;
+    assert_eq!(s, ", world");
+    Ok(())
+}
+
+#[async_std::test]
+async fn test_async_std_unix_stream() -> anyhow::Result<()> {
+    let (a, mut b) = UnixStream::pair()?;
+    let mut interact = AsyncStdReadWriteHandle::new(ReadWriteHandle::unix_stream(a))?;
+
+    let peer = thread::spawn(move || -> std::io::Result<()> {
+        let mut buf = [0_u8; 4];
+        b.read_exact(&mut buf)?;
+        assert_eq!(&buf, b"ping");
+        b.write_all(b"pong")?;
+
+        // Closing shuts down just the writing side.
+        let mut s = String::new();
+        b.read_to_string(&mut s)?;
+        assert_eq!(s, "bye");
+        b.write_all(b"done")
+    });
+
+    interact.write_all(b"ping").await?;
+    interact.flush().await?;
+    let mut buf = [0_u8; 4];
+    interact.read_exact(&mut buf).await?;
+    assert_eq!(&buf, b"pong");
+    interact.write_all(b"bye").await?;
+    close(&mut interact).await?;
+    let mut s = String::new();
+    interact.read_to_string(&mut s).await?;
+    assert_eq!(s, "done");
+    peer.join().unwrap()?;
+    Ok(())
+}
+
+#[async_std::test]
+async fn test_async_std_close() -> anyhow::Result<()> {
+    // Closing a `WriteHandle` lets the reader see the end of the stream.
+    let (mut reader, writer) = os_pipe::pipe()?;
+    let mut output = AsyncStdWriteHandle::new(WriteHandle::pipe_writer(writer))?;
+    output.write_all(b"hello").await?;
+    close(&mut output).await?;
+    let mut s = String::new();
+    reader.read_to_string(&mut s)?;
+    assert_eq!(s, "hello");
+    assert!(output.write_all(b"more").await.is_err());
+
+    // And reports errors from closing it.
+    struct FailingFlush;
+
+    impl Write for FailingFlush {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Err(std::io::Error::other("flush failed"))
+        }
+    }
+
+    let mut output = AsyncStdWriteHandle::new(WriteHandle::piped_thread(Box::new(FailingFlush))?)?;
+    output.write_all(b"hello").await?;
+    let err = close(&mut output).await.unwrap_err();
+    assert_eq!(err.to_string(), "flush failed");
+    Ok(())
+}
@@ -3,11 +3,17 @@
 
 #![cfg_attr(can_vector, feature(can_vector))]
 #![cfg_attr(bench, feature(test))]
+// Stay close to the upstream tests, which predate these lints.
+#![allow(
+    clippy::io_other_error,
+    clippy::unused_io_amount,
+    clippy::write_literal
+)]
 
 #[cfg(bench)]
 extern crate test;
 
-use io_handles::{BufReaderLineWriter, BufReaderWriter};
+use io_handles::{BufReaderLineWriter, BufReaderWriter, FlushPolicy};
 use std::{
     io::{self, prelude::*, ErrorKind, IoSlice},
     panic,
@@ -301,6 +307,7 @@ fn panic_in_write_doesnt_flush_in_drop() {
     assert_eq!(WRITES.load(Ordering::SeqCst), 1);
 }
 
+#[cfg_attr(not(bench), allow(dead_code))]
 struct Empty;
 
 impl Read for Empty {
@@ -325,6 +332,7 @@ fn bench_buffered_reader(b: &mut test::bench::Bencher) {
     b.iter(|| BufReaderWriter::new(Empty));
 }
 
+#[cfg_attr(not(bench), allow(dead_code))]
 struct Sink;
 
 impl Read for Sink {
@@ -916,3 +924,102 @@ fn single_formatted_write() {
         [RecordedEvent::Write("hello, world!\n".to_string())]
     );
 }
+
+/// A stream which replies to reads with `b"reply\n"` and records the order of
+/// reads, writes, and flushes.
+#[derive(Debug, Clone, Default)]
+struct Transcript {
+    pub events: Vec<&'static str>,
+}
+
+impl Read for Transcript {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.events.push("read");
+        (&b"reply\n"[..]).read(buf)
+    }
+}
+
+impl Write for Transcript {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.events.push("write");
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.events.push("flush");
+        Ok(())
+    }
+}
+
+#[test]
+fn flush_policy_always() {
+    let mut stream = BufReaderWriter::new(Transcript::default());
+    assert_eq!(stream.flush_policy(), FlushPolicy::Always);
+
+    // A plain `read` flushes before reading from the inner stream.
+    stream.write_all(b"prompt").unwrap();
+    let mut buf = [0_u8; 2];
+    stream.read(&mut buf).unwrap();
+    assert_eq!(stream.get_ref().events, ["write", "flush", "read"]);
+
+    // Reads satisfied from the reader buffer don't, and `fill_buf` flushes
+    // once it needs more input.
+    stream.write_all(b"prompt").unwrap();
+    stream.read_exact(&mut buf).unwrap();
+    assert_eq!(stream.get_ref().events, ["write", "flush", "read"]);
+
+    stream.consume(2);
+    stream.fill_buf().unwrap();
+    assert_eq!(
+        stream.get_ref().events,
+        ["write", "flush", "read", "write", "flush", "read"]
+    );
+
+    let mut stream = BufReaderLineWriter::new(Transcript::default());
+    stream.write_all(b"prompt").unwrap();
+    let mut buf = [0_u8; 6];
+    stream.read_exact(&mut buf).unwrap();
+    assert_eq!(stream.get_ref().events, ["write", "flush", "read"]);
+}
+
+#[test]
+fn flush_policy_line_reads() {
+    let mut stream = BufReaderWriter::new(Transcript::default());
+    stream.set_flush_policy(FlushPolicy::LineReads);
+    stream.write_all(b"prompt").unwrap();
+    let mut buf = [0_u8; 2];
+    stream.read(&mut buf).unwrap();
+    assert_eq!(stream.get_ref().events, ["read"]);
+
+    let mut line = String::new();
+    stream.read_line(&mut line).unwrap();
+    assert_eq!(line, "ply\n");
+    assert_eq!(stream.get_ref().events, ["read", "write", "flush"]);
+
+    let mut stream = BufReaderLineWriter::new(Transcript::default());
+    stream.set_flush_policy(FlushPolicy::LineReads);
+    stream.write_all(b"prompt").unwrap();
+    stream.fill_buf().unwrap();
+    assert_eq!(stream.get_ref().events, ["read"]);
+    let mut line = Vec::new();
+    stream.read_until(b'\n', &mut line).unwrap();
+    assert_eq!(stream.get_ref().events, ["read", "write", "flush"]);
+}
+
+#[test]
+fn flush_policy_never() {
+    let mut stream = BufReaderWriter::new(Transcript::default());
+    stream.set_flush_policy(FlushPolicy::Never);
+    stream.write_all(b"prompt").unwrap();
+    let mut line = String::new();
+    stream.read_line(&mut line).unwrap();
+    assert_eq!(line, "reply\n");
+    assert_eq!(stream.get_ref().events, ["read"]);
+
+    let mut stream = BufReaderLineWriter::new(Transcript::default());
+    stream.set_flush_policy(FlushPolicy::Never);
+    stream.write_all(b"prompt").unwrap();
+    let mut line = String::new();
+    stream.read_line(&mut line).unwrap();
+    assert_eq!(stream.get_ref().events, ["read"]);
+}
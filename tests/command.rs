@@ -0,0 +1,463 @@
+//! Tests for command-backed streams. These use Unix commands, such as `sh`
+//! and `cat`.
+
+#![cfg(unix)]
+
+use io_handles::{ReadHandle, ReadWriteHandle, WriteHandle};
+use std::{
+    io::{ErrorKind, Read, Write},
+    process::Command,
+};
+
+#[test]
+fn test_exit_status() -> anyhow::Result<()> {
+    let mut command = Command::new("sh");
+    command.arg("-c").arg("echo hello; exit 3");
+    let mut input = ReadHandle::read_from_command(command)?;
+    let mut s = String::new();
+    input.read_to_string(&mut s)?;
+    assert_eq!(s, "hello\n");
+    assert_eq!(input.wait()?.code(), Some(3));
+
+    let mut output = WriteHandle::write_to_command(Command::new("cat"))?;
+    write!(output, "hello")?;
+    assert!(output.wait()?.success());
+
+    assert_eq!(
+        ReadHandle::str("hello")?.wait().unwrap_err().kind(),
+        ErrorKind::Unsupported
+    );
+    Ok(())
+}
+
+#[test]
+fn test_check_exit_status() -> anyhow::Result<()> {
+    let mut command = Command::new("sh");
+    command.arg("-c").arg("echo hello; exit 1");
+    let mut input = ReadHandle::read_from_command(command)?;
+    input.set_check_exit_status(true)?;
+    let mut s = String::new();
+    assert!(input.read_to_string(&mut s).is_err());
+    assert_eq!(s, "hello\n");
+
+    let mut input = ReadHandle::read_from_command(Command::new("true"))?;
+    input.set_check_exit_status(true)?;
+    let mut buf = [0_u8; 8];
+    assert_eq!(input.read(&mut buf)?, 0);
+
+    let mut output = WriteHandle::write_to_command(Command::new("false"))?;
+    output.set_check_exit_status(true)?;
+    assert!(output.close().is_err());
+
+    let mut output = WriteHandle::write_to_command(Command::new("cat"))?;
+    output.set_check_exit_status(true)?;
+    write!(output, "hello")?;
+    output.close()?;
+
+    let mut interact = ReadWriteHandle::interact_with_command(Command::new("false"))?;
+    interact.set_check_exit_status(true)?;
+    assert!(interact.close().is_err());
+
+    // Closing doesn't wait on a child blocked writing output nobody reads.
+    let mut command = Command::new("sh");
+    command
+        .arg("-c")
+        .arg("head -c 1000000 /dev/zero 2>/dev/null; exit 0");
+    let mut interact = ReadWriteHandle::interact_with_command(command)?;
+    interact.set_check_exit_status(true)?;
+    interact.close()?;
+
+    let mut command = Command::new("sh");
+    command.arg("-c").arg("head -c 1000000 /dev/zero; exit 0");
+    let mut interact = ReadWriteHandle::interact_with_command_in_pty(command)?;
+    interact.set_check_exit_status(true)?;
+    interact.close()?;
+
+    // Nor does waiting.
+    let mut command = Command::new("sh");
+    command.arg("-c").arg("head -c 1000000 /dev/zero; exit 5");
+    let interact = ReadWriteHandle::interact_with_command_in_pty(command)?;
+    assert_eq!(interact.wait()?.code(), Some(5));
+    Ok(())
+}
+
+#[test]
+fn test_pty() -> anyhow::Result<()> {
+    let (mut master, mut slave, path) = ReadWriteHandle::pty()?;
+    assert!(path.starts_with("/dev"));
+    slave.write_all(b"hello")?;
+    let mut buf = [0_u8; 5];
+    master.read_exact(&mut buf)?;
+    assert_eq!(&buf, b"hello");
+
+    // Reads reach the end once the slave side is closed.
+    drop(slave);
+    assert_eq!(master.read(&mut buf)?, 0);
+    Ok(())
+}
+
+#[test]
+fn test_command_in_pty() -> anyhow::Result<()> {
+    let mut command = Command::new("sh");
+    command
+        .arg("-c")
+        .arg("test -t 0 && test -t 1 && test -t 2 && read line && echo \"got $line\"; exit 4");
+    let mut interact = ReadWriteHandle::interact_with_command_in_pty(command)?;
+    interact.write_all(b"ping\n")?;
+    let mut s = String::new();
+    interact.read_to_string(&mut s)?;
+    // The terminal echoes input and translates line endings.
+    assert_eq!(s, "ping\r\ngot ping\r\n");
+    assert_eq!(interact.wait()?.code(), Some(4));
+    Ok(())
+}
+
+#[test]
+fn test_terminal() -> anyhow::Result<()> {
+    use std::time::Duration;
+
+    let (mut master, slave, _path) = ReadWriteHandle::pty()?;
+    let mut terminal = ReadWriteHandle::char_device(slave);
+    assert!(terminal.is_terminal());
+    assert!(terminal.terminal_size().is_ok());
+    master.set_read_timeout(Some(Duration::from_millis(50)))?;
+    let mut buf = [0_u8; 16];
+
+    // In raw mode, input isn't echoed, and is available without a newline.
+    {
+        let mut raw = terminal.raw_mode()?;
+        master.write_all(b"x")?;
+        let mut byte = [0_u8];
+        raw.read_exact(&mut byte)?;
+        assert_eq!(&byte, b"x");
+        assert_eq!(
+            master.read(&mut buf).unwrap_err().kind(),
+            ErrorKind::TimedOut
+        );
+    }
+
+    // Dropping the guard restores echoing.
+    master.write_all(b"y\n")?;
+    let mut line = [0_u8; 2];
+    terminal.read_exact(&mut line)?;
+    assert_eq!(&line, b"y\n");
+    master.read_exact(&mut buf[..3])?;
+    assert_eq!(&buf[..3], b"y\r\n");
+
+    let (input, _output) = os_pipe::pipe()?;
+    let mut input = ReadHandle::pipe_reader(input);
+    assert!(!input.is_terminal());
+    assert!(input.terminal_size().is_err());
+    assert!(input.raw_mode().is_err());
+    Ok(())
+}
+
+#[test]
+fn test_into_child() -> anyhow::Result<()> {
+    let mut interact = ReadWriteHandle::interact_with_command(Command::new("cat"))?;
+    write!(interact, "hello")?;
+    let interact = interact.into_char_device().unwrap_err();
+    let mut child = interact.into_child().unwrap();
+    drop(child.stdin.take());
+    let mut s = String::new();
+    let result = child.stdout.take().unwrap().read_to_string(&mut s);
+    assert!(child.wait()?.success());
+    result?;
+    assert_eq!(s, "hello");
+
+    assert!(ReadHandle::str("hello")?.into_child().is_err());
+    Ok(())
+}
+
+#[test]
+fn test_split_command() -> anyhow::Result<()> {
+    let interact = ReadWriteHandle::interact_with_command(Command::new("cat"))?;
+    let (mut reader, mut writer) = interact.into_split();
+    let thread = std::thread::spawn(move || write!(writer, "hello"));
+    let mut s = String::new();
+    reader.read_to_string(&mut s)?;
+    thread.join().unwrap()?;
+    assert_eq!(s, "hello");
+    assert!(reader.wait()?.success());
+    Ok(())
+}
+
+#[test]
+fn test_shutdown_write_command() -> anyhow::Result<()> {
+    let mut interact = ReadWriteHandle::interact_with_command(Command::new("cat"))?;
+    write!(interact, "hello")?;
+    interact.shutdown_write()?;
+    let mut s = String::new();
+    interact.read_to_string(&mut s)?;
+    assert_eq!(s, "hello");
+    assert!(interact.wait()?.success());
+    Ok(())
+}
+
+#[test]
+fn test_stdio() -> anyhow::Result<()> {
+    use std::{convert::TryFrom, process::Stdio};
+
+    // A piped thread keeps feeding the child after the conversion, and the
+    // guard waits for it.
+    let input = ReadHandle::piped_thread(Box::new(&b"from a thread"[..]))?;
+    let (stdin, guard) = input.try_into_stdio()?;
+    let output = Command::new("cat").stdin(stdin).output()?;
+    guard.close()?;
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"from a thread");
+
+    // So does a child process, whose exit status the guard reports.
+    let mut sh = Command::new("sh");
+    sh.arg("-c").arg("echo from a child; exit 3");
+    let mut input = ReadHandle::read_from_command(sh)?;
+    input.set_check_exit_status(true)?;
+    let (stdin, guard) = input.try_into_stdio()?;
+    let output = Command::new("cat").stdin(stdin).output()?;
+    assert_eq!(output.stdout, b"from a child\n");
+    assert!(guard.close().is_err());
+
+    // Writing to a command forwards output through the first child.
+    let (mut reader, writer) = os_pipe::pipe()?;
+    let mut sh = Command::new("sh");
+    sh.arg("-c").arg("cat >&2").stderr(writer);
+    let output = WriteHandle::write_to_command(sh)?;
+    let (stdout, guard) = output.try_into_stdio()?;
+    {
+        let mut echo = Command::new("echo");
+        echo.arg("to a child").stdout(stdout);
+        assert!(echo.status()?.success());
+    }
+    guard.close()?;
+    let mut s = String::new();
+    reader.read_to_string(&mut s)?;
+    assert_eq!(s, "to a child\n");
+
+    // Streams convert with `TryFrom` too, leaving any thread or child
+    // process to carry on by itself.
+    let (reader, mut writer) = os_pipe::pipe()?;
+    writer.write_all(b"from a pipe")?;
+    drop(writer);
+    let output = Command::new("cat")
+        .stdin(Stdio::try_from(ReadHandle::pipe_reader(reader))?)
+        .output()?;
+    assert_eq!(output.stdout, b"from a pipe");
+    let input = ReadHandle::piped_thread(Box::new(&b"from a thread"[..]))?;
+    let output = Command::new("cat")
+        .stdin(Stdio::try_from(input)?)
+        .output()?;
+    assert_eq!(output.stdout, b"from a thread");
+    let mut echo = Command::new("echo");
+    echo.arg("from a child");
+    let input = ReadHandle::read_from_command(echo)?;
+    let output = Command::new("cat")
+        .stdin(Stdio::try_from(input)?)
+        .output()?;
+    assert_eq!(output.stdout, b"from a child\n");
+    Ok(())
+}
+
+#[test]
+fn test_pipeline() -> anyhow::Result<()> {
+    use io_handles::Pipeline;
+    use std::sync::{Arc, Mutex};
+
+    let mut tr = Command::new("tr");
+    tr.arg("a-z").arg("A-Z");
+    let mut output = Pipeline::new()
+        .stdin(ReadHandle::str("hello\nworld\n")?)
+        .command(Command::new("cat"))
+        .command(tr)
+        .command(Command::new("sort"))
+        .read()?;
+    let mut s = String::new();
+    output.read_to_string(&mut s)?;
+    assert_eq!(s, "HELLO\nWORLD\n");
+    let statuses = output.wait_all()?;
+    assert_eq!(statuses.len(), 3);
+    assert!(statuses.iter().all(|status| status.success()));
+
+    // The status of the last failing stage is reported, like `pipefail`.
+    let mut exit = Command::new("sh");
+    exit.arg("-c").arg("exit 3");
+    let mut output = Pipeline::new()
+        .command(Command::new("false"))
+        .command(exit)
+        .command(Command::new("cat"))
+        .read()?;
+    output.read_to_string(&mut s)?;
+    assert_eq!(output.wait()?.code(), Some(3));
+    let mut exit = Command::new("sh");
+    exit.arg("-c").arg("exit 3");
+    let mut output = Pipeline::new()
+        .command(Command::new("false"))
+        .command(exit)
+        .command(Command::new("cat"))
+        .read()?;
+    output.read_to_string(&mut s)?;
+    let codes = output
+        .wait_all()?
+        .iter()
+        .map(|status| status.code())
+        .collect::<Vec<_>>();
+    assert_eq!(codes, [Some(1), Some(3), Some(0)]);
+
+    let mut output = Pipeline::new()
+        .command(Command::new("false"))
+        .command(Command::new("cat"))
+        .read()?;
+    output.set_check_exit_status(true)?;
+    assert!(output.read_to_string(&mut s).is_err());
+
+    // Drain the tail into a `WriteHandle`.
+    let (mut reader, writer) = os_pipe::pipe()?;
+    let mut echo = Command::new("echo");
+    echo.arg("drained");
+    let statuses = Pipeline::new()
+        .command(echo)
+        .command(Command::new("cat"))
+        .write_to(WriteHandle::pipe_writer(writer))?;
+    assert_eq!(statuses.len(), 2);
+    let mut s = String::new();
+    reader.read_to_string(&mut s)?;
+    assert_eq!(s, "drained\n");
+
+    // A piped thread has consumed all the output by the time the statuses
+    // are returned, and its errors are reported.
+    #[derive(Clone, Default)]
+    struct Sink(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Sink {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let sink = Sink::default();
+    let mut echo = Command::new("echo");
+    echo.arg("to a thread");
+    Pipeline::new()
+        .command(echo)
+        .write_to(WriteHandle::piped_thread(Box::new(sink.clone()))?)?;
+    assert_eq!(*sink.0.lock().unwrap(), b"to a thread\n");
+
+    struct Failing;
+
+    impl Write for Failing {
+        fn write(&mut self, _buf: &[u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::other("failing"))
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let mut echo = Command::new("echo");
+    echo.arg("lost");
+    let err = Pipeline::new()
+        .command(echo)
+        .write_to(WriteHandle::piped_thread(Box::new(Failing))?)
+        .unwrap_err();
+    assert_eq!(err.to_string(), "failing");
+
+    // So are those of a child process feeding the pipeline.
+    let mut sh = Command::new("sh");
+    sh.arg("-c").arg("echo fed; exit 3");
+    let mut input = ReadHandle::read_from_command(sh)?;
+    input.set_check_exit_status(true)?;
+    let mut output = Pipeline::new()
+        .stdin(input)
+        .command(Command::new("cat"))
+        .read()?;
+    let mut s = String::new();
+    output.read_to_string(&mut s)?;
+    assert_eq!(s, "fed\n");
+    assert!(output.wait_all().is_err());
+
+    // A pipeline's stream doesn't convert into a single `Child`.
+    let output = Pipeline::new()
+        .command(Command::new("true"))
+        .command(Command::new("true"))
+        .read()?;
+    let output = output.into_child().unwrap_err();
+    output.close()?;
+
+    // When a stage fails to spawn, the child process feeding the pipeline
+    // is waited for before the error is returned.
+    let dir = tempfile::tempdir()?;
+    let marker = dir.path().join("marker");
+    let mut sh = Command::new("sh");
+    sh.arg("-c").arg("yes; touch \"$1\"").arg("sh").arg(&marker);
+    let err = Pipeline::new()
+        .stdin(ReadHandle::read_from_command(sh)?)
+        .command(Command::new("cat"))
+        .command(Command::new("/nonexistent/command"))
+        .read()
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+    assert!(marker.exists());
+
+    assert_eq!(
+        Pipeline::new().read().unwrap_err().kind(),
+        ErrorKind::InvalidInput
+    );
+    Ok(())
+}
+
+#[test]
+fn test_stderr_mode() -> anyhow::Result<()> {
+    use io_handles::StderrMode;
+
+    let noisy = || {
+        let mut command = Command::new("sh");
+        command.arg("-c").arg("echo out; echo err >&2");
+        command
+    };
+
+    let mut input = ReadHandle::read_from_command_with_stderr(noisy(), StderrMode::Null)?;
+    assert!(input.take_stderr().is_none());
+    let mut s = String::new();
+    input.read_to_string(&mut s)?;
+    assert_eq!(s, "out\n");
+
+    let mut input = ReadHandle::read_from_command_with_stderr(noisy(), StderrMode::Merge)?;
+    let mut s = String::new();
+    input.read_to_string(&mut s)?;
+    assert_eq!(s, "out\nerr\n");
+    assert!(input.wait()?.success());
+
+    let mut input = ReadHandle::read_from_command_with_stderr(noisy(), StderrMode::Capture)?;
+    let mut errors = input.take_stderr().unwrap();
+    assert!(input.take_stderr().is_none());
+    let mut s = String::new();
+    input.read_to_string(&mut s)?;
+    assert_eq!(s, "out\n");
+    let mut s = String::new();
+    errors.read_to_string(&mut s)?;
+    assert_eq!(s, "err\n");
+
+    let mut command = Command::new("sh");
+    command.arg("-c").arg("read line; echo \"got $line\" >&2");
+    let mut interact =
+        ReadWriteHandle::interact_with_command_with_stderr(command, StderrMode::Merge)?;
+    writeln!(interact, "ping")?;
+    let mut s = String::new();
+    interact.read_to_string(&mut s)?;
+    assert_eq!(s, "got ping\n");
+
+    let mut command = Command::new("sh");
+    command.arg("-c").arg("cat >&2");
+    let mut output = WriteHandle::write_to_command_with_stderr(command, StderrMode::Capture)?;
+    let mut errors = output.take_stderr().unwrap();
+    write!(output, "diagnostics")?;
+    assert!(output.wait()?.success());
+    let mut s = String::new();
+    errors.read_to_string(&mut s)?;
+    assert_eq!(s, "diagnostics");
+    Ok(())
+}
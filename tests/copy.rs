@@ -0,0 +1,128 @@
+use io_handles::{copy, ReadHandle, WriteHandle};
+use std::{
+    fs::File,
+    io::{self, Read, Seek, SeekFrom, Write},
+    sync::{Arc, Mutex},
+    thread,
+};
+
+fn contents() -> Vec<u8> {
+    (0..300_000_u32).map(|i| (i % 251) as u8).collect()
+}
+
+fn source_file(data: &[u8]) -> anyhow::Result<File> {
+    let mut file = tempfile::tempfile()?;
+    file.write_all(data)?;
+    file.seek(SeekFrom::Start(0))?;
+    Ok(file)
+}
+
+/// A `Write` which appends to a shared buffer.
+#[derive(Clone, Default)]
+struct SharedVec(Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedVec {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Count the `write`-family system calls made by the current thread, to
+/// tell a copy within the kernel from a read/write loop.
+#[cfg(target_os = "linux")]
+fn write_syscalls() -> anyhow::Result<u64> {
+    let io = std::fs::read_to_string("/proc/thread-self/io")?;
+    let line = io.lines().find(|line| line.starts_with("syscw:")).unwrap();
+    Ok(line["syscw:".len()..].trim().parse()?)
+}
+
+#[test]
+fn test_copy_file_to_file() -> anyhow::Result<()> {
+    let data = contents();
+    let mut input = ReadHandle::file(source_file(&data)?);
+    let mut dest = tempfile::tempfile()?;
+    let mut output = WriteHandle::file(dest.try_clone()?);
+
+    assert_eq!(copy(&mut input, &mut output)?, data.len() as u64);
+    drop(output);
+
+    let mut buf = Vec::new();
+    dest.seek(SeekFrom::Start(0))?;
+    dest.read_to_end(&mut buf)?;
+    assert!(buf == data);
+    Ok(())
+}
+
+#[test]
+#[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+fn test_copy_file_to_pipe() -> anyhow::Result<()> {
+    let data = contents();
+    let mut input = ReadHandle::file(source_file(&data)?);
+    let (reader, writer) = os_pipe::pipe()?;
+    let mut output = WriteHandle::pipe_writer(writer);
+
+    let reading = thread::spawn(move || -> std::io::Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        ReadHandle::pipe_reader(reader).read_to_end(&mut buf)?;
+        Ok(buf)
+    });
+    assert_eq!(copy(&mut input, &mut output)?, data.len() as u64);
+    drop(output);
+
+    assert!(reading.join().unwrap()? == data);
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn test_copy_pipe_to_socket() -> anyhow::Result<()> {
+    use std::os::unix::net::UnixStream;
+
+    let data = contents();
+    let (reader, mut writer) = os_pipe::pipe()?;
+    let mut input = ReadHandle::pipe_reader(reader);
+    let (a, mut b) = UnixStream::pair()?;
+    let mut output = WriteHandle::unix_stream(a);
+
+    let writing = {
+        let data = data.clone();
+        thread::spawn(move || writer.write_all(&data))
+    };
+    let reading = thread::spawn(move || -> std::io::Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        b.read_to_end(&mut buf)?;
+        Ok(buf)
+    });
+    assert_eq!(copy(&mut input, &mut output)?, data.len() as u64);
+    drop(output);
+
+    writing.join().unwrap()?;
+    assert!(reading.join().unwrap()? == data);
+    Ok(())
+}
+
+#[test]
+#[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+fn test_copy_file_to_piped_thread() -> anyhow::Result<()> {
+    let data = contents();
+    let mut input = ReadHandle::file(source_file(&data)?);
+    let sink = SharedVec::default();
+    let mut output = WriteHandle::piped_thread(Box::new(sink.clone()))?;
+
+    #[cfg(target_os = "linux")]
+    let before = write_syscalls()?;
+    assert_eq!(copy(&mut input, &mut output)?, data.len() as u64);
+    // On Linux, the data is spliced into the thread's pipe within the
+    // kernel, rather than written from this thread.
+    #[cfg(target_os = "linux")]
+    assert_eq!(write_syscalls()?, before);
+
+    // Flushing waits for everything copied to reach the boxed writer.
+    output.flush()?;
+    assert!(*sink.0.lock().unwrap() == data);
+    Ok(())
+}
@@ -0,0 +1,63 @@
+#![cfg(not(windows))]
+
+use io_handles::{poll, PollFd, ReadHandle, ReadWriteHandle, WriteHandle};
+use std::{
+    io::{ErrorKind, Read, Write},
+    os::unix::net::UnixStream,
+    time::Duration,
+};
+
+#[test]
+fn test_nonblocking() -> anyhow::Result<()> {
+    let (reader, writer) = os_pipe::pipe()?;
+    let mut input = ReadHandle::pipe_reader(reader);
+    let mut output = WriteHandle::pipe_writer(writer);
+    input.set_nonblocking(true)?;
+
+    let mut buf = [0_u8; 8];
+    assert_eq!(
+        input.read(&mut buf).unwrap_err().kind(),
+        ErrorKind::WouldBlock
+    );
+
+    output.write_all(b"hello")?;
+    assert_eq!(input.read(&mut buf)?, 5);
+    assert_eq!(&buf[..5], b"hello");
+
+    input.set_nonblocking(false)?;
+    drop(output);
+    assert_eq!(input.read(&mut buf)?, 0);
+    Ok(())
+}
+
+#[test]
+fn test_poll() -> anyhow::Result<()> {
+    let (reader, writer) = os_pipe::pipe()?;
+    let input = ReadHandle::pipe_reader(reader);
+    let mut output = WriteHandle::pipe_writer(writer);
+    let (a, _b) = UnixStream::pair()?;
+    let interact = ReadWriteHandle::unix_stream(a);
+
+    let mut fds = [PollFd::readable(&input), PollFd::read_side(&interact)];
+    assert_eq!(poll(&mut fds, Some(Duration::from_millis(10)))?, 0);
+    assert!(!fds[0].is_ready());
+    assert!(!fds[1].is_ready());
+
+    output.write_all(b"hello")?;
+    let mut fds = [
+        PollFd::readable(&input),
+        PollFd::writable(&output),
+        PollFd::read_side(&interact),
+        PollFd::write_side(&interact),
+    ];
+    assert_eq!(poll(&mut fds, None)?, 3);
+    assert!(fds[0].is_ready());
+    assert!(fds[1].is_ready());
+    assert!(!fds[2].is_ready());
+    assert!(fds[3].is_ready());
+
+    drop(output);
+    let mut fds = [PollFd::readable(&input)];
+    assert_eq!(poll(&mut fds, None)?, 1);
+    Ok(())
+}
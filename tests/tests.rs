@@ -1,6 +1,6 @@
-use io_handles::{ReadHandle, WriteHandle};
+use io_handles::{HandleKind, ReadHandle, ReadWriteHandle, WriteHandle};
 use std::{
-    fs::{remove_file, File},
+    fs::{self, remove_file, File},
     io::{copy, Read, Write},
 };
 use tempfile::{tempdir, TempDir};
@@ -118,3 +118,637 @@ fn test_null() -> anyhow::Result<()> {
     output.flush()?;
     Ok(())
 }
+
+#[test]
+fn test_stderr() -> anyhow::Result<()> {
+    let mut output = WriteHandle::stderr()?;
+    assert!(WriteHandle::stderr().is_err());
+    assert!(ReadWriteHandle::stdin_stderr().is_err());
+    output.write_all(b"")?;
+    output.flush()?;
+    drop(output);
+
+    let interact = ReadWriteHandle::stdin_stderr()?;
+    assert!(WriteHandle::stderr().is_err());
+    drop(interact);
+
+    WriteHandle::stderr()?;
+    Ok(())
+}
+
+#[test]
+#[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+fn test_tee() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let log = dir.path().join("log");
+    let sink = WriteHandle::file(File::create(&log)?);
+    let (reader, mut writer) = os_pipe::pipe()?;
+    let mut input = ReadHandle::pipe_reader(reader).tee(vec![sink])?;
+
+    writer.write_all(b"hello, tee")?;
+    drop(writer);
+    let mut s = String::new();
+    input.read_to_string(&mut s)?;
+    drop(input);
+
+    assert_eq!(s, "hello, tee");
+    assert_eq!(fs::read_to_string(&log)?, "hello, tee");
+    Ok(())
+}
+
+#[test]
+#[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+fn test_tee_sink_error() -> anyhow::Result<()> {
+    struct FailingFlush;
+
+    impl Write for FailingFlush {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Err(std::io::Error::other("flush failed"))
+        }
+    }
+
+    let sink = WriteHandle::piped_thread(Box::new(FailingFlush))?;
+    let (reader, mut writer) = os_pipe::pipe()?;
+    let mut input = ReadHandle::pipe_reader(reader).tee(vec![sink])?;
+
+    writer.write_all(b"hello, tee")?;
+    drop(writer);
+    let mut s = String::new();
+    let err = input.read_to_string(&mut s).unwrap_err();
+    assert_eq!(err.to_string(), "flush failed");
+    assert_eq!(s, "hello, tee");
+    Ok(())
+}
+
+#[test]
+#[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+fn test_piped_thread_flush() -> anyhow::Result<()> {
+    use std::sync::{Arc, Mutex};
+
+    /// A writer which only makes its data visible when flushed.
+    #[derive(Clone, Default)]
+    struct Shared {
+        pending: Arc<Mutex<Vec<u8>>>,
+        flushed: Arc<Mutex<Vec<u8>>>,
+    }
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.pending.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            let mut pending = self.pending.lock().unwrap();
+            self.flushed.lock().unwrap().append(&mut pending);
+            Ok(())
+        }
+    }
+
+    let shared = Shared::default();
+    let mut output = WriteHandle::piped_thread(Box::new(shared.clone()))?;
+    for i in 0..100 {
+        writeln!(output, "line {}", i)?;
+        output.flush()?;
+        let expected = format!("line {}\n", i);
+        assert!(shared
+            .flushed
+            .lock()
+            .unwrap()
+            .ends_with(expected.as_bytes()));
+    }
+
+    let big = vec![b'x'; 1 << 20];
+    output.write_all(&big)?;
+    output.flush()?;
+    assert!(shared.flushed.lock().unwrap().ends_with(&big));
+    assert!(shared.pending.lock().unwrap().is_empty());
+    Ok(())
+}
+
+#[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+struct Failing;
+
+#[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+impl Read for Failing {
+    fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+        Err(std::io::Error::other("failing reader"))
+    }
+}
+
+#[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+impl Write for Failing {
+    fn write(&mut self, _buf: &[u8]) -> std::io::Result<usize> {
+        Err(std::io::Error::other("failing writer"))
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+#[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+fn test_piped_thread_errors() -> anyhow::Result<()> {
+    // The thread's error is reported at the end of the stream.
+    let mut input = ReadHandle::piped_thread(Box::new(Failing))?;
+    let mut buf = Vec::new();
+    let err = input.read_to_end(&mut buf).unwrap_err();
+    assert_eq!(err.to_string(), "failing reader");
+    assert_eq!(input.read(&mut [0_u8; 8])?, 0);
+    input.close()?;
+
+    // Errors are reported by `close`, and dropping doesn't panic.
+    let mut output = WriteHandle::piped_thread(Box::new(Failing))?;
+    output.write_all(b"hello")?;
+    assert_eq!(output.close().unwrap_err().to_string(), "failing writer");
+    let mut output = WriteHandle::piped_thread(Box::new(Failing))?;
+    output.write_all(b"hello")?;
+    drop(output);
+
+    // Once the thread has exited, writes report its error.
+    let mut output = WriteHandle::piped_thread(Box::new(Failing))?;
+    let err = loop {
+        if let Err(e) = output.write_all(b"hello") {
+            break e;
+        }
+    };
+    assert_eq!(err.to_string(), "failing writer");
+    assert!(output.write_all(b"hello").is_err());
+    output.close()?;
+    Ok(())
+}
+
+#[test]
+fn test_seek() -> anyhow::Result<()> {
+    use std::io::{ErrorKind, Seek, SeekFrom};
+
+    let dir = tempdir()?;
+    let path = dir.path().join("file");
+    let mut output = WriteHandle::file(File::create(&path)?);
+    assert!(output.is_seekable());
+    output.write_all(b"hello, world")?;
+    output.seek(SeekFrom::Start(7))?;
+    output.write_all(b"WORLD")?;
+    drop(output);
+
+    let mut input = ReadHandle::file(File::open(&path)?);
+    assert!(input.is_seekable());
+    let mut s = String::new();
+    input.read_to_string(&mut s)?;
+    assert_eq!(s, "hello, WORLD");
+    assert_eq!(input.seek(SeekFrom::Current(-5))?, 7);
+    s.clear();
+    input.read_to_string(&mut s)?;
+    assert_eq!(s, "WORLD");
+
+    #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+    {
+        let mut input = ReadHandle::str("hello")?;
+        assert!(!input.is_seekable());
+        assert_eq!(
+            input.seek(SeekFrom::Start(0)).unwrap_err().kind(),
+            ErrorKind::Unsupported
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn test_positional() -> anyhow::Result<()> {
+    use std::{
+        io::{IoSlice, IoSliceMut},
+        sync::Arc,
+        thread,
+    };
+
+    let dir = tempdir()?;
+    let path = dir.path().join("file");
+    let output = WriteHandle::file(File::create(&path)?);
+    assert_eq!(output.write_at(b"0123456789", 0)?, 10);
+    assert_eq!(output.write_at(b"abc", 3)?, 3);
+    let bufs = [IoSlice::new(b"x"), IoSlice::new(b"yz")];
+    let n = output.write_vectored_at(&bufs, 7)?;
+    if n < 3 {
+        output.write_at(&b"xyz"[n..], 7 + n as u64)?;
+    }
+    drop(output);
+
+    let input = Arc::new(ReadHandle::file(File::open(&path)?));
+    let threads = (0..4_u64)
+        .map(|i| {
+            let input = Arc::clone(&input);
+            thread::spawn(move || {
+                let mut buf = [0_u8; 2];
+                input.read_at(&mut buf, i * 2).map(|n| (n, buf))
+            })
+        })
+        .collect::<Vec<_>>();
+    let mut all = Vec::new();
+    for t in threads {
+        let (n, buf) = t.join().unwrap()?;
+        assert_eq!(n, 2);
+        all.extend_from_slice(&buf);
+    }
+    assert_eq!(all, b"012abc6x");
+
+    let (mut a, mut b) = ([0_u8; 1], [0_u8; 8]);
+    let n = input.read_vectored_at(&mut [IoSliceMut::new(&mut a), IoSliceMut::new(&mut b)], 7)?;
+    assert!(n >= 1);
+    assert_eq!(&a, b"x");
+    if n == 3 {
+        assert_eq!(&b[..2], b"yz");
+    }
+
+    // The cursor didn't move. On Windows, positional I/O moves the cursor.
+    #[cfg(not(windows))]
+    {
+        let mut input = Arc::try_unwrap(input).unwrap();
+        let mut s = String::new();
+        input.read_to_string(&mut s)?;
+        assert_eq!(s, "012abc6xyz");
+    }
+
+    #[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+    {
+        let input = ReadHandle::str("hello")?;
+        assert_eq!(
+            input.read_at(&mut [0_u8; 2], 0).unwrap_err().kind(),
+            std::io::ErrorKind::Unsupported
+        );
+    }
+    Ok(())
+}
+
+#[test]
+#[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+fn test_read_write_piped_thread() -> anyhow::Result<()> {
+    use io_handles::ReadWrite;
+    use std::{
+        collections::VecDeque,
+        io::{BufRead, BufReader, ErrorKind},
+    };
+
+    /// Echoes what's written to it in upper case, until it sees "quit".
+    #[derive(Default)]
+    struct Shouter {
+        pending: VecDeque<u8>,
+        quit: bool,
+    }
+
+    impl Read for Shouter {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if self.pending.is_empty() {
+                if self.quit {
+                    return Ok(0);
+                }
+                return Err(ErrorKind::WouldBlock.into());
+            }
+            let size = buf.len().min(self.pending.len());
+            for (dst, src) in buf.iter_mut().zip(self.pending.drain(..size)) {
+                *dst = src;
+            }
+            Ok(size)
+        }
+    }
+
+    impl Write for Shouter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            if buf.starts_with(b"quit") {
+                self.quit = true;
+            } else {
+                self.pending.extend(buf.iter().map(u8::to_ascii_uppercase));
+            }
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl ReadWrite for Shouter {}
+
+    let mut interact = BufReader::new(ReadWriteHandle::piped_thread(Box::new(Shouter::default()))?);
+    let mut line = String::new();
+    for word in &["hello\n", "world\n"] {
+        interact.get_mut().write_all(word.as_bytes())?;
+        line.clear();
+        interact.read_line(&mut line)?;
+        assert_eq!(line, word.to_uppercase());
+    }
+    interact.get_mut().write_all(b"quit")?;
+    line.clear();
+    assert_eq!(interact.read_line(&mut line)?, 0);
+    interact.into_inner().close()?;
+
+    // Errors from the `ReadWrite` are reported by `close`.
+    struct Broken;
+
+    impl Read for Broken {
+        fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::other("broken"))
+        }
+    }
+
+    impl Write for Broken {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl ReadWrite for Broken {}
+
+    let mut interact = ReadWriteHandle::piped_thread(Box::new(Broken))?;
+    assert_eq!(interact.read(&mut [0_u8; 8])?, 0);
+    assert_eq!(interact.close().unwrap_err().to_string(), "broken");
+    Ok(())
+}
+
+#[test]
+#[cfg(not(target_os = "wasi"))] // WASI doesn't support pipes yet
+fn test_read_write_piped_thread_blocking() -> anyhow::Result<()> {
+    use io_handles::ReadWrite;
+    use std::{
+        sync::{mpsc, Arc, Mutex},
+        thread,
+        time::Duration,
+    };
+
+    /// Blocks in `read` until it's released, and then reaches its end.
+    /// Blocking `ReadWrite`s aren't supported, but they don't hang dropping
+    /// the stream, or closing it once the `read` returns.
+    struct Blocking {
+        entered: mpsc::Sender<()>,
+        release: mpsc::Receiver<()>,
+        written: Arc<Mutex<Vec<u8>>>,
+    }
+
+    impl Read for Blocking {
+        fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+            let _ = self.entered.send(());
+            let _ = self.release.recv();
+            Ok(0)
+        }
+    }
+
+    impl Write for Blocking {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.written.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl ReadWrite for Blocking {}
+
+    // Dropping the stream doesn't wait for a blocked `read`.
+    let (entered, entered_receiver) = mpsc::channel();
+    let (release, receiver) = mpsc::channel();
+    let mut interact = ReadWriteHandle::piped_thread(Box::new(Blocking {
+        entered,
+        release: receiver,
+        written: Arc::default(),
+    }))?;
+    interact.write_all(b"dropped")?;
+    entered_receiver.recv()?;
+    drop(interact);
+    release.send(())?;
+
+    // Closing waits for it to return, and then for everything written to
+    // the stream to reach the `ReadWrite`.
+    let (entered, entered_receiver) = mpsc::channel();
+    let (release, receiver) = mpsc::channel();
+    let written = Arc::new(Mutex::new(Vec::new()));
+    let mut interact = ReadWriteHandle::piped_thread(Box::new(Blocking {
+        entered,
+        release: receiver,
+        written: Arc::clone(&written),
+    }))?;
+    interact.write_all(b"hello")?;
+    entered_receiver.recv()?;
+    let releasing = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        release.send(())
+    });
+    interact.close()?;
+    releasing.join().unwrap()?;
+    assert_eq!(*written.lock().unwrap(), b"hello");
+    Ok(())
+}
+
+#[test]
+#[cfg(not(target_os = "wasi"))]
+fn test_pair() -> anyhow::Result<()> {
+    let (mut a, mut b) = ReadWriteHandle::pair()?;
+    a.write_all(b"ping")?;
+    let mut buf = [0_u8; 4];
+    b.read_exact(&mut buf)?;
+    assert_eq!(&buf, b"ping");
+    b.write_all(b"pong")?;
+    a.read_exact(&mut buf)?;
+    assert_eq!(&buf, b"pong");
+
+    drop(a);
+    assert_eq!(b.read(&mut buf)?, 0);
+    Ok(())
+}
+
+#[test]
+#[cfg(not(target_os = "wasi"))]
+fn test_kind() -> anyhow::Result<()> {
+    let dir = tmpdir();
+    let path = dir.path().join("kind.txt");
+
+    let writer = WriteHandle::file(File::create(&path)?);
+    assert_eq!(writer.kind(), HandleKind::File);
+    assert!(writer.metadata()?.is_file());
+    assert!(format!("{:?}", writer).contains("kind: File"));
+
+    let (pipe_reader, _pipe_writer) = os_pipe::pipe()?;
+    let reader = ReadHandle::pipe_reader(pipe_reader);
+    assert_eq!(reader.kind(), HandleKind::Pipe);
+    assert!(!reader.metadata()?.is_file());
+
+    let reader = ReadHandle::piped_thread(Box::new(std::io::empty()))?;
+    assert_eq!(reader.kind(), HandleKind::PipedThread);
+    let (a, _b) = ReadWriteHandle::pair()?;
+    assert!(!a.read_metadata()?.is_file());
+    assert!(!a.write_metadata()?.is_file());
+    #[cfg(unix)]
+    assert_eq!(a.kind(), HandleKind::UnixStream);
+    #[cfg(windows)]
+    assert_eq!(a.kind(), HandleKind::Pipe);
+    Ok(())
+}
+
+#[test]
+fn test_into_resources() -> anyhow::Result<()> {
+    let dir = tmpdir();
+    let path = dir.path().join("into.txt");
+
+    let mut writer = WriteHandle::file(File::create(&path)?);
+    write!(writer, "hello")?;
+    let mut file = writer.into_file().unwrap();
+    write!(file, ", world")?;
+    drop(file);
+
+    let reader = ReadHandle::file(File::open(&path)?);
+    let reader = reader.into_tcp_stream().unwrap_err();
+    let mut s = String::new();
+    reader.into_file().unwrap().read_to_string(&mut s)?;
+    assert_eq!(s, "hello, world");
+    Ok(())
+}
+
+#[test]
+#[cfg(not(target_os = "wasi"))]
+fn test_split() -> anyhow::Result<()> {
+    let (a, mut b) = ReadWriteHandle::pair()?;
+    let (mut reader, mut writer) = a.into_split();
+    let thread = std::thread::spawn(move || -> std::io::Result<WriteHandle> {
+        writer.write_all(b"ping")?;
+        Ok(writer)
+    });
+    let mut buf = [0_u8; 4];
+    b.read_exact(&mut buf)?;
+    assert_eq!(&buf, b"ping");
+    b.write_all(b"pong")?;
+    reader.read_exact(&mut buf)?;
+    assert_eq!(&buf, b"pong");
+
+    let writer = thread.join().unwrap()?;
+    let (other, _) = ReadWriteHandle::pair()?;
+    let (_, other_writer) = other.into_split();
+    let (reader, _) = ReadWriteHandle::reunite(reader, other_writer).unwrap_err();
+    let mut a = ReadWriteHandle::reunite(reader, writer).unwrap();
+    a.write_all(b"ping")?;
+    b.read_exact(&mut buf)?;
+    assert_eq!(&buf, b"ping");
+    Ok(())
+}
+
+#[test]
+#[cfg(not(target_os = "wasi"))]
+fn test_shutdown_write() -> anyhow::Result<()> {
+    let (mut a, mut b) = ReadWriteHandle::pair()?;
+    a.write_all(b"ping")?;
+    a.shutdown_write()?;
+    let mut s = String::new();
+    b.read_to_string(&mut s)?;
+    assert_eq!(s, "ping");
+    b.write_all(b"pong")?;
+    let mut buf = [0_u8; 4];
+    a.read_exact(&mut buf)?;
+    assert_eq!(&buf, b"pong");
+
+    let (reader, mut output) = os_pipe::pipe()?;
+    let (mut input, writer) = os_pipe::pipe()?;
+    let mut interact = ReadWriteHandle::pipe_reader_writer(reader, writer);
+    interact.shutdown_write()?;
+    let mut s = String::new();
+    input.read_to_string(&mut s)?;
+    assert_eq!(s, "");
+    assert_eq!(
+        interact.write(b"ping").unwrap_err().kind(),
+        std::io::ErrorKind::BrokenPipe
+    );
+    output.write_all(b"pong")?;
+    interact.read_exact(&mut buf)?;
+    assert_eq!(&buf, b"pong");
+    Ok(())
+}
+
+#[test]
+#[cfg(not(target_os = "wasi"))]
+fn test_try_clone() -> anyhow::Result<()> {
+    let (reader, writer) = os_pipe::pipe()?;
+    let mut writer = WriteHandle::pipe_writer(writer);
+    let mut logger = writer.try_clone()?;
+    assert_eq!(logger.kind(), HandleKind::Pipe);
+    writer.write_all(b"hello, ")?;
+    logger.write_all(b"world")?;
+    drop(writer);
+    drop(logger);
+    let mut s = String::new();
+    ReadHandle::pipe_reader(reader).read_to_string(&mut s)?;
+    assert_eq!(s, "hello, world");
+
+    let (a, mut b) = ReadWriteHandle::pair()?;
+    let mut c = a.try_clone()?;
+    drop(a);
+    c.write_all(b"ping")?;
+    let mut buf = [0_u8; 4];
+    b.read_exact(&mut buf)?;
+    assert_eq!(&buf, b"ping");
+
+    let (_, writer) = c.into_split();
+    writer.try_clone()?.write_all(b"pong")?;
+    b.read_exact(&mut buf)?;
+    assert_eq!(&buf, b"pong");
+
+    let thread = WriteHandle::piped_thread(Box::new(std::io::sink()))?;
+    assert_eq!(
+        thread.try_clone().unwrap_err().kind(),
+        std::io::ErrorKind::Unsupported
+    );
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn test_owned_fd() -> anyhow::Result<()> {
+    use io_handles::AsReadWriteFd;
+    use std::{
+        convert::TryFrom,
+        os::unix::io::{AsFd, AsRawFd, OwnedFd},
+    };
+
+    let (input, output) = os_pipe::pipe()?;
+    let writer = OwnedFd::try_from(WriteHandle::pipe_writer(output)).unwrap();
+    let mut writer = WriteHandle::from(writer);
+    assert_eq!(writer.kind(), HandleKind::Pipe);
+    let reader = ReadHandle::pipe_reader(input);
+    assert_eq!(reader.as_fd().as_raw_fd(), reader.as_raw_fd());
+    write!(writer, "hello")?;
+    drop(writer);
+
+    let fd = OwnedFd::try_from(reader).unwrap();
+    let mut reader = ReadHandle::from(fd);
+    assert_eq!(reader.kind(), HandleKind::Pipe);
+    let mut s = String::new();
+    reader.read_to_string(&mut s)?;
+    assert_eq!(s, "hello");
+
+    let file = ReadHandle::from(OwnedFd::from(tempfile::tempfile()?));
+    assert_eq!(file.kind(), HandleKind::File);
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    let tcp_stream = std::net::TcpStream::connect(listener.local_addr()?)?;
+    let tcp_stream = WriteHandle::from(OwnedFd::from(tcp_stream));
+    assert_eq!(tcp_stream.kind(), HandleKind::TcpStream);
+
+    let (a, mut b) = ReadWriteHandle::pair()?;
+    assert_eq!(a.as_read_fd().as_raw_fd(), a.as_write_fd().as_raw_fd());
+    let mut a = ReadWriteHandle::from(OwnedFd::try_from(a).unwrap());
+    assert_eq!(a.kind(), HandleKind::UnixStream);
+    a.write_all(b"ping")?;
+    let mut buf = [0_u8; 4];
+    b.read_exact(&mut buf)?;
+    assert_eq!(&buf, b"ping");
+
+    assert!(OwnedFd::try_from(ReadHandle::piped_thread(Box::new(std::io::empty()))?).is_err());
+    assert!(OwnedFd::try_from(WriteHandle::piped_thread(Box::new(Vec::new()))?).is_err());
+    Ok(())
+}
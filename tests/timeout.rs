@@ -0,0 +1,103 @@
+#![cfg(not(windows))]
+
+use io_handles::{ReadHandle, ReadWriteHandle, WriteHandle};
+use std::{
+    io::{self, ErrorKind, Read, Write},
+    os::unix::net::UnixStream,
+    thread,
+    time::Duration,
+};
+
+#[test]
+fn test_read_timeout() -> anyhow::Result<()> {
+    let (reader, writer) = os_pipe::pipe()?;
+    let mut input = ReadHandle::pipe_reader(reader);
+    let mut output = WriteHandle::pipe_writer(writer);
+    input.set_read_timeout(Some(Duration::from_millis(10)))?;
+    assert_eq!(input.read_timeout(), Some(Duration::from_millis(10)));
+
+    let mut buf = [0_u8; 8];
+    assert_eq!(
+        input.read(&mut buf).unwrap_err().kind(),
+        ErrorKind::TimedOut
+    );
+
+    output.write_all(b"hello")?;
+    assert_eq!(input.read(&mut buf)?, 5);
+    assert_eq!(&buf[..5], b"hello");
+
+    // `read_exact` applies the timeout to each underlying read.
+    output.write_all(b"abc")?;
+    assert_eq!(
+        input.read_exact(&mut buf).unwrap_err().kind(),
+        ErrorKind::TimedOut
+    );
+
+    drop(output);
+    let mut s = String::new();
+    input.read_to_string(&mut s)?;
+    assert_eq!(s, "");
+    Ok(())
+}
+
+#[test]
+fn test_write_timeout() -> anyhow::Result<()> {
+    let (a, b) = UnixStream::pair()?;
+    let mut interact = ReadWriteHandle::unix_stream(a);
+    interact.set_write_timeout(Some(Duration::from_millis(10)))?;
+    assert_eq!(interact.read_timeout(), None);
+
+    // Fill the socket buffer until the timeout fires.
+    let buf = vec![0_u8; 64 * 1024];
+    let err = loop {
+        if let Err(e) = interact.write(&buf) {
+            break e;
+        }
+    };
+    assert_eq!(err.kind(), ErrorKind::TimedOut);
+    assert_eq!(
+        interact.write_all(&buf).unwrap_err().kind(),
+        ErrorKind::TimedOut
+    );
+    drop(b);
+    Ok(())
+}
+
+#[test]
+fn test_write_timeout_pipe() -> anyhow::Result<()> {
+    let (mut reader, writer) = os_pipe::pipe()?;
+    let mut output = WriteHandle::pipe_writer(writer);
+    output.set_write_timeout(Some(Duration::from_millis(10)))?;
+
+    // A pipe is writable once it has room for `PIPE_BUF` bytes; a write
+    // larger than the pipe's buffer writes what fits instead of blocking.
+    let buf = vec![0_u8; 1024 * 1024];
+    let size = output.write(&buf)?;
+    assert!(size > 0 && size < buf.len());
+    assert_eq!(output.write(&buf).unwrap_err().kind(), ErrorKind::TimedOut);
+    assert_eq!(
+        output.write_all(&buf).unwrap_err().kind(),
+        ErrorKind::TimedOut
+    );
+
+    // The pipe is left in blocking mode.
+    output.set_write_timeout(None)?;
+    let drain = thread::spawn(move || io::copy(&mut reader, &mut io::sink()));
+    output.write_all(&buf)?;
+    drop(output);
+    assert!(drain.join().unwrap()? >= buf.len() as u64);
+    Ok(())
+}
+
+#[test]
+fn test_zero_timeout() {
+    let mut output = WriteHandle::stdout().unwrap();
+    assert_eq!(
+        output
+            .set_write_timeout(Some(Duration::from_secs(0)))
+            .unwrap_err()
+            .kind(),
+        ErrorKind::InvalidInput
+    );
+    assert_eq!(output.write_timeout(), None);
+}